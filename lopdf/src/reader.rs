@@ -15,7 +15,7 @@
 use crate::xref::XrefEntry;
 use crate::{Document, Error, IncrementalDocument, Object, ObjectId, Result};
 
-type FilterFunc = fn((u32, u16), &mut Object) -> Option<((u32, u16), Object)>;
+type FilterFunc<'f> = &'f (dyn Fn((u32, u16), &mut Object) -> Option<((u32, u16), Object)> + Sync);
 
 impl Document {
     /// Load a PDF document from a specified file path.
@@ -27,7 +27,7 @@ pub fn load<P: AsRef<Path>>(path: P) -> Result<Document> {
     }
 
     #[inline]
-    pub fn load_filtered<P: AsRef<Path>>(path: P, filter_func: FilterFunc) -> Result<Document> {
+    pub fn load_filtered<P: AsRef<Path>>(path: P, filter_func: FilterFunc<'_>) -> Result<Document> {
         let file = File::open(path)?;
         let capacity = Some(file.metadata()?.len() as usize);
         Self::load_internal(file, capacity, Some(filter_func))
@@ -40,7 +40,7 @@ pub fn load_from<R: Read>(source: R) -> Result<Document> {
     }
 
     fn load_internal<R: Read>(
-        mut source: R, capacity: Option<usize>, filter_func: Option<FilterFunc>,
+        mut source: R, capacity: Option<usize>, filter_func: Option<FilterFunc<'_>>,
     ) -> Result<Document> {
         let mut buffer = capacity.map(Vec::with_capacity).unwrap_or_default();
         source.read_to_end(&mut buffer)?;
@@ -128,7 +128,7 @@ pub struct Reader<'a> {
 
 impl<'a> Reader<'a> {
     /// Read whole document.
-    pub fn read(mut self, filter_func: Option<FilterFunc>) -> Result<Document> {
+    pub fn read(mut self, filter_func: Option<FilterFunc<'_>>) -> Result<Document> {
         // The document structure can be expressed in PEG as:
         //   document <- header indirect_object* xref trailer xref_start
         let version = parser::header(ParserInput::new_extra(self.buffer, "header")).ok_or(Error::Header)?;
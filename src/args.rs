@@ -0,0 +1,426 @@
+use std::env;
+use std::path::Path;
+
+use search::core::{Analyzer, COMMON_EXCLUDED_DIRS, DEFAULT_MAX_TOKEN_LEN, DEFAULT_MIN_DF, DEFAULT_PATH_WEIGHT, DEFAULT_MAX_TEXT_LEN, HASH_SEED_ENV, PER_DOCUMENT_BELOW, Milestones, Numbers};
+use search::query::Operator;
+use search::server::{DEFAULT_LIMIT, DEFAULT_TIMEOUT, MAX_LIMIT, UNIX_PREFIX, PathStyle, WhileIndexing, parse_header};
+use tiny_http::Header;
+
+pub const DEFAULT_PORT: &str = "6969";
+pub const PORT_ENV: &str = "PORT";
+pub const THREADS_ENV: &str = "SEARCH_THREADS";
+pub const LIMIT_ENV: &str = "SEARCH_LIMIT";
+pub const DEFAULT_PDF_TIMEOUT: u64 = 60;
+pub const STDIN_ROOT: &str = "-";
+
+pub struct Args {
+    pub dir_paths: Vec::<String>,
+    pub port: String,
+
+    // run a single query against the index and print the results instead of serving
+    pub query: Option::<String>,
+    // index once, then read queries from stdin until EOF
+    pub repl: bool,
+    // keep the index up to date with the directories and re-run the last query of `query` or `repl` on changes
+    pub watch: bool,
+    pub json: bool,
+    pub ndjson: bool,
+    pub no_color: bool,
+    // how the server reports the paths of the results
+    pub path_style: PathStyle,
+    // sent with every response of the server
+    pub headers: Vec::<Header>,
+    // the directory the server reads the files of its UI from on every request, instead of the embedded ones
+    pub assets: Option::<String>,
+    // results printed, and returned by the server to requests that don't ask for a number of them
+    pub limit: usize,
+    // milliseconds a search of the server may score before it answers with what it has, 0 means no limit
+    pub search_timeout: u64,
+    // serve right away and answer searches like this until the files are all indexed, `None` indexes them first
+    pub while_indexing: Option::<WhileIndexing>,
+    pub case_sensitive: bool,
+    pub split_compounds: bool,
+    pub keep_originals: bool,
+    // match the original words of the query instead of their stems, needs `keep_originals`
+    pub exact: bool,
+    // only files with these extensions are found by the queries of `--query`, `--repl` and `--watch`, every file when empty
+    pub extensions: Vec::<String>,
+    // what bare words of queries are combined with, also for the server's requests that don't set `operator`
+    pub operator: Operator,
+    // how much words made of digits only count in the scores
+    pub numbers: Numbers,
+    pub comments_only: bool,
+    // the first row of CSV and TSV files names the columns
+    pub csv_headers: bool,
+    // longest word that gets indexed, 0 means no limit
+    pub max_token_len: usize,
+    // occurrences every word of a file's path counts as in the file, 0 means the path is only searched with `path:`
+    pub path_weight: usize,
+    // terms fewer documents than this have are dropped once the directories are indexed
+    pub min_df: usize,
+    // how often the percentage of the files indexed so far is drawn
+    pub progress: Milestones,
+    // what words are stemmed with, in documents and in queries
+    pub analyzer: Analyzer,
+    // `english` or a file with a word per line, the words that are neither indexed nor searched for
+    pub stop_words: Option::<String>,
+    pub strict: bool,
+    // names of directories that are not walked into, on top of `COMMON_EXCLUDED_DIRS` with `exclude_common`
+    pub exclude_dirs: Vec::<String>,
+    pub exclude_common: bool,
+    // gitignore-style patterns of the paths below the directories that are not walked, see `search::ignore`
+    pub exclude: Vec::<String>,
+    pub gitignore: bool,
+    pub no_follow_symlinks: bool,
+    // levels of directories below the given ones that are walked, every one when `None`
+    pub max_depth: Option::<usize>,
+    // file the parsed texts are checkpointed to while indexing and resumed from on the next start
+    pub checkpoint: Option::<String>,
+    // file the index is saved to once it's built and loaded from on the next start
+    pub index: Option::<String>,
+
+    // replace the default PDF object types and dictionary keys dropped while loading PDFs
+    pub pdf_ignore: Option::<Vec::<String>>,
+    pub pdf_strip_keys: Option::<Vec::<String>>,
+    // seconds a single PDF may take to extract, 0 means no limit
+    pub pdf_timeout: u64,
+    // bytes of text a single file may extract to, 0 leaves it to the size limit of files
+    pub max_text_len: usize,
+
+    // size of the rayon pool used for walking, parsing (including PDF pages) and searching, 0 means rayon's default
+    pub threads: usize
+}
+
+impl Args {
+    #[inline]
+    pub fn usage(program: &str) -> String {
+        format!("usage: {program} <directories to search in..> [port to serve at] [options]\n\
+                 the port is the argument, otherwise `{PORT_ENV}`, otherwise {DEFAULT_PORT}. `{UNIX_PREFIX}<path>` serves at a unix socket\n\
+                 `{STDIN_ROOT}` as a directory reads a single document from stdin, `RUST_LOG` controls what gets logged\n\
+                 `{HASH_SEED_ENV}=<n>` hashes the same way every run, for comparing timings (build with `--features dbg`)\n\
+                 options:\n\
+                 \x20   --dir <directory>    search in <directory> as well, can be given more than once, with it a single\n\
+                 \x20                        positional argument is the port\n\
+                 \x20   -q, --query <query>  run a single query, print the results and exit\n\
+                 \x20   --repl               index once, then read queries from stdin until EOF\n\
+                 \x20   --watch              reindex files as they change, with `--query` or `--repl` the results of the last\n\
+                 \x20                        query are printed again, and the server searches the files as they are now\n\
+                 \x20   --json               print the results as JSON\n\
+                 \x20   --ndjson             print the results as JSON, one object per line\n\
+                 \x20   --no-color           do not colorize the results (also honors `NO_COLOR`)\n\
+                 \x20   --limit <n>          print at most <n> results, also what the server returns when a request\n\
+                 \x20                        doesn't set `limit`, up to {MAX_LIMIT} (default: `{LIMIT_ENV}` or {DEFAULT_LIMIT})\n\
+                 \x20   --search-timeout <ms>\n\
+                 \x20                        answer requests to the server whose search takes longer than <ms> with\n\
+                 \x20                        the best results found by then, 0 means no limit (default: {search_timeout})\n\
+                 \x20   --while-indexing <how>\n\
+                 \x20                        serve right away and answer searches from the files indexed so far,\n\
+                 \x20                        `partial` flags their results as such, `unavailable` or `<n>%` answer with\n\
+                 \x20                        503 until all or <n> percent of them are\n\
+                 \x20   --paths <style>      `relative` to the indexed directory or `absolute` paths in the server's\n\
+                 \x20                        results (default: relative)\n\
+                 \x20   --assets <dir>       read `query.html`, `script.js` and `styles.css` from <dir> on every request\n\
+                 \x20                        instead of serving the ones built in, for working on the UI (`--assets src`)\n\
+                 \x20   --header <header>    add `Name: value` to every response of the server, can be given more than\n\
+                 \x20                        once (`Cache-Control: no-store`, `X-Content-Type-Options: nosniff`)\n\
+                 \x20   --case-sensitive     do not lowercase words when indexing and searching\n\
+                 \x20   --split-compounds    also index the parts of hyphenated, snake_case and camelCase words\n\
+                 \x20   --keep-originals     also index the unstemmed words, so they can be matched exactly\n\
+                 \x20   --exact              match the unstemmed words of queries, implies `--keep-originals`\n\
+                 \x20   --ext <exts>         comma separated extensions of the only files queries find (`pdf,docx`)\n\
+                 \x20   --operator <op>      `or` finds documents with any word of a query, `and` only those with all of\n\
+                 \x20                        them, unless the query says `AND`/`OR` itself (default: or)\n\
+                 \x20   --numbers <policy>   how much numbers (years, ids) count in scores: `keep` them as any other word,\n\
+                 \x20                        `boost` them for logs, `demote` or `exclude` them for prose (default: keep)\n\
+                 \x20   --max-token-len <n>  drop words longer than <n> bytes, 0 means no limit (default: {DEFAULT_MAX_TOKEN_LEN})\n\
+                 \x20   --path-weight <n>    count every word of a file's path <n> times in the file, 0 leaves paths to\n\
+                 \x20                        `path:` queries (default: {DEFAULT_PATH_WEIGHT})\n\
+                 \x20   --min-df <n>         drop the words fewer than <n> files have once they are indexed, which saves\n\
+                 \x20                        memory on noisy files (scans, logs) at the cost of rare words (default: {DEFAULT_MIN_DF})\n\
+                 \x20   --progress <every>   draw the progress of indexing every `<n>%` or every `<n>` files, every file\n\
+                 \x20                        when there are fewer than {PER_DOCUMENT_BELOW} of them (default: 1%)\n\
+                 \x20   --analyzer <name>    `english` stems words, `unstemmed` keeps them as they are, which suits text in\n\
+                 \x20                        other languages better (default: english)\n\
+                 \x20   --stop-words <list>  neither index nor search for the words of <list>, `english` for common\n\
+                 \x20                        english words or a file with a word per line (default: none)\n\
+                 \x20   --comments-only      index only comments and docstrings of source code files\n\
+                 \x20   --csv-headers        take the first row of CSV and TSV files for the names of the columns, indexed\n\
+                 \x20                        as tags instead of as text\n\
+                 \x20   --max-text-len <n>   skip the files whose text is longer than <n> bytes once in memory, 0 leaves it\n\
+                 \x20                        to the limit of 1 GiB per file (default: {DEFAULT_MAX_TEXT_LEN})\n\
+                 \x20   --strict             fail instead of skipping directories that could not be read\n\
+                 \x20   --exclude-dir <names>\n\
+                 \x20                        comma separated names of directories to skip, along with everything in them\n\
+                 \x20   --exclude-common     skip version control, dependency and build directories:\n\
+                 \x20                        {common}\n\
+                 \x20   --exclude <glob>     skip the files and directories that match <glob>, the way `.gitignore` does,\n\
+                 \x20                        can be given more than once (`*.log`, `.git/`, `*/target/*`, `!keep.log`)\n\
+                 \x20   --gitignore          also skip what the `.gitignore` right in the directories ignores\n\
+                 \x20   --no-follow-symlinks skip symlinks instead of walking into the directories and files they point\n\
+                 \x20                        to, a directory is never walked twice either way\n\
+                 \x20   --max-depth <n>      walk at most <n> levels of directories below the given ones, 0 indexes only\n\
+                 \x20                        the files right in them\n\
+                 \x20   --checkpoint <file>  write the parsed files to <file> while indexing and take the ones that haven't\n\
+                 \x20                        changed from it on the next start instead of parsing them again\n\
+                 \x20   --index <file>       save the index to <file> once it's built and load it from there on the next\n\
+                 \x20                        start, only the files that changed since are parsed again\n\
+                 \x20   --pdf-ignore <types> comma separated PDF object types to drop while loading PDFs\n\
+                 \x20   --pdf-strip-keys <keys>\n\
+                 \x20                        comma separated keys to remove from PDF dictionaries, an empty list keeps\n\
+                 \x20                        everything, which is slower but may recover more text\n\
+                 \x20   --pdf-timeout <secs> skip the rest of a PDF whose extraction takes longer than <secs>,\n\
+                 \x20                        keeping the pages extracted so far, 0 means no limit (default: {DEFAULT_PDF_TIMEOUT})\n\
+                 \x20   --threads <n>        size of the thread pool used for indexing, PDF page extraction and\n\
+                 \x20                        searching (default: `{THREADS_ENV}` or the number of CPUs)", search_timeout = DEFAULT_TIMEOUT.as_millis(), common = COMMON_EXCLUDED_DIRS.join(", "))
+    }
+
+    pub fn parse() -> Result::<Args, String> {
+        let mut args = env::args();
+        let program = args.next().unwrap_or_else(|| "search".to_owned());
+
+        let mut query = None;
+        let mut repl = false;
+        let mut watch = false;
+        let mut json = false;
+        let mut ndjson = false;
+        let mut no_color = false;
+        let mut path_style = PathStyle::default();
+        let mut headers = Vec::new();
+        let mut assets = None;
+        let mut limit = None;
+        let mut search_timeout = DEFAULT_TIMEOUT.as_millis() as u64;
+        let mut while_indexing = None;
+        let mut case_sensitive = false;
+        let mut split_compounds = false;
+        let mut keep_originals = false;
+        let mut exact = false;
+        let mut operator = Operator::default();
+        let mut numbers = Numbers::default();
+        let mut comments_only = false;
+        let mut csv_headers = false;
+        let mut max_token_len = DEFAULT_MAX_TOKEN_LEN;
+        let mut path_weight = DEFAULT_PATH_WEIGHT;
+        let mut min_df = DEFAULT_MIN_DF;
+        let mut progress = Milestones::default();
+        let mut analyzer = Analyzer::default();
+        let mut stop_words = None;
+        let mut strict = false;
+        let mut exclude_dirs = Vec::new();
+        let mut extensions = Vec::new();
+        let mut exclude_common = false;
+        let mut exclude = Vec::new();
+        let mut gitignore = false;
+        let mut no_follow_symlinks = false;
+        let mut max_depth = None;
+        let mut checkpoint = None;
+        let mut index = None;
+        let mut threads = None;
+        let mut pdf_ignore = None;
+        let mut pdf_strip_keys = None;
+        let mut pdf_timeout = DEFAULT_PDF_TIMEOUT;
+        let mut max_text_len = DEFAULT_MAX_TEXT_LEN;
+        let mut positional = Vec::new();
+        let mut dirs = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-q" | "--query" => query = Some(value(&mut args, &arg, "a query")?),
+                "--repl" => repl = true,
+                "--watch" => watch = true,
+                "--json" => json = true,
+                "--ndjson" => ndjson = true,
+                "--no-color" => no_color = true,
+                "--paths" => {
+                    let style = value(&mut args, &arg, "`relative` or `absolute`")?;
+                    path_style = PathStyle::from_name(&style).ok_or_else(|| format!("`{style}` is not a path style, expected `relative` or `absolute`"))?
+                },
+                "--assets" => assets = Some(value(&mut args, &arg, "a directory")?),
+                "--header" => headers.push(parse_header(&value(&mut args, &arg, "`Name: value`")?)?),
+                "--limit" => {
+                    let n = value(&mut args, &arg, "a number")?;
+                    limit = Some(n.parse().map_err(|_| format!("`{n}` is not a valid limit"))?)
+                },
+                "--search-timeout" => {
+                    let n = value(&mut args, &arg, "a number of milliseconds")?;
+                    search_timeout = n.parse().map_err(|_| format!("`{n}` is not a valid timeout"))?
+                },
+                "--while-indexing" => {
+                    let how = value(&mut args, &arg, "`partial`, `unavailable` or `<n>%`")?;
+                    while_indexing = Some(WhileIndexing::parse(&how).ok_or_else(|| format!("`{how}` is not `partial`, `unavailable` or `<n>%`"))?)
+                },
+                "--case-sensitive" => case_sensitive = true,
+                "--split-compounds" => split_compounds = true,
+                "--keep-originals" => keep_originals = true,
+                "--exact" => exact = true,
+                "--ext" => extensions.extend(list(&value(&mut args, &arg, "a list of extensions")?)),
+                "--operator" => {
+                    let op = value(&mut args, &arg, "`and` or `or`")?;
+                    operator = Operator::from_name(&op).ok_or_else(|| format!("`{op}` is not an operator, expected `and` or `or`"))?
+                },
+                "--numbers" => {
+                    let policy = value(&mut args, &arg, "`keep`, `boost`, `demote` or `exclude`")?;
+                    numbers = Numbers::from_name(&policy).ok_or_else(|| format!("`{policy}` is not a policy for numbers, expected `keep`, `boost`, `demote` or `exclude`"))?
+                },
+                "--analyzer" => {
+                    let name = value(&mut args, &arg, "`english` or `unstemmed`")?;
+                    analyzer = Analyzer::from_name(&name).ok_or_else(|| format!("`{name}` is not an analyzer, expected `english` or `unstemmed`"))?
+                },
+                "--stop-words" => stop_words = Some(value(&mut args, &arg, "`english` or a file")?),
+                "--comments-only" => comments_only = true,
+                "--csv-headers" => csv_headers = true,
+                "--max-token-len" => {
+                    let n = value(&mut args, &arg, "a number")?;
+                    max_token_len = n.parse().map_err(|_| format!("`{n}` is not a valid token length"))?
+                },
+                "--path-weight" => {
+                    let n = value(&mut args, &arg, "a number")?;
+                    path_weight = n.parse().map_err(|_| format!("`{n}` is not a valid path weight"))?
+                },
+                "--min-df" => {
+                    let n = value(&mut args, &arg, "a number")?;
+                    min_df = n.parse().map_err(|_| format!("`{n}` is not a valid document frequency"))?
+                },
+                "--strict" => strict = true,
+                "--exclude-dir" => exclude_dirs.extend(list(&value(&mut args, &arg, "a list of directory names")?)),
+                "--exclude-common" => exclude_common = true,
+                "--exclude" => exclude.push(value(&mut args, &arg, "a glob")?),
+                "--gitignore" => gitignore = true,
+                "--no-follow-symlinks" => no_follow_symlinks = true,
+                "--max-depth" => {
+                    let n = value(&mut args, &arg, "a number")?;
+                    max_depth = Some(n.parse().map_err(|_| format!("`{n}` is not a valid depth"))?)
+                },
+                "--checkpoint" => checkpoint = Some(value(&mut args, &arg, "a file")?),
+                "--index" => index = Some(value(&mut args, &arg, "a file")?),
+                "--progress" => {
+                    let every = value(&mut args, &arg, "a percentage or a number of files")?;
+                    progress = Milestones::parse(&every).ok_or_else(|| format!("`{every}` is not a valid progress interval, expected `<n>%` or `<n>` files"))?
+                },
+                "--max-text-len" => {
+                    let n = value(&mut args, &arg, "a number")?;
+                    max_text_len = n.parse().map_err(|_| format!("`{n}` is not a valid text length"))?
+                },
+                "--threads" => {
+                    let n = value(&mut args, &arg, "a number")?;
+                    threads = Some(n.parse().map_err(|_| format!("`{n}` is not a valid number of threads"))?)
+                },
+                "--pdf-ignore" => pdf_ignore = Some(list(&value(&mut args, &arg, "a list of object types")?)),
+                "--pdf-strip-keys" => pdf_strip_keys = Some(list(&value(&mut args, &arg, "a list of keys")?)),
+                "--pdf-timeout" => {
+                    let n = value(&mut args, &arg, "a number of seconds")?;
+                    pdf_timeout = n.parse().map_err(|_| format!("`{n}` is not a valid timeout"))?
+                },
+                "--dir" => dirs.push(value(&mut args, &arg, "a directory")?),
+                _ if arg.starts_with("--") => return Err(format!("unknown flag `{arg}`")),
+                _ => positional.push(arg)
+            }
+        }
+
+        if repl && query.is_some() {
+            return Err("`--repl` and `--query` can not be used together".to_owned())
+        }
+
+        if while_indexing.is_some() && (repl || query.is_some()) {
+            return Err("`--while-indexing` is for the server, it can not be used with `--repl` or `--query`".to_owned())
+        }
+
+        if repl && positional.iter().chain(dirs.iter()).any(|p| p == STDIN_ROOT) {
+            return Err(format!("`--repl` reads queries from stdin, so it can not be used with `{STDIN_ROOT}`"))
+        }
+
+        // the last positional argument is the port when it's one, a number or a socket, and not a directory as well.
+        // with `--dir` a single one can be the port
+        let port = match positional.last() {
+            Some(last) if (positional.len() > 1 || !dirs.is_empty()) && is_port(last) && !Path::new(last).is_dir() => positional.pop(),
+            _ => None
+        };
+        positional.extend(dirs);
+
+        // the argument, then `PORT`, which is how most container platforms hand out ports, then the default
+        let port = match port {
+            Some(port) => port,
+            None => match env::var(PORT_ENV) {
+                Ok(port) => {
+                    parse_port(&port).map_err(|err| format!("{PORT_ENV}: {err}"))?;
+                    port
+                },
+                Err(_) => DEFAULT_PORT.to_owned()
+            }
+        };
+
+        let socket = port.strip_prefix(UNIX_PREFIX).filter(|path| !path.is_empty());
+        if cfg!(not(unix)) && socket.is_some() {
+            return Err(format!("`{port}`: unix sockets are only supported on unix"))
+        }
+
+        if socket.is_none() {
+            parse_port(&port)?;
+        }
+
+        if positional.is_empty() {
+            return Err(Self::usage(&program))
+        }
+
+        let threads = match threads {
+            Some(threads) => threads,
+            None => match env::var(THREADS_ENV) {
+                Ok(n) => n.parse().map_err(|_| format!("`{THREADS_ENV}={n}` is not a valid number of threads"))?,
+                Err(_) => 0
+            }
+        };
+
+        let limit = match limit {
+            Some(limit) => limit,
+            None => match env::var(LIMIT_ENV) {
+                Ok(n) => n.parse().map_err(|_| format!("`{LIMIT_ENV}={n}` is not a valid limit"))?,
+                Err(_) => DEFAULT_LIMIT
+            }
+        };
+
+        Ok(Args {dir_paths: positional, port, query, repl, watch, json, ndjson, no_color, path_style, headers, assets, limit, search_timeout, while_indexing, case_sensitive, split_compounds, keep_originals: keep_originals || exact, exact, extensions, operator, numbers, comments_only, csv_headers, max_token_len, path_weight, min_df, progress, analyzer, stop_words, strict, exclude_dirs, exclude_common, exclude, gitignore, no_follow_symlinks, max_depth, checkpoint, index, pdf_ignore, pdf_strip_keys, pdf_timeout, max_text_len, threads})
+    }
+}
+
+#[inline]
+fn value(args: &mut impl Iterator::<Item = String>, flag: &str, what: &str) -> Result::<String, String> {
+    args.next().ok_or_else(|| format!("`{flag}` expects {what}"))
+}
+
+#[inline]
+fn list(value: &str) -> Vec::<String> {
+    value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_owned).collect()
+}
+
+// any TCP port but 0, which would have the system pick one nobody knows about
+#[inline]
+pub fn parse_port(port: &str) -> Result::<u16, String> {
+    port.parse().ok().filter(|port| *port > 0).ok_or_else(|| format!("`{port}` is not a valid port to serve at, expected a number from 1 to 65535"))
+}
+
+// what a positional argument has to look like to be taken as the port, `parse_port` still has to accept a number
+#[inline]
+fn is_port(arg: &str) -> bool {
+    arg.parse::<u16>().is_ok() || arg.starts_with(UNIX_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ports_are_any_nonzero_u16() {
+        assert_eq!(parse_port("80"), Ok(80));
+        assert_eq!(parse_port("443"), Ok(443));
+        assert_eq!(parse_port("65535"), Ok(65535));
+        assert!(parse_port("0").unwrap_err().contains("from 1 to 65535"));
+        assert!(parse_port("70000").is_err());
+        assert!(parse_port("http").is_err());
+        assert!(parse_port("").is_err());
+    }
+
+    #[test]
+    fn only_numbers_and_sockets_are_taken_as_the_port() {
+        assert!(is_port("8080") && is_port("0") && is_port("unix:/tmp/search.sock"));
+        assert!(!is_port("docs") && !is_port("70000") && !is_port("-") && !is_port("./8080x"));
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs::{self, File, Metadata};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, BufWriter, IntoInnerError, Write, Result as IoResult, ErrorKind as IoErrorKind};
+
+use log::warn;
+use hashbrown::HashMap;
+use serde_json::{json, Value};
+
+use crate::core::{HashState, ParseConfig, Text};
+
+// the version of the format that the first line of a checkpoint has, next to the `ParseConfig::fingerprint` of the
+// options its files were parsed with. a file written by another version, or parsed with other options, is not
+// resumed from. the version goes up when files are parsed differently too, the texts of the checkpoint are the old ones
+const VERSION: u64 = 5;
+
+// the files parsed since the last flush are written out once there are this many of them, or once this much
+// time has passed, whichever comes first. what wasn't flushed when the process dies is parsed again
+pub const CHECKPOINT_EVERY_FILES: usize = 1000;
+pub const CHECKPOINT_EVERY: Duration = Duration::from_secs(30);
+
+// what a file was like when it was parsed, a file that still has the same length and modification time
+// is taken to be the same file and isn't parsed again
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Stamp {
+    pub(crate) len: u64,
+    // seconds and nanoseconds since the epoch, `None` where the file system doesn't keep it
+    pub(crate) modified: Option::<(u64, u32)>
+}
+
+impl Stamp {
+    #[inline]
+    pub fn of(md: &Metadata) -> Self {
+        let modified = md.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| (since.as_secs(), since.subsec_nanos()));
+        Stamp { len: md.len(), modified }
+    }
+}
+
+// what parsing a walked file came to: its documents, the one of the file itself or the entries of an archive,
+// and how many parts of it could not be extracted
+pub struct Parsed {
+    pub stamp: Stamp,
+    pub texts: Vec::<(PathBuf, Text)>,
+    pub failed: usize
+}
+
+// the texts of the files parsed so far, for an interrupted indexing to resume without parsing them again.
+// runs write to `<path>.partial` as they go and `finish` puts it in place of `path`, unchanged files come from either
+pub struct Checkpoint {
+    path: PathBuf,
+    partial: PathBuf,
+    done: HashMap::<PathBuf, Parsed, HashState>,
+    writer: BufWriter::<File>,
+    pending: usize,
+    flushed: Instant
+}
+
+impl Checkpoint {
+    // the files were parsed with `config`, none of the ones parsed with other options is taken
+    pub fn open(path: &Path, config: &ParseConfig) -> IoResult::<Self> {
+        let mut partial = OsString::from(path.as_os_str());
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let header = json!({"checkpoint": VERSION, "parse": config.fingerprint()}).to_string();
+        let mut done = HashMap::default();
+        load(path, &header, &mut done)?;
+        load(&partial, &header, &mut done)?;
+
+        let writer = BufWriter::new(File::create(&partial)?);
+        let mut checkpoint = Self {path: path.to_owned(), partial, done, writer, pending: 0, flushed: Instant::now()};
+        writeln!(checkpoint.writer, "{header}")?;
+        Ok(checkpoint)
+    }
+
+    // how many files the checkpoint has that haven't been taken yet
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    // what `file_path` was parsed into, if it's still `stamp`
+    #[inline]
+    pub fn take(&mut self, file_path: &Path, stamp: Stamp) -> Option::<Parsed> {
+        if self.done.get(file_path)?.stamp != stamp { return None }
+        self.done.remove(file_path)
+    }
+
+    // the line `record` writes for `file_path`, made without holding on to the checkpoint,
+    // `None` for a path that isn't UTF-8, which is parsed again every time
+    pub fn line(file_path: &Path, parsed: &Parsed) -> Option::<String> {
+        let texts = parsed.texts.iter().map(|(path, text)| Some(json!({
+            "path": path.to_str()?,
+            "title": text.title,
+            "tags": text.tags,
+            "body": text.body
+        }))).collect::<Option::<Vec::<_>>>()?;
+
+        Some(json!({
+            "file": file_path.to_str()?,
+            "len": parsed.stamp.len,
+            "modified": parsed.stamp.modified,
+            "failed": parsed.failed,
+            "texts": texts
+        }).to_string())
+    }
+
+    // write a `line` out, flushing every `CHECKPOINT_EVERY_FILES` lines or `CHECKPOINT_EVERY`
+    pub fn record(&mut self, line: &str) -> IoResult::<()> {
+        writeln!(self.writer, "{line}")?;
+        self.pending += 1;
+        if self.pending >= CHECKPOINT_EVERY_FILES || self.flushed.elapsed() >= CHECKPOINT_EVERY {
+            self.writer.flush()?;
+            self.pending = 0;
+            self.flushed = Instant::now()
+        }
+        Ok(())
+    }
+
+    // the walk is done, what was written this time is the checkpoint now
+    pub fn finish(self) -> IoResult::<()> {
+        let Checkpoint { path, partial, writer, .. } = self;
+        drop(writer.into_inner().map_err(IntoInnerError::into_error)?);
+        fs::rename(partial, path)
+    }
+}
+
+fn load(path: &Path, header: &str, done: &mut HashMap::<PathBuf, Parsed, HashState>) -> IoResult::<()> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == IoErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err)
+    };
+
+    let mut lines = BufReader::new(file).lines();
+    match lines.next() {
+        Some(Ok(line)) if line == header => {},
+        None => return Ok(()),
+        Some(..) => {
+            warn!("not resuming from {path:?}, it isn't a checkpoint of this version or its files were parsed with other options");
+            return Ok(())
+        }
+    }
+
+    for line in lines {
+        let Some((file_path, parsed)) = line.ok().and_then(|line| parse_line(&line)) else { break };
+        done.insert(file_path, parsed);
+    }
+    Ok(())
+}
+
+fn parse_line(line: &str) -> Option::<(PathBuf, Parsed)> {
+    let Value::Object(object) = serde_json::from_str::<Value>(line).ok()? else { return None };
+    let modified = match object.get("modified")? {
+        Value::Null => None,
+        Value::Array(since) => Some((since.first()?.as_u64()?, since.get(1)?.as_u64()? as u32)),
+        _ => return None
+    };
+    let stamp = Stamp { len: object.get("len")?.as_u64()?, modified };
+
+    let texts = object.get("texts")?.as_array()?.iter().map(|text| {
+        let title = match text.get("title")? {
+            Value::Null => None,
+            title => Some(title.as_str()?.to_owned())
+        };
+        let tags = text.get("tags")?.as_array()?.iter().map(|tag| tag.as_str().map(str::to_owned)).collect::<Option::<_>>()?;
+        let body = text.get("body")?.as_str()?.to_owned();
+        Some((PathBuf::from(text.get("path")?.as_str()?), Text { title, tags, body, analyzer: None }))
+    }).collect::<Option::<Vec::<_>>>()?;
+
+    let failed = object.get("failed")?.as_u64()? as usize;
+    Some((PathBuf::from(object.get("file")?.as_str()?), Parsed { stamp, texts, failed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use crate::core::{WalkConfig, dir_get_contents_resumed};
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn indexing_resumes_from_the_checkpoint() {
+        let root = temp_dir("checkpoint");
+        let dir = root.join("docs");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "apples").unwrap();
+        fs::write(dir.join("b.txt"), "bananas").unwrap();
+        let path = root.join("checkpoint");
+
+        let run = |finish: bool| {
+            let mut checkpoint = Checkpoint::open(&path, &ParseConfig::default()).unwrap();
+            let (mut contents, report) = dir_get_contents_resumed(&[&dir], &WalkConfig::default(), &ParseConfig::default(), &mut checkpoint).unwrap();
+            if finish { checkpoint.finish().unwrap() }
+            contents.sort_by(|a, b| a.0.cmp(&b.0));
+            let bodies = contents.into_iter().map(|(_, text)| text.body).collect::<Vec::<_>>();
+            (bodies, report.resumed)
+        };
+
+        assert_eq!(run(true), (vec!["apples".to_owned(), "bananas".to_owned()], 0));
+
+        fs::write(dir.join("b.txt"), "blueberries").unwrap();
+        fs::write(dir.join("c.txt"), "cherries").unwrap();
+        assert_eq!(run(true), (vec!["apples".to_owned(), "blueberries".to_owned(), "cherries".to_owned()], 1));
+
+        // a run that died on its way, in the middle of a line
+        fs::remove_file(dir.join("a.txt")).unwrap();
+        let (_, resumed) = run(false);
+        assert_eq!(resumed, 2);
+        let mut partial = OpenOptions::new().append(true).open(root.join("checkpoint.partial")).unwrap();
+        write!(partial, r#"{{"file": "{dir}/c.txt", "len": "#, dir = dir.display()).unwrap();
+        drop(partial);
+
+        let checkpoint = Checkpoint::open(&path, &ParseConfig::default()).unwrap();
+        assert_eq!(checkpoint.len(), 3);
+        drop(checkpoint);
+        // the texts are of the options they were parsed with
+        let csv_headers = ParseConfig { csv_headers: true, ..ParseConfig::default() };
+        assert!(Checkpoint::open(&root.join("other"), &ParseConfig::default()).unwrap().is_empty());
+        fs::copy(root.join("checkpoint.partial"), root.join("other.partial")).unwrap();
+        assert!(Checkpoint::open(&root.join("other"), &csv_headers).unwrap().is_empty());
+        assert_eq!(run(true), (vec!["blueberries".to_owned(), "cherries".to_owned()], 2));
+    }
+}
@@ -1,104 +1,362 @@
 use std::str;
-use std::slice;
-use std::fmt::Debug;
-use std::borrow::Cow;
-use std::sync::mpsc::Sender;
+use std::iter;
+use std::mem;
+use std::fmt::{self, Debug};
+use std::borrow::{Borrow, Cow};
+use std::cmp::{Ordering, Reverse};
+use std::env;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
+use std::sync::mpsc::{Sender, channel};
 use std::path::{Path, PathBuf};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BinaryHeap};
 #[cfg(unix)] use std::os::unix::fs::MetadataExt;
-use std::fs::{File, metadata, read_to_string};
-use std::io::{BufReader, Result as IoResult, Error as IoError, ErrorKind as IoErrorKind};
+use std::fs::{self, File, Metadata, metadata};
+use std::ffi::OsString;
+use std::io::{Cursor as IoCursor, Read, Seek, Write, BufReader, BufWriter, IntoInnerError, Result as IoResult, Error as IoError, ErrorKind as IoErrorKind};
 
+use log::{info, warn};
 use rayon::prelude::*;
-use tl::ParserOptions;
-use hashbrown::HashMap;
+use tl::{Node, ParserOptions, VDom};
+use flate2::read::GzDecoder;
+use hashbrown::{HashMap, HashSet};
 use lopdf::{Document, Object};
-use foldhash::fast::RandomState;
-use xml::reader::{EventReader, XmlEvent};
+use foldhash::SharedSeed;
+use foldhash::fast::{FoldHasher, SeedableRandomState};
+use xml::name::OwnedName;
+use xml::reader::{EventReader, ParserConfig as XmlConfig, XmlEvent};
+use zip::ZipArchive;
+use zip::result::ZipError;
 
 use crate::dir_rec::DirRec;
-use crate::term::{Signal, SIGNAL_STOP};
+use crate::ignore::{GITIGNORE, Ignore};
+use crate::checkpoint::{Checkpoint, Parsed, Stamp};
+use crate::formats::archive;
+use crate::formats::comments::{comment_syntax, extract_comments};
+use crate::formats::csv;
+use crate::formats::rtf::{self, Document as RtfDocument};
+use crate::formats::markdown::{Frontmatter, plain_text, split_frontmatter};
+use crate::term::Signal;
+#[cfg(feature = "ocr")]
+use crate::ocr;
+use crate::query::{Expr, Field, Operator, Query, QueryError, parse as parse_query, split_field};
 use crate::snowball::{SnowballEnv, algorithms::english_stemmer::stem};
 
-const GIG: u64 = 1024 * 1024 * 1024;
+pub(crate) const GIG: u64 = 1024 * 1024 * 1024;
+
+// how big the text of a single document may get in memory while it's extracted, see `ParseConfig::max_text_len`.
+// unlike the `GIG` limit on the size on disk, it's checked on what's extracted
+pub const DEFAULT_MAX_TEXT_LEN: usize = 256 * 1024 * 1024;
+
+// how much of a file is looked at to tell whether it's binary
+const SNIFF_LEN: usize = 8 * 1024;
 
 const SPLIT_CHARACTERS: &[char] = &[' ', ',', '.', ';'];
 
-const IGNORE: &[&str] = &["Length", "BBox", "FormType", "Matrix", "Type", "XObject", "Subtype", "Filter", "ColorSpace", "Width", "Height", "BitsPerComponent", "Length1", "Length2", "Length3", "PTEX.FileName", "PTEX.PageNumber", "PTEX.InfoDict", "FontDescriptor", "ExtGState", "MediaBox", "Annot",];
+// object types dropped while loading PDFs. `XObject` and `Annot` may carry text, form XObjects
+// and annotation contents respectively, so documents with a lot of it tend to recover more text without them
+pub const PDF_IGNORE: &[&str] = &["Length", "BBox", "FormType", "Matrix", "Type", "XObject", "Subtype", "Filter", "ColorSpace", "Width", "Height", "BitsPerComponent", "Length1", "Length2", "Length3", "PTEX.FileName", "PTEX.PageNumber", "PTEX.InfoDict", "FontDescriptor", "ExtGState", "MediaBox", "Annot",];
+
+// keys removed from every dictionary while loading PDFs, dictionaries left empty are dropped. `Producer`, `ModDate`,
+// `Creator`, `ProcSet` and `MediaBox` are metadata or layout only and safe to strip, `XObject` and `Annots` reference
+// form XObjects and annotations which may carry text, so those are the ones worth keeping for better text recovery
+pub const PDF_STRIP_KEYS: &[&str] = &["Producer", "ModDate", "Creator", "ProcSet", "Procset", "XObject", "MediaBox", "Annots"];
+
+// the text of a file, split into the fields that can be searched on their own (see `Field`),
+// the path is the last one and comes from the file itself
+#[derive(Default, Debug)]
+pub struct Text {
+    pub title: Option::<String>,
+    // keywords the file was tagged with, the `tags` of markdown frontmatter
+    pub tags: Vec::<String>,
+    pub body: String,
+    // what the text is analyzed with, whatever the tokenizer of the model says when `None`
+    pub analyzer: Option::<Analyzer>
+}
+
+impl From<String> for Text {
+    #[inline]
+    fn from(body: String) -> Self {
+        Text { title: None, tags: Vec::new(), body, analyzer: None }
+    }
+}
+
+impl From<&str> for Text {
+    #[inline]
+    fn from(body: &str) -> Self {
+        Text::from(body.to_owned())
+    }
+}
+
+pub type Contents = Vec::<(PathBuf, Text)>;
+// walked file -> what it was like when it was parsed, see `Model::stamps`
+pub type Stamps = HashMap::<PathBuf, Stamp, HashState>;
+type DocFreq<'a> = HashMap<Held<'a, str>, usize, HashState>;
+type TermFreq<'a> = HashMap<Held<'a, str>, usize, HashState>;
+// where the words of a body are, term -> ascending positions of the words it's the term of
+type Positions<'a> = HashMap<Held<'a, str>, Vec::<u32>, HashState>;
+pub type Ranks<'a> = Vec::<(&'a PathBuf, f32)>;
+// the field a term of a query is scoped to, the word of the query it came from and the term itself, which is only
+// owned when it isn't a slice of the query (a stem with an ending replaced, an exact key), it's never leaked
+type QueryTerm<'q> = (Option::<Field>, &'q str, Cow::<'q, str>);
+// a `QueryTerm` without the word
+type FieldTerm<'q> = (Option::<Field>, Cow::<'q, str>);
+
+// a term the model holds: a slice of a text it borrows for `'a`, or a copy of its own that every map with the term
+// shares, which is dropped along with the last of them
+pub enum Held<'a, T: ?Sized> {
+    Borrowed(&'a T),
+    Owned(Arc::<T>)
+}
+
+impl<'a> Held<'a, str> {
+    // the term as `known` has it already, a copy of it when it doesn't
+    #[inline]
+    fn copied(term: &str, known: &DocFreq<'a>) -> Self {
+        known.get_key_value(term).map_or_else(|| Held::Owned(term.into()), |(held, _)| held.clone())
+    }
+
+    // a slice of a text the model borrows as it is, anything else as `copied` makes it
+    #[inline]
+    fn lent(term: Cow::<'a, str>, known: &DocFreq<'a>) -> Self {
+        match term {
+            Cow::Borrowed(term) => Held::Borrowed(term),
+            Cow::Owned(term) => Self::copied(&term, known)
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for Held<'_, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        match self {
+            Held::Borrowed(t) => t,
+            Held::Owned(t) => t
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for Held<'_, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        match self {
+            Held::Borrowed(t) => Held::Borrowed(t),
+            Held::Owned(t) => Held::Owned(Arc::clone(t))
+        }
+    }
+}
+
+impl<T: ?Sized> Borrow<T> for Held<'_, T> {
+    #[inline(always)]
+    fn borrow(&self) -> &T { self }
+}
+
+impl<T: ?Sized + Hash> Hash for Held<'_, T> {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) { (**self).hash(state) }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for Held<'_, T> {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool { **self == **other }
+}
+
+impl<T: ?Sized + Eq> Eq for Held<'_, T> {}
+
+impl<T: ?Sized + Debug> Debug for Held<'_, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { (**self).fmt(f) }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for Held<'_, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { (**self).fmt(f) }
+}
+
+pub const HASH_SEED_ENV: &str = "SEARCH_HASH_SEED";
+
+// the hasher of every map of the index and of the searches. randomly seeded, like hashbrown's default, unless
+// `SEARCH_HASH_SEED` is set to a number, which seeds all of them with it, so that benchmarks hash, lay out and
+// iterate the maps the same way on every run and their numbers can be compared
+#[derive(Clone, Copy, Debug)]
+pub struct HashState(SeedableRandomState);
+
+impl HashState {
+    fn seed() -> Option::<u64> {
+        static SEED: OnceLock::<Option::<u64>> = OnceLock::new();
+        *SEED.get_or_init(|| {
+            let seed = env::var(HASH_SEED_ENV).ok()?;
+            seed.parse().inspect_err(|_| warn!("`{HASH_SEED_ENV}={seed}` is not a number, hashing randomly")).ok()
+        })
+    }
+}
+
+impl Default for HashState {
+    #[inline]
+    fn default() -> Self {
+        match Self::seed() {
+            Some(seed) => HashState(SeedableRandomState::with_seed(seed, SharedSeed::global_fixed())),
+            None => HashState(SeedableRandomState::random())
+        }
+    }
+}
+
+impl BuildHasher for HashState {
+    type Hasher = FoldHasher;
 
-type Contents = Vec::<(PathBuf, String)>;
-type DocFreq<'a> = HashMap<&'a str, usize>;
-type TermFreq<'a> = HashMap<&'a str, usize>;
-type Ranks<'a> = Vec::<(&'a PathBuf, f32)>;
+    #[inline(always)]
+    fn build_hasher(&self) -> FoldHasher {
+        self.0.build_hasher()
+    }
+}
 
 macro_rules! am {
     ($($tt: tt) *) => { std::sync::Arc::new(std::sync::Mutex::new($($tt) *)) }
 }
 
+// the error of a text that got longer than `max_len` bytes on its way into memory
+#[inline]
+fn text_too_long(max_len: usize) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, format!("text is longer than {max_len} bytes"))
+}
+
+#[inline]
+fn check_text_len(len: usize, max_len: Option::<usize>) -> IoResult::<()> {
+    match max_len {
+        Some(max_len) if len > max_len => Err(text_too_long(max_len)),
+        _ => Ok(())
+    }
+}
+
+// the contents of a text file, reading no more than a byte past `max_len` of it
+fn read_text<P>(file_path: P, max_len: Option::<usize>) -> IoResult::<String>
+where
+    P: AsRef::<Path> + Debug
+{
+    let mut bytes = Vec::new();
+    read_file(&file_path)?.take(max_len.map_or(u64::MAX, |max_len| max_len as u64 + 1)).read_to_end(&mut bytes)?;
+    check_text_len(bytes.len(), max_len)?;
+    String::from_utf8(bytes).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))
+}
+
 #[inline]
 fn read_file<P>(file_path: P) -> IoResult::<BufReader::<File>>
 where
     P: AsRef::<Path> + Debug
 {
     let file = File::open(&file_path).inspect_err(|err| {
-        eprintln!("could not read {file_path:?}: {err}")
+        warn!("could not read {file_path:?}: {err}")
     })?;
 
     Ok(BufReader::new(file))
 }
 
-struct PdfText {
-    text: BTreeMap<u32, Vec::<String>>,
-    errors: Vec::<String>
+pub struct PdfText {
+    // lines of every page that could be extracted, keyed by page number
+    pub text: BTreeMap<u32, Vec::<String>>,
+    // one message for every page that could not be
+    pub errors: Vec::<String>,
+    // whether some pages were skipped because the extraction ran out of time
+    pub timed_out: bool,
+    // `/Title` of the document information dictionary
+    pub title: Option::<String>
+}
+
+#[derive(Clone, Debug)]
+pub struct PdfFilter {
+    pub ignore: Vec::<String>,
+    pub strip_keys: Vec::<String>
 }
 
-fn filter_func(object_id: (u32, u16), object: &mut Object) -> Option::<((u32, u16), Object)> {
-    if IGNORE.contains(&object.type_name().unwrap_or_default()) {
-        return None;
+impl Default for PdfFilter {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            ignore: PDF_IGNORE.iter().map(|s| s.to_string()).collect(),
+            strip_keys: PDF_STRIP_KEYS.iter().map(|s| s.to_string()).collect()
+        }
     }
+}
 
-    if let Ok(d) = object.as_dict_mut() {
-        d.remove(b"Producer");
-        d.remove(b"ModDate");
-        d.remove(b"Creator");
-        d.remove(b"ProcSet");
-        d.remove(b"Procset");
-        d.remove(b"XObject");
-        d.remove(b"MediaBox");
-        d.remove(b"Annots");
-        if d.is_empty() {
+impl PdfFilter {
+    fn filter(&self, object_id: (u32, u16), object: &mut Object) -> Option::<((u32, u16), Object)> {
+        let type_name = object.type_name().unwrap_or_default();
+        if self.ignore.iter().any(|t| t == type_name) {
             return None;
         }
-    }
 
-    Some((object_id, object.to_owned()))
+        if let Ok(d) = object.as_dict_mut() {
+            self.strip_keys.iter().for_each(|key| { d.remove(key.as_bytes()); });
+            if d.is_empty() {
+                return None;
+            }
+        }
+
+        Some((object_id, object.to_owned()))
+    }
 }
 
 #[inline(always)]
-fn load_pdf<P>(path: P) -> Result::<Document, IoError>
+fn load_pdf<P>(path: P, filter: &PdfFilter) -> Result::<Document, IoError>
 where
     P: AsRef::<Path>
 {
-    Document::load_filtered(path, filter_func)
-        .map_err(|e| IoError::new(IoErrorKind::Other, e.to_string()))
+    Document::load_filtered(path, &|id, object| filter.filter(id, object))
+        .map_err(|e| IoError::other(e.to_string()))
 }
 
-fn get_pdf_text(doc: &Document) -> Result::<PdfText, IoError> {
+// text strings are either UTF-16BE with a BOM or PDFDocEncoding, which is close enough to latin-1 for searching
+fn pdf_title(doc: &Document) -> Option::<String> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, info) = doc.dereference(info).ok()?;
+    let title = info.as_dict().ok()?.get(b"Title").ok()?.as_str().ok()?;
+    let title = match title {
+        [0xfe, 0xff, utf16 @ ..] => {
+            let units = utf16.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect::<Vec::<_>>();
+            String::from_utf16_lossy(&units)
+        },
+        latin1 => latin1.iter().map(|b| *b as char).collect()
+    };
+    Some(title).filter(|title| !title.trim().is_empty())
+}
+
+// the deadline is checked before every page, a page that is already being extracted can't be interrupted.
+// with the `ocr` feature the pages of `file_path` that have next to no text are read off their image instead,
+// see `ocr::recover`, a PDF that isn't a file of its own has no `file_path` and isn't OCRed
+#[cfg_attr(not(feature = "ocr"), allow(unused_variables))]
+fn get_pdf_text(doc: &Document, deadline: Option::<Instant>, file_path: Option::<&Path>) -> Result::<PdfText, IoError> {
     let mut pdf_text = PdfText {
         text: BTreeMap::new(),
         errors: Vec::new(),
+        timed_out: false,
+        title: pdf_title(doc)
     };
 
     let pdf_text_am = am!(&mut pdf_text);
 
+    // pages are extracted on the current rayon pool, so `--threads` bounds this too
     doc.get_pages()
         .into_par_iter()
         .map(|(npage, page_id)| {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(IoError::new(IoErrorKind::TimedOut, format!("timed out before page {npage}")))
+            }
+
             let text = doc.extract_text(&[npage]).map_err(|e| {
-                IoError::new(IoErrorKind::Other,
-                             format!("could not to extract text from page {npage} id={page_id:?}: {e:}"))
+                IoError::other(format!("could not to extract text from page {npage} id={page_id:?}: {e:}"))
             })?;
 
+            #[cfg(feature = "ocr")]
+            let text = match file_path {
+                Some(file_path) => ocr::recover(file_path, npage, text),
+                None => text
+            };
+
             Ok((npage,
                 text.split('\n')
                     .map(|s| s.to_lowercase())
@@ -107,7 +365,10 @@ fn get_pdf_text(doc: &Document) -> Result::<PdfText, IoError> {
             let mut pdf_text = unsafe { pdf_text_am.lock().unwrap_unchecked() };
             match page {
                 Ok((npage, lines)) => { pdf_text.text.insert(npage, lines); },
-                Err(e) => pdf_text.errors.push(e.to_string()),
+                Err(e) => {
+                    pdf_text.timed_out |= e.kind() == IoErrorKind::TimedOut;
+                    pdf_text.errors.push(e.to_string())
+                }
             }
         });
 
@@ -115,34 +376,74 @@ fn get_pdf_text(doc: &Document) -> Result::<PdfText, IoError> {
 }
 
 trait ParseFn {
-    fn parse<P>(file_path: P) -> IoResult::<String>
+    // the text of a document whose contents are `input`, `ext` is the extension it was dispatched on
+    fn parse_str(input: &str, ext: &str) -> IoResult::<Text>;
+
+    // the file is `InvalidData` when it's longer than `max_len`, or its text is for the parsers that stream it
+    #[inline]
+    fn parse<P>(file_path: P, max_len: Option::<usize>) -> IoResult::<Text>
     where
-        P: AsRef::<Path> + Debug;
+        P: AsRef::<Path> + Debug
+    {
+        let ext = file_path.as_ref().extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        Self::parse_str(&read_text(&file_path, max_len)?, ext)
+    }
 }
 
-struct Pdf;
+pub struct Pdf;
 
-impl ParseFn for Pdf {
-    #[inline]
-    fn parse<P>(file_path: P) -> IoResult::<String>
+impl Pdf {
+    // text of a PDF page by page, for callers that need to know where the text came from. pages that fail to
+    // extract, and the rest of them once `timeout` runs out, are skipped, it's an error when none could be
+    pub fn pages<P>(file_path: P, filter: &PdfFilter, timeout: Option::<Duration>) -> IoResult::<PdfText>
     where
         P: AsRef::<Path> + Debug
     {
-        let doc = load_pdf(&file_path)?;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        Self::pages_of(&load_pdf(&file_path, filter)?, deadline, Some(file_path.as_ref()))
+    }
+
+    fn pages_of(doc: &Document, deadline: Option::<Instant>, file_path: Option::<&Path>) -> IoResult::<PdfText> {
         if doc.is_encrypted() {
-            return Err(IoError::new(IoErrorKind::InvalidData, "doc is encrypted"))
+            return Err(IoError::new(IoErrorKind::InvalidData, Encrypted))
         }
 
-        let text = get_pdf_text(&doc)?;
-        if !text.errors.is_empty() {
-            return Err(IoError::new(IoErrorKind::InvalidData, "could not parse document as pdf"))
+        let text = get_pdf_text(doc, deadline, file_path)?;
+        if text.text.is_empty() && !text.errors.is_empty() {
+            let kind = if text.timed_out { IoErrorKind::TimedOut } else { IoErrorKind::InvalidData };
+            return Err(IoError::new(kind, format!(
+                "could not extract any of the {n} pages: {err}",
+                n = text.errors.len(), err = text.errors[0]
+            )))
         }
 
-        let string = text.text.iter()
-            .map(|(_, text)| text.join(" "))
-            .collect();
+        Ok(text)
+    }
+
+    // the pages collapsed into a single string for indexing, along with the number of pages that failed
+    fn parse_lossy<P>(file_path: P, filter: &PdfFilter, timeout: Option::<Duration>) -> IoResult::<(Text, usize)>
+    where
+        P: AsRef::<Path> + Debug
+    {
+        Ok(Self::collapse(Self::pages(file_path, filter, timeout)?))
+    }
+
+    // a PDF that isn't a file of its own, gzipped or in an archive. it's loaded whole, without `PdfFilter`,
+    // which lopdf only applies while reading from a file
+    fn parse_mem(bytes: &[u8], timeout: Option::<Duration>) -> IoResult::<(Text, usize)> {
+        let doc = Document::load_mem(bytes).map_err(|e| IoError::new(IoErrorKind::InvalidData, e.to_string()))?;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        Ok(Self::collapse(Self::pages_of(&doc, deadline, None)?))
+    }
+
+    #[inline]
+    fn collapse(text: PdfText) -> (Text, usize) {
+        let string = text.text.values()
+            .map(|text| text.join(" "))
+            .collect::<Vec::<_>>()
+            .join(" ");
 
-        Ok(string)
+        (Text { title: text.title, tags: Vec::new(), body: string, analyzer: None }, text.errors.len())
     }
 }
 
@@ -150,270 +451,4451 @@ struct Txt;
 
 impl ParseFn for Txt {
     #[inline]
-    fn parse<P>(file_path: P) -> IoResult::<String>
+    fn parse_str(input: &str, _ext: &str) -> IoResult::<Text> {
+        Ok(input.to_owned().into())
+    }
+
+    #[inline]
+    fn parse<P>(file_path: P, max_len: Option::<usize>) -> IoResult::<Text>
     where
         P: AsRef::<Path> + Debug
     {
-        read_to_string(file_path)
+        read_text(file_path, max_len).map(Text::from)
+    }
+}
+
+// the title and the tags of the frontmatter go to their fields, the rest is the text without the markup, see `plain_text`
+struct Markdown;
+
+impl ParseFn for Markdown {
+    #[inline]
+    fn parse_str(input: &str, _ext: &str) -> IoResult::<Text> {
+        let Frontmatter { title, tags, body } = split_frontmatter(input).unwrap_or(Frontmatter { title: None, tags: Vec::new(), body: input });
+        Ok(Text { title, tags, body: plain_text(body), analyzer: None })
+    }
+}
+
+// comma separated values, tab separated ones for `tsv`: the body is the values of the cells, a space between the
+// ones of a row and a line between rows, so the quotes and the delimiters around a cell never make it into a word.
+// with `HEADER` the first row is the names of the columns, which go to the tags
+struct Csv<const HEADER: bool>;
+
+impl<const HEADER: bool> ParseFn for Csv<HEADER> {
+    fn parse_str(input: &str, ext: &str) -> IoResult::<Text> {
+        let delimiter = if ext == "tsv" { '\t' } else { ',' };
+        let mut rows = csv::rows(input.strip_prefix('\u{feff}').unwrap_or(input), delimiter);
+        let tags = match HEADER.then(|| rows.next()).flatten() {
+            Some(names) => names.iter().map(|name| name.trim()).filter(|name| !name.is_empty()).map(str::to_owned).collect(),
+            None => Vec::new()
+        };
+
+        let mut body = String::with_capacity(input.len());
+        for row in rows {
+            for (i, cell) in row.iter().enumerate() {
+                if i > 0 { body.push(' ') }
+                body.push_str(cell.trim())
+            }
+            body.push('\n')
+        }
+        Ok(Text { title: None, tags, body, analyzer: None })
+    }
+}
+
+// the text of the groups that hold text, the title of `\info` to the title, see `rtf::text`
+struct Rtf;
+
+impl ParseFn for Rtf {
+    #[inline]
+    fn parse_str(input: &str, _ext: &str) -> IoResult::<Text> {
+        let RtfDocument { title, body } = rtf::text(input).ok_or_else(|| IoError::new(IoErrorKind::InvalidData, "not an RTF document"))?;
+        Ok(Text { title, tags: Vec::new(), body, analyzer: None })
+    }
+}
+
+struct Code;
+
+impl ParseFn for Code {
+    #[inline]
+    fn parse_str(source: &str, ext: &str) -> IoResult::<Text> {
+        let Some(syntax) = comment_syntax(ext) else {
+            return Txt::parse_str(source, ext)
+        };
+        Ok(extract_comments(source, syntax).into())
     }
 }
 
 struct Html;
 
+// elements that are blocks of their own, so their text never runs into the text around them
+const HTML_BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption", "figure",
+    "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav", "ol", "p",
+    "pre", "section", "table", "td", "th", "title", "tr", "ul"
+];
+
+impl Html {
+    // the text of every node once, in document order, with a space around every block.
+    // the tree is walked with a stack of its own, `None` on it is where a block ends
+    fn text(dom: &VDom) -> String {
+        let parser = dom.parser();
+        let mut text = String::new();
+        let mut stack = dom.children().iter().rev().copied().map(Some).collect::<Vec::<_>>();
+        while let Some(handle) = stack.pop() {
+            let Some(handle) = handle else {
+                text.push(' ');
+                continue
+            };
+            match handle.get(parser) {
+                Some(Node::Raw(raw)) => text.push_str(&raw.as_utf8_str()),
+                Some(Node::Tag(tag)) => {
+                    let name = tag.name().as_utf8_str();
+                    if HTML_BLOCKS.iter().any(|block| name.eq_ignore_ascii_case(block)) {
+                        text.push(' ');
+                        stack.push(None)
+                    }
+                    stack.extend(tag.children().top().as_slice().iter().rev().copied().map(Some))
+                },
+                Some(Node::Comment(..)) | None => {}
+            }
+        }
+        text
+    }
+}
+
 impl ParseFn for Html {
-    fn parse<P>(file_path: P) -> IoResult::<String>
-    where
-        P: AsRef::<Path> + Debug
-    {
-        let input = read_to_string(&file_path)?;
-        let Ok(dom) = tl::parse(&input, ParserOptions::default()) else {
+    fn parse_str(input: &str, _ext: &str) -> IoResult::<Text> {
+        let Ok(dom) = tl::parse(input, ParserOptions::default()) else {
             return Err(IoError::new(IoErrorKind::InvalidData, "could not parse html"))
         };
         let parser = dom.parser();
-        let string = dom.nodes().iter().map(|node| node.inner_text(parser)).collect();
-        Ok(string)
+        let title = dom.query_selector("title")
+            .and_then(|mut titles| titles.next())
+            .and_then(|title| title.get(parser))
+            .map(|title| title.inner_text(parser).into_owned());
+
+        Ok(Text { title, tags: Vec::new(), body: Html::text(&dom), analyzer: None })
     }
 }
 
 struct Xml;
 
+// what xml-rs fails with once a run of character data gets longer than its `max_data_length`,
+// which it only tells apart from the errors of malformed documents by the message
+const XML_LIMIT_MESSAGE: &str = "This document is larger/more complex than allowed by the parser's configuration";
+
+impl Xml {
+    // the character data of the document up to where it stops being well-formed. the parser holds a whole run
+    // of it before handing it over, so it's kept from holding more than `max_len` bytes of one, not just the text
+    fn text(reader: impl Read, max_len: Option::<usize>) -> IoResult::<String> {
+        let config = XmlConfig::new().max_data_length(max_len.map_or(usize::MAX, |max_len| max_len.saturating_add(1)));
+        let mut text = String::new();
+        for event in EventReader::new_with_config(reader, config) {
+            match event {
+                Ok(XmlEvent::Characters(chars)) => {
+                    check_text_len(text.len() + chars.len(), max_len)?;
+                    text.push_str(&chars)
+                },
+                Ok(..) => {},
+                Err(err) => match max_len {
+                    Some(max_len) if err.msg() == XML_LIMIT_MESSAGE => return Err(text_too_long(max_len)),
+                    _ => break
+                }
+            }
+        }
+        Ok(text)
+    }
+}
+
 impl ParseFn for Xml {
-    fn parse<P>(file_path: P) -> IoResult::<String>
+    #[inline]
+    fn parse_str(input: &str, _ext: &str) -> IoResult::<Text> {
+        Ok(Xml::text(input.as_bytes(), None)?.into())
+    }
+
+    // streamed from the file instead of being read into a string first
+    #[inline]
+    fn parse<P>(file_path: P, max_len: Option::<usize>) -> IoResult::<Text>
+    where
+        P: AsRef::<Path> + Debug
+    {
+        Ok(Xml::text(read_file(&file_path)?, max_len)?.into())
+    }
+}
+
+// Office Open XML word processing documents: the text of the runs of `word/document.xml`, a line per paragraph,
+// the title is the `dc:title` of `docProps/core.xml`. it's a zip, so it's read the way `Pdf` is
+pub struct Docx;
+
+const DOCX_BODY: &str = "word/document.xml";
+const DOCX_PROPERTIES: &str = "docProps/core.xml";
+// the namespaces of the elements of the body, of transitional and of strict documents
+const WORDPROCESSINGML: &[&str] = &["http://schemas.openxmlformats.org/wordprocessingml/2006/main", "http://purl.oclc.org/ooxml/wordprocessingml/main"];
+const DUBLIN_CORE: &str = "http://purl.org/dc/elements/1.1/";
+
+impl Docx {
+    #[inline]
+    fn parse<P>(file_path: P, max_len: Option::<usize>) -> IoResult::<Text>
     where
         P: AsRef::<Path> + Debug
     {
-        let file = read_file(&file_path)?;
-        let parser = EventReader::new(file);
+        Self::read(read_file(&file_path)?, max_len)
+    }
+
+    // a document that isn't a file of its own, gzipped or in an archive
+    #[inline]
+    fn parse_mem(bytes: &[u8], max_len: Option::<usize>) -> IoResult::<Text> {
+        Self::read(IoCursor::new(bytes), max_len)
+    }
+
+    fn read(reader: impl Read + Seek, max_len: Option::<usize>) -> IoResult::<Text> {
+        let invalid = |err: ZipError| IoError::new(IoErrorKind::InvalidData, err);
+        let mut zip = ZipArchive::new(reader).map_err(invalid)?;
+        let body = Self::body(zip.by_name(DOCX_BODY).map_err(invalid)?.take(GIG), max_len)?;
+        // the properties are optional, and so is everything in them
+        let title = zip.by_name(DOCX_PROPERTIES).ok().and_then(Self::title);
+        Ok(Text { title, tags: Vec::new(), body, analyzer: None })
+    }
+
+    #[inline]
+    fn is_word(name: &OwnedName) -> bool {
+        name.namespace_ref().is_some_and(|namespace| WORDPROCESSINGML.contains(&namespace))
+    }
 
-        let string = parser.into_iter().filter_map(|event| {
+    // like `Xml::text`, up to where the document stops being well-formed
+    fn body(reader: impl Read, max_len: Option::<usize>) -> IoResult::<String> {
+        let config = XmlConfig::new().max_data_length(max_len.map_or(usize::MAX, |max_len| max_len.saturating_add(1)));
+        let mut body = String::new();
+        // inside of a `w:t`, the character data of everything else is markup
+        let mut in_run = false;
+        for event in EventReader::new_with_config(reader, config) {
             match event {
-                Ok(XmlEvent::Characters(text)) => Some(text),
-                _ => None
+                Ok(XmlEvent::StartElement { name, .. }) if Self::is_word(&name) => match name.local_name.as_str() {
+                    "t" => in_run = true,
+                    "tab" | "br" | "cr" => body.push(' '),
+                    _ => {}
+                },
+                Ok(XmlEvent::EndElement { name }) if Self::is_word(&name) => match name.local_name.as_str() {
+                    "t" => in_run = false,
+                    "p" => body.push('\n'),
+                    "tc" => body.push(' '),
+                    _ => {}
+                },
+                Ok(XmlEvent::Characters(chars) | XmlEvent::Whitespace(chars)) if in_run => {
+                    check_text_len(body.len() + chars.len(), max_len)?;
+                    body.push_str(&chars)
+                },
+                Ok(..) => {},
+                Err(err) => match max_len {
+                    Some(max_len) if err.msg() == XML_LIMIT_MESSAGE => return Err(text_too_long(max_len)),
+                    _ => break
+                }
             }
-        }).collect();
+        }
+        Ok(body)
+    }
 
-        Ok(string)
+    fn title(reader: impl Read) -> Option::<String> {
+        let mut title = String::new();
+        let mut in_title = false;
+        for event in EventReader::new(reader) {
+            match event.ok()? {
+                XmlEvent::StartElement { name, .. } => in_title = name.local_name == "title" && name.namespace_ref() == Some(DUBLIN_CORE),
+                XmlEvent::EndElement { .. } if in_title => break,
+                XmlEvent::Characters(chars) if in_title => title.push_str(&chars),
+                _ => {}
+            }
+        }
+        Some(title).filter(|title| !title.trim().is_empty())
     }
 }
 
-#[inline]
-fn parse(file_path: &Path) -> IoResult::<String> {
-    let ext = unsafe {
-        file_path.extension()
-            .unwrap_or_default()
-            .to_str()
-            .unwrap_unchecked()
-    };
+// EPUB e-books: the text of the documents of the spine in reading order, the title is the `dc:title` of the package.
+// the documents that can't be read are the parts that failed
+pub struct Epub;
 
-    let md = metadata(file_path)?;
+const EPUB_CONTAINER: &str = "META-INF/container.xml";
+const EPUB_PACKAGE: &str = "http://www.idpf.org/2007/opf";
+const EPUB_NCX: &str = "application/x-dtbncx+xml";
 
-    #[cfg(unix)]
-    if md.mode() & 0o111 != 0 {
-        return Err(IoError::new(IoErrorKind::Unsupported, "not parsing binary files"))
+// what the package document of an EPUB says: the id of every file of the book -> its path in the archive and
+// whether it's for navigation, and the ids of the spine
+#[derive(Default)]
+struct EpubPackage {
+    title: Option::<String>,
+    manifest: HashMap::<String, (String, bool)>,
+    spine: Vec::<String>
+}
+
+impl Epub {
+    #[inline]
+    fn parse<P>(file_path: P, max_len: Option::<usize>) -> IoResult::<(Text, usize)>
+    where
+        P: AsRef::<Path> + Debug
+    {
+        Self::read(read_file(&file_path)?, max_len)
     }
 
-    if md.len() >= GIG {
-        return Err(IoError::new(IoErrorKind::InvalidData, "file is too big"))
+    // a book that isn't a file of its own, gzipped or in an archive
+    #[inline]
+    fn parse_mem(bytes: &[u8], max_len: Option::<usize>) -> IoResult::<(Text, usize)> {
+        Self::read(IoCursor::new(bytes), max_len)
     }
 
-    match ext {
-        "pdf" => Pdf::parse(file_path),
-        "html" => Html::parse(file_path),
-        "xml" | "xhtml" => Xml::parse(file_path),
-          "txt"      | "css"     | "js"       | "json"    | "rs"       | "py"
-        | "rb"       | "java"    | "c"        | "cpp"     | "go"       | "sh"
-        | "md"       | "yaml"    | "ini"      | "sql"     | "csv"      | "log"
-        | "makefile" | "bat"     | "php"      | "pl"      | "asm"      | "dockerfile"
-        | "erb"      | "proto"   | "tf"       | "tfvars"  | "toml"     | "v"
-        | "rspec"    | "ml"      | "dart"     | "lua"     | "coffee"   | "scss"
-        | "less"     | "svg"     | "acl"      | "patch"   | "diff"     | "zsh"
-        | "r"        | "groovy"  | "h"        | "hpp"     | "c++"      | "nasm"
-        | "wxml"     | "wxs"     | "cfg"      | "zig"     | "env"      | "d"
-        | "f90"      | "f"       | "jl"       | "cabal"   | "hs"       | "nim"
-        | "sol"      | "swift"   | "mxml"     | "clj"     | "cljs"     | "lisp"
-        | "el"       | "sml"     | "styl"     | "nut"     | "wsgi"     | "raku"
-        | "q"        | "sage"    | "pike"     | "xqy"     | "slim"     | "hx"
-        | "pmd"      | "gsql"    | "cs"       | "ts"      | "gitignore"| "in" => Txt::parse(file_path),
-        _ => Err(IoError::new(IoErrorKind::InvalidData, "unknown extension"))
+    fn read(reader: impl Read + Seek, max_len: Option::<usize>) -> IoResult::<(Text, usize)> {
+        let mut zip = ZipArchive::new(reader).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?;
+        let container = zip_entry_text(&mut zip, EPUB_CONTAINER, max_len)?;
+        let package_path = Self::package_path(&container).ok_or_else(|| IoError::new(IoErrorKind::InvalidData, "no package document in the container"))?;
+        let package = Self::package(&zip_entry_text(&mut zip, &package_path, max_len)?, &package_path);
+
+        let mut body = String::new();
+        let mut failed = 0;
+        let mut errors = Vec::new();
+        let documents = package.spine.iter().filter_map(|id| package.manifest.get(id)).filter(|(_, navigation)| !navigation);
+        for (path, _) in documents {
+            let document = zip_entry_text(&mut zip, path, max_len).and_then(|document| match tl::parse(&document, ParserOptions::default()) {
+                Ok(dom) => Ok(Html::text(&dom)),
+                Err(..) => Err(IoError::new(IoErrorKind::InvalidData, "could not parse html"))
+            });
+            match document {
+                Ok(text) => {
+                    check_text_len(body.len() + text.len(), max_len)?;
+                    body.push_str(&text);
+                    body.push('\n')
+                },
+                Err(err) => {
+                    failed += 1;
+                    errors.push(format!("{path}: {err}"))
+                }
+            }
+        }
+        if body.is_empty() && !errors.is_empty() {
+            return Err(IoError::new(IoErrorKind::InvalidData, format!("could not read any of the {failed} documents: {err}", err = errors[0])))
+        }
+
+        Ok((Text { title: package.title, tags: Vec::new(), body, analyzer: None }, failed))
     }
-}
 
-pub struct Doc<'a> {
-    tf: TermFreq<'a>,
-    count: usize
+    // the `full-path` of the first `rootfile` of the container
+    fn package_path(container: &str) -> Option::<String> {
+        EventReader::new(container.as_bytes()).into_iter().map_while(Result::ok).find_map(|event| match event {
+            XmlEvent::StartElement { name, attributes, .. } if name.local_name == "rootfile" => {
+                attributes.into_iter().find(|attribute| attribute.name.local_name == "full-path").map(|attribute| attribute.value)
+            },
+            _ => None
+        })
+    }
+
+    // the hrefs of the manifest are relative to the package document at `package_path`
+    fn package(package: &str, package_path: &str) -> EpubPackage {
+        let dir = package_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let mut parsed = EpubPackage::default();
+        let mut in_title = false;
+        for event in EventReader::new(package.as_bytes()).into_iter().map_while(Result::ok) {
+            match event {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    let attribute = |local_name: &str| attributes.iter().find(|attribute| attribute.name.local_name == local_name).map(|attribute| attribute.value.as_str());
+                    in_title = parsed.title.is_none() && name.local_name == "title" && name.namespace_ref() == Some(DUBLIN_CORE);
+                    if name.namespace_ref() != Some(EPUB_PACKAGE) { continue }
+                    match name.local_name.as_str() {
+                        "item" => if let (Some(id), Some(href)) = (attribute("id"), attribute("href")) {
+                            let navigation = attribute("media-type") == Some(EPUB_NCX) ||
+                                attribute("properties").is_some_and(|properties| properties.split_whitespace().any(|property| property == "nav"));
+                            parsed.manifest.insert(id.to_owned(), (zip_join(dir, href), navigation));
+                        },
+                        "itemref" => parsed.spine.extend(attribute("idref").map(str::to_owned)),
+                        _ => {}
+                    }
+                },
+                XmlEvent::EndElement { .. } => in_title = false,
+                XmlEvent::Characters(chars) if in_title => parsed.title = Some(chars).filter(|title| !title.trim().is_empty()),
+                _ => {}
+            }
+        }
+        parsed
+    }
 }
 
-pub type Docs<'a> = HashMap::<&'a PathBuf, Doc<'a>>;
+// the name of the entry `href` points to from the directory `dir` of an archive, `..` taking a directory off
+fn zip_join(dir: &str, href: &str) -> String {
+    let mut parts = dir.split('/').filter(|part| !part.is_empty()).collect::<Vec::<_>>();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {},
+            ".." => _ = parts.pop(),
+            part => parts.push(part)
+        }
+    }
+    parts.join("/")
+}
 
-#[inline]
-unsafe fn str_to_lower<'a>(s: &'a str) -> &'a str {
-    let bytes = slice::from_raw_parts_mut(s.as_ptr() as *mut _, s.len());
+// the UTF-8 text of the entry `name`, reading no more than a byte past `max_len` of it, `InvalidData` when there's no such entry
+fn zip_entry_text<R: Read + Seek>(zip: &mut ZipArchive::<R>, name: &str, max_len: Option::<usize>) -> IoResult::<String> {
+    let entry = zip.by_name(name).map_err(|err| IoError::new(IoErrorKind::InvalidData, format!("{name}: {err}")))?;
+    let mut bytes = Vec::new();
+    entry.take(max_len.map_or(GIG, |max_len| max_len as u64 + 1)).read_to_end(&mut bytes)?;
+    check_text_len(bytes.len(), max_len)?;
+    String::from_utf8(bytes).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))
+}
 
-    bytes.iter_mut()
-        .filter(|byte| **byte >= b'A' && **byte <= b'Z')
-        .for_each(|byte| *byte += 32);
+// a file is binary if it's an executable image or if its head contains a NUL byte or mostly consists of
+// control characters, bytes >= 0x80 count as text, so UTF-8 and legacy encodings pass
+fn is_binary(file_path: &Path, md: &Metadata) -> IoResult::<bool> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(file_path)?.take(SNIFF_LEN as _).read_to_end(&mut head)?;
+    Ok(is_executable_image(md, &head) || is_binary_bytes(&head))
+}
 
-    str::from_utf8_unchecked(bytes)
+// the mode bit alone is not enough, scripts are executable too (and so
+// is everything on some mounted filesystems), so the magic number decides
+#[cfg(unix)]
+#[inline]
+fn is_executable_image(md: &Metadata, head: &[u8]) -> bool {
+    const MAGICS: &[&[u8]] = &[b"\x7fELF", b"\xfe\xed\xfa\xce", b"\xfe\xed\xfa\xcf", b"\xce\xfa\xed\xfe", b"\xcf\xfa\xed\xfe", b"\xca\xfe\xba\xbe"];
+    md.mode() & 0o111 != 0 && MAGICS.iter().any(|magic| head.starts_with(magic))
 }
 
+// there's no executable bit on windows, PE images (`.exe`, `.dll`, however they're named) start with `MZ`
+#[cfg(windows)]
 #[inline]
-pub fn string_to_str(string: String) -> &'static str {
-    Box::leak(string.into_boxed_str())
+fn is_executable_image(_md: &Metadata, head: &[u8]) -> bool {
+    head.starts_with(b"MZ")
+}
+
+#[cfg(not(any(unix, windows)))]
+#[inline(always)]
+fn is_executable_image(_md: &Metadata, _head: &[u8]) -> bool {
+    false
 }
 
-// trim, stem and lowercase word avoiding copying
 #[inline]
-fn prepare_word<'a>(word: &'a str) -> Option::<&'a str> {
-    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
-    if word.is_empty() || word.len() > 64 { return None }
-    let word = unsafe { str_to_lower(word) };
-    let mut env = SnowballEnv::create(word);
-    stem(&mut env);
-    let word = match env.get_current() {
-        Cow::Owned(ow) => string_to_str(ow),
-        Cow::Borrowed(bw) => bw,
-    };
-    Some(word)
+fn is_binary_bytes(head: &[u8]) -> bool {
+    if head.contains(&0) { return true }
+    let control = head.iter().filter(|b| {
+        (**b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | b'\x0c' | b'\x1b')) || **b == 0x7f
+    }).count();
+    control * 10 > head.len() * 3
 }
 
-impl<'a> Doc<'a> {
-    pub fn new(content: &'a str) -> Self {
-        let (count, tf) = content.split(SPLIT_CHARACTERS).fold({
-            (0, TermFreq::with_capacity_and_hasher(128, RandomState::default()))
-        }, |(c, mut tf), word| {
-            if let Some(word) = prepare_word(word) {
-                *tf.entry(word).or_insert(0) += 1;
-                (c + 1, tf)
-            } else {
-                (c, tf)
-            }
-        });
+// the error inside of the `IoError` of `Pdf::pages` for an encrypted PDF, which `ParseError` tells apart
+#[derive(Debug)]
+struct Encrypted;
 
-        Doc { tf, count }
+impl fmt::Display for Encrypted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "doc is encrypted")
     }
 }
 
-pub struct Model<'a> {
-    // how many already indexed
-    count: usize,
-    milestones_tx: Sender::<Signal>,
-    milestones: Vec::<(usize, Signal)>,
+impl std::error::Error for Encrypted {}
 
-    pub df: DocFreq<'a>,
-    pub docs: Docs<'a>
+#[derive(Debug)]
+pub enum ParseError {
+    // at least `GIG` bytes
+    TooBig,
+    // an executable image or mostly control characters, see `is_binary`
+    Binary,
+    // not an extension `parse` knows how to extract text from
+    UnknownExtension,
+    // a PDF that can't be read without its password
+    Encrypted,
+    // the contents could not be made sense of: not UTF-8, malformed XML or a PDF nothing could be extracted from
+    Decode(IoError),
+    // the file could not be read
+    Io(IoError)
 }
 
-impl<'a> Model<'a> {
+impl ParseError {
+    // what files that failed this way are grouped under in `Report::skipped_by_reason`
     #[inline]
-    fn calculate_milestones(docs_count: usize) -> Vec::<(usize, Signal)> {
-        (5..=100).step_by(5).map(|count| ((docs_count * count) / 100, count as _)).collect()
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ParseError::TooBig => "too big",
+            ParseError::Binary => "binary",
+            ParseError::UnknownExtension => "unknown extension",
+            ParseError::Encrypted => "encrypted",
+            ParseError::Decode(..) => "could not be decoded",
+            ParseError::Io(..) => "could not be read"
+        }
     }
+}
 
-    pub fn new(milestones_tx: Sender::<Signal>, docs_count: usize) -> Self {
-        Model {
-            count: 0,
-            milestones_tx,
-            milestones: Self::calculate_milestones(docs_count),
-            docs: Docs::with_capacity_and_hasher(docs_count, RandomState::default()),
-            df: HashMap::with_capacity_and_hasher(docs_count * 128, RandomState::default())
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::TooBig => write!(f, "file is too big"),
+            ParseError::Binary => write!(f, "not parsing binary files"),
+            ParseError::UnknownExtension => write!(f, "unknown extension"),
+            ParseError::Encrypted => write!(f, "{Encrypted}"),
+            ParseError::Decode(err) | ParseError::Io(err) => write!(f, "{err}")
         }
     }
+}
 
-    pub fn search(&self, query: &str) -> Ranks {
-        let tokens = query.split(SPLIT_CHARACTERS)
-            .filter_map(prepare_word)
-            .collect::<Vec<_>>();
+impl std::error::Error for ParseError {}
 
-        let mut ranks = self.docs.par_iter().filter_map(|(path, doc)| {
-            let rank = tokens.iter().map(|token| {
-                Self::tf(token, doc) * self.idf(token)
-            }).sum::<f32>();
+impl From<IoError> for ParseError {
+    #[inline]
+    fn from(err: IoError) -> Self {
+        match err.kind() {
+            IoErrorKind::InvalidData if err.get_ref().is_some_and(|inner| inner.is::<Encrypted>()) => ParseError::Encrypted,
+            IoErrorKind::InvalidData => ParseError::Decode(err),
+            _ => ParseError::Io(err)
+        }
+    }
+}
 
-            if !rank.is_nan() {
-                Some((*path, rank))
-            } else {
-                None
+#[inline]
+fn parse_text<T: ParseFn>(file_path: &Path, md: &Metadata, config: &ParseConfig) -> Result::<Text, ParseError> {
+    if is_binary(file_path, md)? {
+        return Err(ParseError::Binary)
+    }
+    Ok(T::parse(file_path, config.max_text_len)?)
+}
+
+#[derive(Clone)]
+pub struct ParseConfig {
+    // index only the comments and docstrings of source code files
+    pub comments_only: bool,
+    // the first row of CSV and TSV files is the names of the columns, indexed as tags rather than as text
+    pub csv_headers: bool,
+    pub pdf_filter: PdfFilter,
+    // how long a single PDF may take to extract, `None` means no limit
+    pub pdf_timeout: Option::<Duration>,
+    // the most bytes of text a plain text, source code, HTML, markdown, XML, RTF, DOCX or EPUB document, a file or an entry of an
+    // archive, may have in memory, the ones with more are `InvalidData`. `None` leaves it to the size limit of files
+    // (`GIG`), see `DEFAULT_MAX_TEXT_LEN`
+    pub max_text_len: Option::<usize>
+}
+
+impl ParseConfig {
+    // every option that changes what a file is parsed to, what a `Saved` index and a `Checkpoint` are checked against,
+    // the `ocr` feature included. `pdf_timeout` isn't one, what it cuts a PDF short of depends on the machine
+    pub(crate) fn fingerprint(&self) -> String {
+        let sorted = |list: &[String]| {
+            let mut list = list.iter().map(String::as_str).collect::<Vec::<_>>();
+            list.sort_unstable();
+            list.join(",")
+        };
+        format!(
+            "comments_only={comments_only} csv_headers={csv_headers} pdf_ignore={pdf_ignore} pdf_strip_keys={pdf_strip_keys} \
+             max_text_len={max_text_len:?} ocr={ocr}",
+            comments_only = self.comments_only,
+            csv_headers = self.csv_headers,
+            pdf_ignore = sorted(&self.pdf_filter.ignore),
+            pdf_strip_keys = sorted(&self.pdf_filter.strip_keys),
+            max_text_len = self.max_text_len,
+            ocr = cfg!(feature = "ocr")
+        )
+    }
+}
+
+impl Default for ParseConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            comments_only: false,
+            csv_headers: false,
+            pdf_filter: PdfFilter::default(),
+            pdf_timeout: None,
+            max_text_len: Some(DEFAULT_MAX_TEXT_LEN)
+        }
+    }
+}
+
+// plain text of a single file, without any of the indexing, extracted as its extension says (`pdf`, `html`, `md`,
+// `docx`, `epub`, `rtf`, `csv`, plain text and code, any of them gzipped). other extensions are `UnknownExtension`
+#[inline]
+pub fn extract_text(path: &Path) -> Result::<String, ParseError> {
+    parse(path, &ParseConfig::default()).map(|(text, _)| text.body)
+}
+
+// returns the text along with the number of parts of the file (PDF pages, EPUB chapters) that could not be extracted
+#[inline]
+pub fn parse(file_path: &Path, config: &ParseConfig) -> Result::<(Text, usize), ParseError> {
+    let ext = file_path.extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default();
+
+    let md = metadata(file_path)?;
+    if md.len() >= GIG {
+        return Err(ParseError::TooBig)
+    }
+
+    let mut failed = 0;
+    let text = match ext {
+        _ if config.comments_only && comment_syntax(ext).is_some() => parse_text::<Code>(file_path, &md, config),
+        "pdf" => Pdf::parse_lossy(file_path, &config.pdf_filter, config.pdf_timeout).map(|(text, n)| { failed = n; text }).map_err(ParseError::from),
+        "gz" => parse_bytes(&fs::read(file_path)?, file_path, config).map(|(text, n)| { failed = n; text }),
+        "html" => parse_text::<Html>(file_path, &md, config),
+        "md" | "markdown" => parse_text::<Markdown>(file_path, &md, config),
+        "xml" | "xhtml" => parse_text::<Xml>(file_path, &md, config),
+        "docx" => Docx::parse(file_path, config.max_text_len).map_err(ParseError::from),
+        "rtf" => parse_text::<Rtf>(file_path, &md, config),
+        "csv" | "tsv" if config.csv_headers => parse_text::<Csv<true>>(file_path, &md, config),
+        "csv" | "tsv" => parse_text::<Csv<false>>(file_path, &md, config),
+        "epub" => Epub::parse(file_path, config.max_text_len).map(|(text, n)| { failed = n; text }).map_err(ParseError::from),
+        _ if is_text_extension(ext) => parse_text::<Txt>(file_path, &md, config),
+        _ => Err(ParseError::UnknownExtension)
+    }?;
+
+    Ok((sanitized(text), failed))
+}
+
+#[inline]
+fn sanitized(text: Text) -> Text {
+    Text {
+        title: text.title.map(sanitize),
+        tags: text.tags.into_iter().map(sanitize).collect(),
+        body: sanitize(text.body),
+        analyzer: text.analyzer
+    }
+}
+
+// the contents of a file that isn't on disk by itself, a gzipped file or an entry of an archive, parsed by the
+// extension of `name` like `parse` does. `.gz` is decompressed to at most `GIG`, the text isn't sanitized yet
+fn parse_bytes(bytes: &[u8], name: &Path, config: &ParseConfig) -> Result::<(Text, usize), ParseError> {
+    let ext = name.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+
+    let parse = match ext {
+        "gz" => {
+            let mut inflated = Vec::new();
+            // a corrupt or truncated stream is a file that can't be decoded, not one that can't be read
+            GzDecoder::new(bytes).take(GIG).read_to_end(&mut inflated).map_err(|err| match err.kind() {
+                IoErrorKind::InvalidInput | IoErrorKind::UnexpectedEof => IoError::new(IoErrorKind::InvalidData, err),
+                _ => err
+            })?;
+            if inflated.len() as u64 >= GIG {
+                return Err(ParseError::TooBig)
             }
-        }).collect::<Vec::<_>>();
+            return parse_bytes(&inflated, Path::new(name.file_stem().unwrap_or_default()), config)
+        },
+        "pdf" => return Ok(Pdf::parse_mem(bytes, config.pdf_timeout)?),
+        "docx" => return Ok((Docx::parse_mem(bytes, config.max_text_len)?, 0)),
+        "epub" => return Ok(Epub::parse_mem(bytes, config.max_text_len)?),
+        _ if config.comments_only && comment_syntax(ext).is_some() => Code::parse_str,
+        "html" => Html::parse_str,
+        "md" | "markdown" => Markdown::parse_str,
+        "xml" | "xhtml" => Xml::parse_str,
+        "rtf" => Rtf::parse_str,
+        "csv" | "tsv" if config.csv_headers => Csv::<true>::parse_str,
+        "csv" | "tsv" => Csv::<false>::parse_str,
+        _ if is_text_extension(ext) => Txt::parse_str,
+        _ => return Err(ParseError::UnknownExtension)
+    };
 
-        ranks.par_sort_unstable_by(|a, b| unsafe { b.1.partial_cmp(&a.1).unwrap_unchecked() });
-        ranks
+    if is_binary_bytes(&bytes[..bytes.len().min(SNIFF_LEN)]) {
+        return Err(ParseError::Binary)
     }
+    check_text_len(bytes.len(), config.max_text_len)?;
 
-    fn print_progress(&self) {
-        self.milestones.iter().for_each(|(count, percentage)| {
-            if self.count.eq(count) {
-                self.milestones_tx.send(*percentage).unwrap();
-                return
+    let input = str::from_utf8(bytes).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?;
+    Ok((parse(input, ext)?, 0))
+}
+
+// the text of every entry of the archive at `file_path` that `parse` would index as a file of its own, each under
+// its `archive::virtual_path`. entries of other kinds are passed over the way the walk passes over such files,
+// along with the number of entries that could not be parsed, read or were too big
+pub fn parse_archive(file_path: &Path, kind: archive::Kind, config: &ParseConfig) -> Result::<(Vec::<(PathBuf, Text)>, usize), ParseError> {
+    let mut texts = Vec::new();
+    let mut failed = 0;
+    let skipped = archive::for_each_entry(file_path, kind, |name, bytes| {
+        if !archive::is_addressable(file_path, name) {
+            warn!("skipping `{name}` in {file_path:?}, there is no path to index it under");
+            failed += 1;
+            return
+        }
+        match parse_bytes(&bytes, Path::new(name), config) {
+            Ok((text, n)) => {
+                failed += n;
+                texts.push((archive::virtual_path(file_path, name), sanitized(text)))
+            },
+            Err(ParseError::UnknownExtension | ParseError::Binary) => {},
+            Err(err) => {
+                warn!("could not parse `{name}` in {file_path:?}: {err}");
+                failed += 1
             }
-        })
+        }
+    })?;
+    Ok((texts, failed + skipped))
+}
+
+// extensions of the files that are indexed as they are
+#[inline]
+fn is_text_extension(ext: &str) -> bool {
+    matches!(ext,
+          "txt"      | "css"     | "js"       | "json"    | "rs"       | "py"
+        | "rb"       | "java"    | "c"        | "cpp"     | "go"       | "sh"
+        | "md"       | "yaml"    | "ini"      | "sql"     | "csv"      | "log"
+        | "makefile" | "bat"     | "php"      | "pl"      | "asm"      | "dockerfile"
+        | "erb"      | "proto"   | "tf"       | "tfvars"  | "toml"     | "v"
+        | "rspec"    | "ml"      | "dart"     | "lua"     | "coffee"   | "scss"
+        | "less"     | "svg"     | "acl"      | "patch"   | "diff"     | "zsh"
+        | "r"        | "groovy"  | "h"        | "hpp"     | "c++"      | "nasm"
+        | "wxml"     | "wxs"     | "cfg"      | "zig"     | "env"      | "d"
+        | "f90"      | "f"       | "jl"       | "cabal"   | "hs"       | "nim"
+        | "sol"      | "swift"   | "mxml"     | "clj"     | "cljs"     | "lisp"
+        | "el"       | "sml"     | "styl"     | "nut"     | "wsgi"     | "raku"
+        | "q"        | "sage"    | "pike"     | "xqy"     | "slim"     | "hx"
+        | "pmd"      | "gsql"    | "cs"       | "ts"      | "gitignore"| "in"
+    )
+}
+
+// the name documents read from stdin are indexed and shown under
+pub const STDIN_PATH: &str = "<stdin>";
+
+// a single document from a reader of unknown length (stdin), under the same guards as files:
+// reading stops right after the size limit so a runaway pipe is rejected without being buffered whole
+pub fn parse_reader(reader: impl Read) -> Result::<Text, ParseError> {
+    let mut bytes = Vec::new();
+    reader.take(GIG).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 >= GIG {
+        return Err(ParseError::TooBig)
     }
 
-    pub fn add_document(&mut self, file_path: &'a PathBuf, content: &'a str) {
-        self.rm_document(&file_path);
+    if is_binary_bytes(&bytes[..bytes.len().min(SNIFF_LEN)]) {
+        return Err(ParseError::Binary)
+    }
 
-        let doc = Doc::new(content);
+    let text = String::from_utf8(bytes).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?;
+    Ok(sanitize(text).into())
+}
 
-        doc.tf.keys().for_each(|t| {
-            if let Some(f) = self.df.get_mut(t) {
-                *f += 1;
-            } else {
-                self.df.insert(t, 1);
-            }
-        });
+// stray NULs and other control characters (PDFs, odd encodings) become spaces,
+// and so do tabs and newlines, so that snippets come out on a single line
+#[inline]
+fn sanitize(text: String) -> String {
+    if !text.contains(char::is_control) { return text }
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
 
-        self.count += 1;
-        self.print_progress();
-        self.docs.insert(file_path, doc);
+pub struct Doc<'a> {
+    // position of the document in every posting list it's in
+    id: u32,
+    // term frequencies of all the fields together, which is what unscoped terms match
+    tf: TermFreq<'a>,
+    // term frequencies of the title and the path alone, the body has what's left of `tf`
+    title_tf: TermFreq<'a>,
+    tags_tf: TermFreq<'a>,
+    path_tf: TermFreq<'a>,
+    // what every word of `path_tf` counts as in `tf`, see `TokenizerConfig::path_weight`
+    path_weight: usize,
+    // of the words of the body alone, what phrases are matched against
+    positions: Positions<'a>,
+    count: usize,
+    content: Held<'a, str>
+}
+
+// the paths of the documents are held like their terms are, see `Model::add_text`
+pub type Docs<'a> = HashMap::<Held<'a, PathBuf>, Doc<'a>, HashState>;
+
+// a document containing a term, documents get increasing ids as they're added,
+// so every posting list is sorted by `id` without ever sorting it
+#[derive(Clone)]
+pub struct Posting<'a> {
+    id: u32,
+    path: Held<'a, PathBuf>,
+    // normalized term frequency, the same as `Model::tf`
+    tf: f32
+}
+
+#[derive(Default)]
+pub struct PostingList<'a> {
+    postings: Vec::<Posting<'a>>,
+    // upper bound of `tf` in `postings`, it isn't lowered when documents are removed, which keeps it
+    // a valid (if looser) bound for pruning, until `Model::finalize` makes it the highest one again
+    max_tf: f32,
+    // the same for the words of the paths alone, which is what `path:` terms score. it's the one bound of `tf`
+    // that `max_tf` doesn't cover, a path that doesn't count (`path_weight` 0) has none of its words in `tf`
+    max_path_tf: f32
+}
+
+type Postings<'a> = HashMap<Held<'a, str>, PostingList<'a>, HashState>;
+
+// min-heap entry for top-k retrieval
+struct Hit<'a>(f32, &'a PathBuf);
+
+impl PartialEq for Hit<'_> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool { self.cmp(other).is_eq() }
+}
+
+impl Eq for Hit<'_> {}
+
+impl PartialOrd for Hit<'_> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option::<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for Hit<'_> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering { other.0.total_cmp(&self.0) }
+}
+
+// position in the posting list of one query term while walking them in WAND
+struct Cursor<'b, 't, 'a> {
+    postings: &'b [Posting<'a>],
+    at: usize,
+    field: Option::<Field>,
+    term: &'t str,
+    // idf of the term times the number of times it appears in the query
+    weight: f32,
+    // the most this term can add to the score of any document
+    bound: f32
+}
+
+impl Cursor<'_, '_, '_> {
+    #[inline(always)]
+    fn id(&self) -> u32 { self.postings[self.at].id }
+
+    #[inline(always)]
+    fn done(&self) -> bool { self.at >= self.postings.len() }
+
+    // skip to the first posting with an id of at least `id`
+    #[inline]
+    fn seek(&mut self, id: u32) {
+        self.at += self.postings[self.at..].partition_point(|p| p.id < id)
+    }
+}
+
+#[inline]
+pub fn string_to_str(string: String) -> &'static str {
+    Box::leak(string.into_boxed_str())
+}
+
+pub const DEFAULT_MAX_TOKEN_LEN: usize = 64;
+// see `Model::prune`
+pub const DEFAULT_MIN_DF: usize = 1;
+pub const DEFAULT_PATH_WEIGHT: usize = 1;
+
+#[derive(Clone, Copy)]
+pub struct TokenizerConfig {
+    // keep the case of the tokens, applied to documents and queries alike.
+    // stemming still happens, but the english stemmer only knows lowercase suffixes,
+    // so e.g. `RUNNING` stays `RUNNING` while `Running` becomes `Run`
+    pub case_sensitive: bool,
+    // also emit the parts of `state-of-the-art`, `snake_case` and `camelCase` words, next to the whole word
+    pub split_compounds: bool,
+    // keep the original (lowercased, unstemmed) words next to their stems so they can be matched exactly,
+    // every distinct word of a document is stored twice then
+    pub keep_originals: bool,
+    // longer words (in bytes) are dropped from documents and queries alike, 0 means no limit.
+    // raising it keeps long identifiers, URLs and hashes, but every one of them costs memory in `df` and `postings`
+    pub max_token_len: usize,
+    // how many occurrences every word of a document's path (`2023/invoices/acme.pdf` -> `2023`, `invoic`, `acm`, `pdf`)
+    // counts as in the document itself, so that unscoped queries match on the directories and the name of a file even
+    // when it has no text of its own. 0 leaves the path out of the document, it's still searchable with `path:`
+    pub path_weight: usize,
+    // what words are reduced to once they're normalized, for the documents and queries that don't name one
+    pub analyzer: Analyzer,
+    // words that are neither indexed nor searched for, `None` keeps every word
+    pub stop_words: Option::<&'static StopWords>
+}
+
+impl TokenizerConfig {
+    // every option that changes what the words of a text are tokenized to, what a `Saved` index is checked against
+    fn fingerprint(&self) -> String {
+        let mut stop_words = self.stop_words.map(|stop_words| stop_words.0.iter().map(String::as_str).collect::<Vec::<_>>()).unwrap_or_default();
+        stop_words.sort_unstable();
+        format!(
+            "case_sensitive={case_sensitive} split_compounds={split_compounds} keep_originals={keep_originals} max_token_len={max_token_len} \
+             path_weight={path_weight} analyzer={analyzer:?} stop_words={stop_words}",
+            case_sensitive = self.case_sensitive,
+            split_compounds = self.split_compounds,
+            keep_originals = self.keep_originals,
+            max_token_len = self.max_token_len,
+            path_weight = self.path_weight,
+            analyzer = self.analyzer,
+            stop_words = stop_words.join(",")
+        )
     }
 
+    // whether a normalized word, or the term it's stemmed to, is a stop word
     #[inline]
-    pub fn add_contents(&mut self, contents: &'a Contents) {
-        let have_big_files = contents.iter()
-            .take(contents.len() / 2)
-            .any(|content| content.1.len() >= GIG as _);
+    fn is_stop_word(&self, word: &str, term: &str) -> bool {
+        self.stop_words.is_some_and(|stop_words| stop_words.0.contains(word) || stop_words.0.contains(term))
+    }
+}
 
-        if have_big_files {
-            let zelf = am!(self);
-            contents.par_iter().for_each(|(file_path, content)| {
-                let mut zelf = unsafe { zelf.lock().unwrap_unchecked() };
-                zelf.add_document(file_path, content);
-            });
-            zelf.lock().unwrap().milestones_tx.send(SIGNAL_STOP).unwrap();
-        } else {
-            contents.iter().for_each(|(file_path, content)| {
-                self.add_document(file_path, content);
-            });
-            self.milestones_tx.send(SIGNAL_STOP).unwrap();
+impl Default for TokenizerConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            case_sensitive: false,
+            split_compounds: false,
+            keep_originals: false,
+            max_token_len: DEFAULT_MAX_TOKEN_LEN,
+            path_weight: DEFAULT_PATH_WEIGHT,
+            analyzer: Analyzer::default(),
+            stop_words: None
         }
     }
+}
+
+// the stop words of `StopWords::english`, the ones Lucene drops by default
+pub const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it", "no", "not",
+    "of", "on", "or", "such", "that", "the", "their", "then", "there", "these", "they", "this", "to", "was", "will", "with"
+];
 
+// words too common to tell documents apart, which would only fill `df` and the posting lists. they're compared
+// lowercased, with the words of documents and queries both as they are and stemmed, so `having` goes with `have`.
+// with `case_sensitive` only the lowercase words are stop words
+pub struct StopWords(HashSet::<String, HashState>);
+
+impl StopWords {
     #[inline]
-    fn rm_document(&mut self, file_path: &PathBuf) {
-        if let Some(doc) = self.docs.remove(file_path) {
-            doc.tf.keys().for_each(|t| {
-                self.df.entry(t).and_modify(|f| *f -= 1);
-            });
+    pub fn new<'w>(words: impl IntoIterator::<Item = &'w str>) -> Self {
+        StopWords(words.into_iter().map(str::trim).filter(|word| !word.is_empty()).map(str::to_lowercase).collect())
+    }
+
+    #[inline]
+    pub fn english() -> Self {
+        Self::new(ENGLISH_STOP_WORDS.iter().copied())
+    }
+
+    // a word per line, blank lines and lines starting with `#` are skipped
+    pub fn from_file(path: &Path) -> IoResult::<Self> {
+        let list = fs::read_to_string(path)?;
+        Ok(Self::new(list.lines().filter(|line| !line.trim_start().starts_with('#'))))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+// what terms made of digits only (years, ids, amounts) weigh in the scores, next to their idf. they're indexed
+// either way, so `AND` and phrases still find documents by them
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Numbers {
+    #[default]
+    Keep,
+    // twice the weight, for logs and the like where an id or a status code is what's searched for
+    Boost,
+    // half the weight, for prose where they're mostly noise
+    Demote,
+    // no weight at all, a number adds nothing to a score
+    Exclude
+}
+
+impl Numbers {
+    const NAMES: &[(&str, Numbers)] = &[("keep", Numbers::Keep), ("boost", Numbers::Boost), ("demote", Numbers::Demote), ("exclude", Numbers::Exclude)];
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option::<Numbers> {
+        Self::NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, numbers)| *numbers)
+    }
+
+    #[inline]
+    fn weight(self) -> f32 {
+        match self {
+            Numbers::Keep => 1.0,
+            Numbers::Boost => 2.0,
+            Numbers::Demote => 0.5,
+            Numbers::Exclude => 0.0
         }
     }
+}
 
-    #[inline(always)]
-    fn tf(t: &str, doc: &Doc) -> f32 {
-        *doc.tf.get(t).unwrap_or(&0) as f32 / doc.count as f32
+// the parameters of `Model::search_bm25`: `k1` is how quickly more of a term stops counting for more,
+// `b` how much a document's length is held against it, from 0 (not at all) to 1 (in full)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bm25 {
+    pub k1: f32,
+    pub b: f32
+}
+
+impl Default for Bm25 {
+    #[inline]
+    fn default() -> Self {
+        Bm25 { k1: 1.2, b: 0.75 }
     }
+}
 
-    #[inline(always)]
-    fn idf(&self, term: &str) -> f32 {
-        (self.docs.len() as f32 / *self.df.get(term).unwrap_or(&0) as f32).log10()
+// the original of a number is a number too
+#[inline]
+fn is_numeric(term: &str) -> bool {
+    let term = term.strip_prefix(ORIGINAL_PREFIX).unwrap_or(term);
+    !term.is_empty() && term.chars().all(char::is_numeric)
+}
+
+// how the normalized words of a text are reduced to the terms they're indexed as, the model's unless the text or the
+// query names another. text that isn't english is better off `Unstemmed`
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Analyzer {
+    #[default]
+    English,
+    // lowercased (unless `case_sensitive`) and nothing else
+    Unstemmed
+}
+
+impl Analyzer {
+    const NAMES: &[(&str, Analyzer)] = &[("english", Analyzer::English), ("unstemmed", Analyzer::Unstemmed)];
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option::<Analyzer> {
+        Self::NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, analyzer)| *analyzer)
     }
 }
 
+// originals are stored under this prefix next to the stems, no stem can start
+// with it since words are trimmed to alphanumerics before they're stemmed
+const ORIGINAL_PREFIX: char = '=';
+
+// parts of a hyphenated, snake_case, camelCase or `HTTPServer`-like word, any other
+// inner punctuation (`foo::bar`, `f(x)`) splits too. Empty if there's nothing to split
+fn split_compound(word: &str) -> Vec::<&str> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    let mut parts = Vec::new();
+    word.split(|c: char| !c.is_alphanumeric()).for_each(|piece| {
+        let chars = piece.char_indices().collect::<Vec::<_>>();
+        let mut start = 0;
+        for k in 1..chars.len() {
+            let (i, c) = chars[k];
+            let prev = chars[k - 1].1;
+            let next_is_lower = chars.get(k + 1).is_some_and(|(_, c)| c.is_lowercase());
+            if c.is_uppercase() && (prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower)) {
+                parts.push(&piece[start..i]);
+                start = i
+            }
+        }
+        parts.push(&piece[start..])
+    });
+
+    parts.retain(|part| !part.is_empty());
+    if parts.len() > 1 { parts } else { Vec::new() }
+}
+
+// the word itself and, if configured so, its parts. The parts are
+// computed from the word as it is, so camelCase survives
 #[inline]
-pub fn dir_get_contents(dir_path: &str) -> Contents {
-    let dir = DirRec::new(dir_path);
-    dir.into_iter()
-        .par_bridge()
-        .filter_map(|e| {
-            parse(&e).ok().map(|r| (e, r))
-        }).collect()
+fn word_and_parts<'a>(word: &'a str, config: &TokenizerConfig) -> impl Iterator::<Item = &'a str> {
+    let parts = if config.split_compounds { split_compound(word) } else { Vec::new() };
+    iter::once(word).chain(parts)
+}
+
+// why a word didn't make it into the index or a query
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Discard {
+    // nothing alphanumeric in it
+    Empty,
+    // longer than `TokenizerConfig::max_token_len`
+    TooLong,
+    // one of `TokenizerConfig::stop_words`
+    StopWord
+}
+
+impl Discard {
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Discard::Empty => "empty",
+            Discard::TooLong => "too long",
+            Discard::StopWord => "stop word"
+        }
+    }
+}
+
+#[inline]
+fn check_word<'a>(word: &'a str, config: &TokenizerConfig) -> Result::<&'a str, Discard> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    if word.is_empty() { return Err(Discard::Empty) }
+    if config.max_token_len > 0 && word.len() > config.max_token_len { return Err(Discard::TooLong) }
+    Ok(word)
+}
+
+// the word itself when it's lowercase already, a lowercased copy when it has letters that have a lowercase of their
+// own (`A`, `É`, `Ж`, `Σ`). the text a word is a slice of is never written to, it's shared by every slice of it
+#[inline]
+fn lowercase(word: &str) -> Cow::<'_, str> {
+    if word.is_ascii() {
+        if !word.bytes().any(|byte| byte.is_ascii_uppercase()) { return Cow::Borrowed(word) }
+        return Cow::Owned(word.to_ascii_lowercase())
+    }
+    if word.chars().all(|c| c.to_lowercase().eq(iter::once(c))) { return Cow::Borrowed(word) }
+    Cow::Owned(word.to_lowercase())
+}
+
+// trim and lowercase word avoiding copying where it can
+#[inline]
+fn normalize_word<'a>(word: &'a str, config: &TokenizerConfig) -> Option::<Cow::<'a, str>> {
+    let word = check_word(word, config).ok()?;
+    Some(if config.case_sensitive { Cow::Borrowed(word) } else { lowercase(word) })
+}
+
+#[inline]
+fn stem_normalized(word: &str, analyzer: Analyzer) -> Cow::<'_, str> {
+    match analyzer {
+        Analyzer::English => {
+            let mut env = SnowballEnv::create(word);
+            stem(&mut env);
+            env.get_current()
+        },
+        Analyzer::Unstemmed => Cow::Borrowed(word)
+    }
+}
+
+// the stem of a normalized word, borrowing from what the word borrows from if it can
+#[inline]
+fn stem_cow<'a>(word: &Cow::<'a, str>, analyzer: Analyzer) -> Cow::<'a, str> {
+    match word {
+        Cow::Borrowed(word) => stem_normalized(word, analyzer),
+        Cow::Owned(word) => Cow::Owned(stem_normalized(word, analyzer).into_owned())
+    }
+}
+
+// trim, stem and lowercase word avoiding copying
+#[inline]
+fn stem_word<'a>(word: &'a str, config: &TokenizerConfig) -> Option::<Cow::<'a, str>> {
+    normalize_word(word, config).map(|word| stem_cow(&word, config.analyzer))
+}
+
+#[inline]
+fn original_key(word: &str) -> String {
+    format!("{ORIGINAL_PREFIX}{word}")
+}
+
+// how many edits away from a word of `len` characters what it's corrected to may be,
+// short words have too many neighbours for a correction of them to mean anything
+#[inline]
+fn max_edits(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2
+    }
+}
+
+// the optimal string alignment distance between `a` and `b`, in characters: inserting, deleting or replacing one
+// or swapping two next to each other is an edit each. `None` as soon as it's sure to be more than `max`
+fn edit_distance(a: &str, b: &str, max: usize) -> Option::<usize> {
+    let (a, b) = (a.chars().collect::<Vec::<_>>(), b.chars().collect::<Vec::<_>>());
+    if a.len().abs_diff(b.len()) > max { return None }
+
+    // the rows of `a[..i - 2]`, `a[..i - 1]` and `a[..i]`
+    let mut before = vec![0; b.len() + 1];
+    let mut prev = (0..=b.len()).collect::<Vec::<_>>();
+    let mut row = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        row[0] = i;
+        for j in 1..=b.len() {
+            let replace = prev[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = replace.min(prev[j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1)
+            }
+        }
+        if row.iter().all(|distance| *distance > max) { return None }
+        mem::swap(&mut before, &mut prev);
+        mem::swap(&mut prev, &mut row);
+    }
+    Some(prev[b.len()]).filter(|distance| *distance <= max)
+}
+
+#[inline(always)]
+fn is_split_character(c: char) -> bool {
+    c.is_whitespace() || SPLIT_CHARACTERS.contains(&c)
+}
+
+// the words of a body, a title, a query or a phrase, split the same way for all of them,
+// so whatever a word of a document turns into the same word of a query turns into too
+#[inline]
+fn split_words(text: &str) -> impl Iterator::<Item = &str> {
+    text.split(is_split_character)
+}
+
+// at most `len` bytes of `text` starting a bit before `at`, trimmed to whole words
+#[inline]
+fn window(text: &str, at: usize, len: usize) -> &str {
+    excerpt(text, at.saturating_sub(len / 4), at, at, len)
+}
+
+// at most `len` bytes of `text` from `start` on, or sooner when that runs past the end of `text`,
+// trimmed to whole words without cutting into `from..to`, which must fit into `len`
+fn excerpt(text: &str, start: usize, from: usize, to: usize, len: usize) -> &str {
+    let mut end = (start + len).min(text.len());
+    let mut start = start.min(end.saturating_sub(len));
+
+    while !text.is_char_boundary(start) { start += 1 }
+    while !text.is_char_boundary(end) { end -= 1 }
+
+    if start > 0 && !text[..start].ends_with(is_split_character) {
+        if let Some((i, c)) = text[start..from].char_indices().find(|(_, c)| is_split_character(*c)) {
+            start += i + c.len_utf8()
+        }
+    }
+
+    if end < text.len() && !text[end..].starts_with(is_split_character) {
+        if let Some(i) = text[to..end].rfind(is_split_character) {
+            end = to + i
+        }
+    }
+
+    text[start..end].trim_matches(is_split_character)
+}
+
+// bytes of a document's body that `Model::snippet_with` looks for matches in, the rest of a bigger body is never scanned
+pub const MAX_PASSAGE_SCAN: usize = 1024 * 1024;
+
+// how `Model::snippet_with` picks the excerpt of a document
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum SnippetMode {
+    // around the first match, the same as `Model::snippet`
+    #[default]
+    First,
+    // where the most distinct words of the query are closest together
+    Densest,
+    // the two densest windows that don't overlap, in the order they're in the document, joined by `PASSAGE_SEPARATOR`.
+    // each gets half the length, a document with a single cluster of matches only gets the one
+    Merged
+}
+
+pub const PASSAGE_SEPARATOR: &str = " … ";
+
+impl SnippetMode {
+    const NAMES: &[(&str, SnippetMode)] = &[("first", SnippetMode::First), ("densest", SnippetMode::Densest), ("merged", SnippetMode::Merged)];
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option::<SnippetMode> {
+        Self::NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, mode)| *mode)
+    }
+}
+
+// where a word of the body matched which of the query's words: `at..to` in bytes and the index of the word
+type Match = (usize, usize, usize);
+
+// the `from..to` of the densest run of `hits` that spans at most `len` bytes and doesn't overlap `taken`:
+// the most distinct words first, then the most matches. two pointers over the hits, so it's linear in them
+fn densest(hits: &[Match], words: usize, len: usize, taken: Option::<(usize, usize)>) -> Option::<(usize, usize)> {
+    // far enough apart for their excerpts not to overlap either
+    let free = |(from, to): (usize, usize)| taken.is_none_or(|(start, end)| to + len <= start || from >= end + len);
+    let mut counts = vec![0usize; words];
+    let (mut distinct, mut best) = (0, None::<((usize, usize), (usize, usize))>);
+    let mut first = 0;
+    for (last, &(_, to, word)) in hits.iter().enumerate() {
+        if counts[word] == 0 { distinct += 1 }
+        counts[word] += 1;
+        while first <= last && to - hits[first].0 > len {
+            let word = hits[first].2;
+            counts[word] -= 1;
+            if counts[word] == 0 { distinct -= 1 }
+            first += 1
+        }
+        if first > last { continue }
+
+        let span = (hits[first].0, to);
+        let score = (distinct, last + 1 - first);
+        if free(span) && best.is_none_or(|(best, _)| score > best) {
+            best = Some((score, span))
+        }
+    }
+    best.map(|(_, span)| span)
+}
+
+// the terms `text` is indexed and searched as, in order, the way the words of a document's body and those of
+// a query are tokenized. the originals `keep_originals` indexes next to them aren't among them, they're the words
+// with `=` in front (see `Query::exact`)
+pub fn tokenize(text: &str, config: &TokenizerConfig) -> Vec::<String> {
+    tokens(split_words(text), config).map(|(_, _, term)| term.into_owned()).collect()
+}
+
+// every word of `words` and its parts, as they are, normalized and stemmed, the words that are never indexed and
+// the stop words left out. the one way from words to terms, for documents and queries alike
+#[inline]
+fn tokens<'a, 'c>(words: impl Iterator::<Item = &'a str> + 'c, config: &'c TokenizerConfig) -> impl Iterator::<Item = (&'a str, Cow::<'a, str>, Cow::<'a, str>)> + 'c
+where
+    'a: 'c
+{
+    words.flat_map(|word| word_and_parts(word, config))
+        .filter_map(|part| Some((part, normalize_word(part, config)?)))
+        .map(|(part, word)| {
+            let term = stem_cow(&word, config.analyzer);
+            (part, word, term)
+        })
+        .filter(|(_, word, term)| !config.is_stop_word(word, term))
+}
+
+// counts the stems only, kept originals don't make a document any longer. with `positions` every word is recorded
+// under its term and its original, `hold` makes the key of a term the first time the document has it
+fn count_words<'t, 'a>(words: impl Iterator::<Item = &'t str>, config: &TokenizerConfig, tf: &mut TermFreq<'a>, mut positions: Option::<&mut Positions<'a>>, hold: &mut impl FnMut(Cow::<'t, str>) -> Held<'a, str>) -> usize {
+    let (mut count, mut at) = (0, 0);
+    let mut counted = |tf: &mut TermFreq<'a>, term: Cow::<'t, str>| match tf.get_key_value_mut(&*term) {
+        Some((key, n)) => { *n += 1; key.clone() },
+        None => { let key = hold(term); tf.insert(key.clone(), 1); key }
+    };
+    for word in words {
+        let mut indexed = false;
+        for (part, normalized, term) in tokens(iter::once(word), config) {
+            let whole = part.len() == word.len();
+            let original = config.keep_originals.then(|| counted(tf, Cow::Owned(original_key(&normalized))));
+            let term = counted(tf, term);
+            count += 1;
+
+            if let Some(positions) = positions.as_deref_mut().filter(|_| whole) {
+                original.into_iter().chain(iter::once(term)).for_each(|key| positions.entry(key).or_default().push(at));
+                indexed = true
+            }
+        }
+        at += indexed as u32
+    }
+    count
+}
+
+// paths are split on separators and every other non-alphanumeric character
+#[inline]
+fn path_words(path: &str) -> impl Iterator::<Item = &str> {
+    path.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty())
+}
+
+// the words of a leaf of the query with the field they're scoped to, the words of a phrase are never scoped
+fn leaf_words<'q>(leaf: &Expr<'q>) -> Result::<Vec::<(Option::<Field>, &'q str)>, QueryError> {
+    let mut words = Vec::new();
+    match leaf {
+        Expr::Word(text) => for word in split_words(text).filter(|word| !word.is_empty()) {
+            let (field, word) = split_field(word)?;
+            if field == Some(Field::Path) {
+                words.extend(path_words(word).map(|word| (field, word)))
+            } else {
+                words.push((field, word))
+            }
+        },
+        Expr::Phrase(text) => words.extend(split_words(text).filter(|word| !word.is_empty()).map(|word| (None, word))),
+        Expr::And(..) | Expr::Or(..) => {}
+    }
+    Ok(words)
+}
+
+impl<'a> Doc<'a> {
+    // a document of the body alone, that has terms of its own only
+    #[inline]
+    pub fn new(content: &'a str, config: &TokenizerConfig) -> Self {
+        Self::tokenized(Held::Borrowed(content), config, &DocFreq::default())
+    }
+
+    // the terms that `known` has already are taken from it, so the documents of a model share them,
+    // the rest are slices of a body the model borrows and copies of one it owns
+    fn tokenized(content: Held<'a, str>, config: &TokenizerConfig, known: &DocFreq<'a>) -> Self {
+        let mut tf = TermFreq::with_capacity_and_hasher(128, HashState::default());
+        let mut positions = Positions::default();
+        let count = match &content {
+            Held::Borrowed(body) => count_words(split_words(body), config, &mut tf, Some(&mut positions), &mut |term| Held::lent(term, known)),
+            Held::Owned(body) => count_words(split_words(body), config, &mut tf, Some(&mut positions), &mut |term| Held::copied(&term, known))
+        };
+        Doc {
+            id: 0,
+            tf,
+            title_tf: TermFreq::default(),
+            tags_tf: TermFreq::default(),
+            path_tf: TermFreq::default(),
+            path_weight: 0,
+            positions,
+            count,
+            content
+        }
+    }
+
+    // index the title, the tags and the path too, all of them count as part of the document for unscoped terms,
+    // the words of the path `path_weight` times each. the terms of a path that doesn't count are still
+    // in `tf`, with no occurrences, so that the document is in their posting lists for `path:` to find
+    pub fn with_fields(self, title: Option::<&'a str>, tags: &'a [String], path: &'a Path, config: &TokenizerConfig, known: &DocFreq<'a>) -> Self {
+        match path.to_string_lossy() {
+            Cow::Borrowed(path) => self.fields(title, tags, path, config, &mut |term| Held::lent(term, known)),
+            // a path that isn't UTF-8 is split as its lossy copy, the terms of which are copies too
+            Cow::Owned(path) => self.fields(title, tags, &path, config, &mut |term| Held::copied(&term, known))
+        }
+    }
+
+    // `with_fields` of a document the model owns, the terms of which are all copies
+    #[inline]
+    fn with_copied_fields(self, title: Option::<&str>, tags: &[String], path: &Path, config: &TokenizerConfig, known: &DocFreq<'a>) -> Self {
+        self.fields(title, tags, &path.to_string_lossy(), config, &mut |term| Held::copied(&term, known))
+    }
+
+    fn fields<'t>(mut self, title: Option::<&'t str>, tags: &'t [String], path: &'t str, config: &TokenizerConfig, hold: &mut impl FnMut(Cow::<'t, str>) -> Held<'a, str>) -> Self {
+        if let Some(title) = title {
+            count_words(split_words(title), config, &mut self.title_tf, None, hold);
+        }
+        count_words(tags.iter().flat_map(|tag| split_words(tag)), config, &mut self.tags_tf, None, hold);
+        count_words(path_words(path), config, &mut self.path_tf, None, hold);
+        self.path_weight = config.path_weight;
+
+        let Doc { tf, title_tf, tags_tf, path_tf, path_weight, count, .. } = &mut self;
+        let weighted = path_tf.iter().map(|(t, n)| (t, n * *path_weight));
+        title_tf.iter().chain(tags_tf.iter()).map(|(t, n)| (t, *n)).chain(weighted).for_each(|(t, n)| {
+            match tf.get_mut(&**t) {
+                Some(f) => *f += n,
+                None => _ = tf.insert(t.clone(), n)
+            }
+            *count += n
+        });
+
+        self
+    }
+
+    #[inline]
+    fn field_count(&self, field: Option::<Field>, t: &str) -> usize {
+        let count = |tf: &TermFreq| tf.get(t).copied().unwrap_or(0);
+        match field {
+            None => count(&self.tf),
+            Some(Field::Title) => count(&self.title_tf),
+            Some(Field::Tags) => count(&self.tags_tf),
+            Some(Field::Path) => count(&self.path_tf),
+            Some(Field::Body) => count(&self.tf) - count(&self.title_tf) - count(&self.tags_tf) - count(&self.path_tf) * self.path_weight
+        }
+    }
+}
+
+pub struct Explained<'a, 'q> {
+    pub ranks: Ranks<'a>,
+    // query words that were too long or had nothing searchable in them, in query order
+    pub discarded: Vec::<(&'q str, Discard)>,
+    // when nothing was found, the query words that were searched for along with what they were searched as,
+    // none of which is in any document
+    pub absent: Vec::<(&'q str, Cow::<'q, str>)>,
+    // when nothing was found, `Model::suggest`
+    pub suggestion: Option::<String>
+}
+
+// how often the progress of indexing is reported, see `Model::with_milestones`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Milestones {
+    // every this many percent of the documents, from 1 to 100
+    Percent(u8),
+    // every this many documents, and once they are all indexed
+    Documents(usize)
+}
+
+impl Default for Milestones {
+    #[inline]
+    fn default() -> Self {
+        Milestones::Percent(1)
+    }
+}
+
+impl Milestones {
+    // `<n>%` or `<n>` documents, neither can be 0
+    #[inline]
+    pub fn parse(value: &str) -> Option::<Milestones> {
+        match value.strip_suffix('%') {
+            Some(percent) => percent.parse().ok().filter(|percent| (1..=100).contains(percent)).map(Milestones::Percent),
+            None => value.parse().ok().filter(|n| *n > 0).map(Milestones::Documents)
+        }
+    }
+}
+
+// corpora with fewer documents than this report every one of them, whatever the milestones are
+pub const PER_DOCUMENT_BELOW: usize = 100;
+
+// the least a term that's in every document (or in more of them than `log10` can tell apart) weighs,
+// so that a common word in a query adds a little to the documents that have it instead of nothing
+pub const MIN_IDF: f32 = 0.01;
+
+pub struct Model<'a> {
+    // how many already indexed
+    count: usize,
+    // how many are going to be, what the milestones are percentages of
+    expected: usize,
+    milestones_tx: Sender::<Signal>,
+    // the counts of documents at which the percentage is sent, ascending and with a single percentage each
+    milestones: Vec::<(usize, u8)>,
+    every: Milestones,
+    tokenizer: TokenizerConfig,
+    // what bare words of queries that don't set one are combined with
+    operator: Operator,
+    numbers: Numbers,
+    bm25: Bm25,
+    // terms fewer documents have are pruned by `finalize`
+    min_df: usize,
+    next_id: u32,
+    // the words of all of `docs`, for the average length of a document that `search_bm25` normalizes by
+    total_count: usize,
+
+    pub df: DocFreq<'a>,
+    pub docs: Docs<'a>,
+    // the inverted index, term -> documents containing it, built in `add_document` next to `df`,
+    // which is the length of every list, and `docs`, which still holds the term frequencies for
+    // the removal of a document and for snippets
+    pub postings: Postings<'a>,
+    // what the walked files that the documents were parsed from were like before they were, what `save_to` writes
+    // out with them. it's filled in by whoever parses the files, `add_document` doesn't know where a text came from,
+    // and it isn't kept up to date as documents are removed and reindexed
+    pub stamps: Stamps
+}
+
+impl<'a> Model<'a> {
+    fn calculate_milestones(docs_count: usize, every: Milestones) -> Vec::<(usize, u8)> {
+        let percentage = |count: usize| (count * 100 / docs_count) as u8;
+        let counts = match every {
+            _ if docs_count < PER_DOCUMENT_BELOW => (1..=docs_count).collect::<Vec::<_>>(),
+            Milestones::Percent(percent) => (percent as usize..100).step_by(percent.max(1) as _).map(|percent| docs_count * percent / 100).collect(),
+            Milestones::Documents(n) => (n..docs_count).step_by(n.max(1)).collect()
+        };
+
+        let mut milestones = counts.into_iter()
+            .filter(|count| (1..docs_count).contains(count))
+            .chain((docs_count > 0).then_some(docs_count))
+            .map(|count| (count, percentage(count)))
+            .collect::<Vec::<_>>();
+        milestones.dedup_by_key(|(count, _)| *count);
+        milestones
+    }
+
+    pub fn new(milestones_tx: Sender::<Signal>, docs_count: usize) -> Self {
+        Model {
+            count: 0,
+            expected: docs_count,
+            milestones_tx,
+            milestones: Self::calculate_milestones(docs_count, Milestones::default()),
+            every: Milestones::default(),
+            tokenizer: TokenizerConfig::default(),
+            operator: Operator::default(),
+            numbers: Numbers::default(),
+            bm25: Bm25::default(),
+            min_df: DEFAULT_MIN_DF,
+            next_id: 0,
+            total_count: 0,
+            docs: Docs::with_capacity_and_hasher(docs_count, HashState::default()),
+            df: HashMap::with_capacity_and_hasher(docs_count * 128, HashState::default()),
+            postings: HashMap::with_capacity_and_hasher(docs_count * 128, HashState::default()),
+            stamps: Stamps::default()
+        }
+    }
+
+    // a model whose progress nobody is drawing, to be filled with `add_text` and `add_document`
+    #[inline]
+    pub fn detached() -> Self {
+        Self::new(channel().0, 0)
+    }
+
+    #[inline]
+    pub fn with_tokenizer(mut self, tokenizer: TokenizerConfig) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    // with `Operator::And` only the documents with every word of a query are found, unless the query says otherwise
+    #[inline]
+    pub fn with_operator(mut self, operator: Operator) -> Self {
+        self.operator = operator;
+        self
+    }
+
+    #[inline]
+    pub fn with_numbers(mut self, numbers: Numbers) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    // the `k1` and `b` that `search_bm25` scores with
+    #[inline]
+    pub fn with_bm25(mut self, bm25: Bm25) -> Self {
+        self.bm25 = bm25;
+        self
+    }
+
+    // the progress is sent at these, or at every document of a corpus smaller than `PER_DOCUMENT_BELOW`
+    #[inline]
+    pub fn with_milestones(mut self, every: Milestones) -> Self {
+        self.milestones = Self::calculate_milestones(self.expected, every);
+        self.every = every;
+        self
+    }
+
+    // how many documents are going to be indexed, once that's known after some of them already were. the progress
+    // they made is sent right away
+    pub fn expect(&mut self, docs_count: usize) {
+        self.expected = docs_count;
+        self.milestones = Self::calculate_milestones(docs_count, self.every);
+        let passed = self.milestones.partition_point(|(count, _)| *count <= self.count);
+        if let Some((_, percentage)) = passed.checked_sub(1).map(|at| self.milestones[at]) {
+            _ = self.milestones_tx.send(Signal::Progress(percentage))
+        }
+    }
+
+    #[inline]
+    pub fn with_min_df(mut self, min_df: usize) -> Self {
+        self.min_df = min_df;
+        self
+    }
+
+    // the words of the query along with the field they're scoped to, before they're tokenized
+    #[inline]
+    fn expr<'q>(&self, query: &Query<'q>) -> Result::<Expr<'q>, QueryError> {
+        parse_query(query.text, query.operator.unwrap_or(self.operator))
+    }
+
+    fn query_words<'q>(&self, query: &Query<'q>) -> Result::<Vec::<(Option::<Field>, &'q str)>, QueryError> {
+        let mut words = Vec::new();
+        for leaf in self.expr(query)?.leaves() {
+            words.extend(leaf_words(leaf)?)
+        }
+        Ok(words)
+    }
+
+    // the tokenizer of the model with the analyzer the query asks for
+    #[inline]
+    fn tokenizer_of(&self, query: &Query) -> TokenizerConfig {
+        TokenizerConfig { analyzer: query.analyzer.unwrap_or(self.tokenizer.analyzer), ..self.tokenizer }
+    }
+
+    // how a word of a phrase is compared to the words of a body, the exact original or the stem,
+    // `None` for the words that are never indexed, stop words among them
+    #[inline]
+    fn phrase_key<'w>(&self, word: &'w str, query: &Query) -> Option::<Cow::<'w, str>> {
+        let tokenizer = self.tokenizer_of(query);
+        let word = normalize_word(word, &tokenizer)?;
+        let stem = stem_cow(&word, tokenizer.analyzer);
+        if tokenizer.is_stop_word(&word, &stem) { return None }
+        Some(if query.exact { word } else { stem })
+    }
+
+    // whether the body of `doc` has words with the terms of `terms` right after one another,
+    // words that are never indexed (too long ones, lone punctuation) don't break a phrase up
+    fn has_phrase(doc: &Doc, terms: &[Cow::<str>]) -> bool {
+        let Some(positions) = terms.iter().map(|term| doc.positions.get(&**term)).collect::<Option::<Vec::<_>>>() else { return false };
+        let Some((first, rest)) = positions.split_first() else { return false };
+        first.iter().any(|at| rest.iter().zip(1..).all(|(positions, i)| positions.binary_search(&(at + i)).is_ok()))
+    }
+
+    // the documents `expr` matches, `None` when it has nothing to search for (every word of it was dropped),
+    // which leaves the documents alone instead of matching none of them. phrases are found by going through
+    // the documents with their first term and looking up where the terms are in each of them
+    fn matching(&self, expr: &Expr, query: &Query) -> Result::<Option::<HashSet::<&PathBuf, HashState>>, QueryError> {
+        let exact = query.exact;
+        Ok(match expr {
+            Expr::Word(..) => {
+                let terms = self.terms_of(leaf_words(expr)?, query);
+                (!terms.is_empty()).then(|| terms.into_iter().filter_map(|(field, _, term)| {
+                    let list = self.postings.get(term.as_ref())?;
+                    Some(list.postings.iter().filter(move |posting| self.posting_tf(posting, field, &term).is_some()).map(|posting| &*posting.path))
+                }).flatten().collect())
+            },
+            Expr::Phrase(phrase) => {
+                let keys = split_words(phrase).filter_map(|word| self.phrase_key(word, query)).collect::<Vec::<_>>();
+                let terms = keys.iter().map(|key| if exact { Cow::Owned(original_key(key)) } else { key.clone() }).collect::<Vec::<_>>();
+                let Some(first) = terms.first() else { return Ok(None) };
+                let candidates = self.postings.get(&**first).map(|list| list.postings.as_slice()).unwrap_or_default();
+                Some(candidates.iter()
+                     .filter_map(|posting| Some((&*posting.path, self.docs.get(&*posting.path)?)))
+                     .take_while(|_| !query.out_of_time())
+                     .filter(|(_, doc)| Self::has_phrase(doc, &terms))
+                     .map(|(path, _)| path)
+                     .collect())
+            },
+            Expr::And(operands) => {
+                let mut matching: Option::<HashSet::<_, HashState>> = None;
+                for operand in operands {
+                    let Some(docs) = self.matching(operand, query)? else { continue };
+                    matching = Some(match matching {
+                        Some(mut matching) => { matching.retain(|path| docs.contains(path)); matching },
+                        None => docs
+                    })
+                }
+                matching
+            },
+            Expr::Or(operands) => {
+                let mut matching: Option::<HashSet::<_, HashState>> = None;
+                for operand in operands {
+                    let Some(docs) = self.matching(operand, query)? else { continue };
+                    matching.get_or_insert_with(HashSet::default).extend(docs)
+                }
+                matching
+            }
+        })
+    }
+
+    // the stemmed terms of the query along with the field they're scoped to and the word they came from
+    fn analyze<'q>(&self, query: &Query<'q>) -> Result::<Vec::<QueryTerm<'q>>, QueryError> {
+        if query.exact && !self.tokenizer.keep_originals {
+            return Err(QueryError::OriginalsNotKept)
+        }
+
+        Ok(self.terms_of(self.query_words(query)?, query))
+    }
+
+    fn terms_of<'q>(&self, words: Vec::<(Option::<Field>, &'q str)>, query: &Query) -> Vec::<QueryTerm<'q>> {
+        let tokenizer = self.tokenizer_of(query);
+        let mut terms = Vec::new();
+        for (field, word) in words {
+            terms.extend(tokens(iter::once(word), &tokenizer).map(|(part, word, term)| {
+                let term = if query.exact { Cow::Owned(original_key(&word)) } else { term };
+                (field, part, term)
+            }));
+        }
+        terms
+    }
+
+    // the stemmed terms of the query along with the field they're scoped to
+    #[inline]
+    fn query_terms<'q>(&self, query: &Query<'q>) -> Result::<Vec::<FieldTerm<'q>>, QueryError> {
+        Ok(self.analyze(query)?.into_iter().map(|(field, _, term)| (field, term)).collect())
+    }
+
+    // words of the query whose terms aren't in any document, along with the terms. exact terms are
+    // given without the prefix they're stored under. a scoped word is only absent if it isn't in any field
+    pub fn absent<'q>(&self, query: impl Into::<Query<'q>>) -> Result::<Vec::<(&'q str, Cow::<'q, str>)>, QueryError> {
+        let absent = self.analyze(&query.into())?.into_iter()
+            .filter(|(_, _, term)| self.df.get(term.as_ref()).is_none_or(|df| *df == 0))
+            .map(|(_, word, term)| (word, match term {
+                Cow::Borrowed(term) => Cow::Borrowed(term.strip_prefix(ORIGINAL_PREFIX).unwrap_or(term)),
+                Cow::Owned(term) => Cow::Owned(term.strip_prefix(ORIGINAL_PREFIX).map(str::to_owned).unwrap_or(term))
+            }))
+            .collect();
+        Ok(absent)
+    }
+
+    // "did you mean": the query with every word no document has replaced by the closest indexed word, `None` when
+    // none could be. it looks at every term, so it's for queries that found nothing
+    pub fn suggest<'q>(&self, query: impl Into::<Query<'q>>) -> Result::<Option::<String>, QueryError> {
+        let query = query.into();
+        let text = query.text;
+        let mut corrections = self.analyze(&query)?.into_iter()
+            .filter(|(_, _, term)| self.df.get(term.as_ref()).is_none_or(|df| *df == 0))
+            // words are replaced where they are in the query, which the parts of compounds are too
+            .filter(|(_, word, _)| text.as_bytes().as_ptr_range().contains(&word.as_ptr()))
+            .filter_map(|(_, word, term)| {
+                let correction = if query.exact || self.tokenizer.keep_originals {
+                    let original = term.strip_prefix(ORIGINAL_PREFIX).map_or_else(|| self.normalized(word, &query), str::to_owned);
+                    self.closest(&original, true)?.to_owned()
+                } else {
+                    self.readable(word, &term, self.closest(&term, false)?, &query)
+                };
+                Some((word.as_ptr() as usize - text.as_ptr() as usize, word.len(), correction))
+            })
+            .collect::<Vec::<_>>();
+        if corrections.is_empty() { return Ok(None) }
+
+        // a whole compound comes before its parts, which are left alone then
+        corrections.sort_unstable_by_key(|(at, len, _)| (*at, Reverse(*len)));
+        let mut suggestion = String::with_capacity(text.len());
+        let mut end = 0;
+        for (at, len, correction) in corrections {
+            if at < end { continue }
+            suggestion.push_str(&text[end..at]);
+            suggestion.push_str(&correction);
+            end = at + len
+        }
+        suggestion.push_str(&text[end..]);
+        Ok(Some(suggestion))
+    }
+
+    // the indexed stem, or original without its prefix, at the fewest edits from `term`, no more than `max_edits`
+    // of them, and of the ones that are as close the one the most documents have
+    fn closest(&self, term: &str, originals: bool) -> Option::<&str> {
+        let max = max_edits(term.chars().count());
+        if max == 0 { return None }
+
+        self.df.iter()
+            .filter(|(_, df)| **df > 0)
+            .filter_map(|(candidate, df)| {
+                let word = match candidate.strip_prefix(ORIGINAL_PREFIX) {
+                    Some(original) if originals => original,
+                    None if !originals => candidate,
+                    _ => return None
+                };
+                Some((edit_distance(term, word, max)?, Reverse(*df), word))
+            })
+            .min()
+            .map(|(_, _, word)| word)
+    }
+
+    // `word` as the tokenizer of the query would index it before stemming, lowercased if it lowercases
+    #[inline]
+    fn normalized(&self, word: &str, query: &Query) -> String {
+        if self.tokenizer_of(query).case_sensitive { word.to_owned() } else { word.to_lowercase() }
+    }
+
+    // a word for the user to see in place of `word`, that stems to `closest`, see `suggest`
+    fn readable(&self, word: &str, term: &str, closest: &str, query: &Query) -> String {
+        let tokenizer = self.tokenizer_of(query);
+        let stems_to_closest = |word: &str| stem_word(word, &tokenizer).is_some_and(|stem| stem == closest);
+        if let Some(suffix) = self.normalized(word, query).strip_prefix(term) {
+            let guess = format!("{closest}{suffix}");
+            if stems_to_closest(&guess) { return guess }
+        }
+
+        self.postings.get(closest)
+            .and_then(|list| list.postings.first())
+            .and_then(|posting| self.docs.get(&*posting.path))
+            .and_then(|doc| {
+                let mut scanned = doc.content.len().min(MAX_PASSAGE_SCAN);
+                while !doc.content.is_char_boundary(scanned) { scanned -= 1 }
+                split_words(&doc.content[..scanned]).find(|word| stems_to_closest(word))
+            })
+            .map_or_else(|| closest.to_owned(), |word| self.normalized(word, query))
+    }
+
+    // indexed terms starting with `prefix` along with the number of documents they're in, most frequent first,
+    // and how many there are in total. the originals kept for exact matching are only listed when `prefix` asks for
+    // them by starting with `=`. terms of documents that were all removed again are left out
+    pub fn terms(&self, prefix: &str, limit: usize) -> (Vec::<(&str, usize)>, usize) {
+        let originals = prefix.starts_with(ORIGINAL_PREFIX);
+        let mut terms = self.df.iter()
+            .filter(|(term, df)| **df > 0 && term.starts_with(prefix) && (originals || !term.starts_with(ORIGINAL_PREFIX)))
+            .map(|(term, df)| (&**term, *df))
+            .collect::<Vec::<_>>();
+
+        let total = terms.len();
+        terms.sort_unstable_by(|(a, a_df), (b, b_df)| b_df.cmp(a_df).then_with(|| a.cmp(b)));
+        terms.truncate(limit);
+        (terms, total)
+    }
+
+    // words of the query that were dropped instead of searched for, and why
+    pub fn discarded<'q>(&self, query: impl Into::<Query<'q>>) -> Result::<Vec::<(&'q str, Discard)>, QueryError> {
+        let query = query.into();
+        let tokenizer = self.tokenizer_of(&query);
+        let is_stop_word = |word: &str| {
+            let word = if tokenizer.case_sensitive { Cow::Borrowed(word) } else { lowercase(word) };
+            tokenizer.is_stop_word(&word, &stem_cow(&word, tokenizer.analyzer))
+        };
+        let discarded = self.query_words(&query)?.into_iter()
+            .filter_map(|(_, word)| match check_word(word, &tokenizer) {
+                Ok(checked) => is_stop_word(checked).then_some((word, Discard::StopWord)),
+                Err(discard) => Some((word, discard))
+            })
+            .collect();
+        Ok(discarded)
+    }
+
+    // `search` along with what happened to the query on the way there
+    pub fn search_explained<'q>(&self, query: impl Into::<Query<'q>>) -> Result::<Explained<'_, 'q>, QueryError> {
+        let query = query.into();
+        let ranks = self.search(query)?;
+        let (absent, suggestion) = if ranks.is_empty() { (self.absent(query)?, self.suggest(query)?) } else { (Vec::new(), None) };
+        Ok(Explained {
+            ranks,
+            discarded: self.discarded(query)?,
+            absent,
+            suggestion
+        })
+    }
+
+    // `tf` of a scoped term in the document of the posting, `None` when the field doesn't contain it
+    #[inline]
+    fn posting_tf(&self, posting: &Posting, field: Option::<Field>, term: &str) -> Option::<f32> {
+        let Some(field) = field else { return (posting.tf > 0.0).then_some(posting.tf) };
+        let doc = self.docs.get(&*posting.path)?;
+        let n = doc.field_count(Some(field), term);
+        // a document whose path doesn't count can have no words of its own
+        (n > 0).then(|| n as f32 / doc.count.max(1) as f32)
+    }
+
+    // every document that scores for a query term, scored term at a time from the posting lists. past the query's
+    // deadline the scores are what the postings looked at by then add up to
+    pub fn search<'q>(&self, query: impl Into::<Query<'q>>) -> Result::<Ranks<'_>, QueryError> {
+        let query = query.into();
+        let terms = self.query_terms(&query)?;
+
+        let mut scores = HashMap::<&PathBuf, f32, HashState>::default();
+        'terms: for (field, term, list, idf) in terms.iter().filter_map(|(field, term)| Some((*field, term.as_ref(), self.postings.get(term.as_ref())?, self.idf(term)))) {
+            for posting in list.postings.iter().filter(|posting| query.admits(&posting.path)) {
+                if query.out_of_time() { break 'terms }
+                if let Some(tf) = self.posting_tf(posting, field, term) {
+                    *scores.entry(&*posting.path).or_default() += tf * idf
+                }
+            }
+        }
+        scores.retain(|_, score| *score > 0.0);
+
+        // phrases and `AND` only narrow down the documents, which are scored by all the words either way
+        let expr = self.expr(&query)?;
+        if !expr.is_plain() {
+            if let Some(matching) = self.matching(&expr, &query)? {
+                scores.retain(|path, _| matching.contains(path))
+            }
+        }
+
+        let mut ranks = scores.into_iter().collect::<Vec::<_>>();
+        ranks.par_sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(ranks)
+    }
+
+    // the same documents as `search`, scored with BM25 instead: a term counts for less the more often it's
+    // in a document, up to `k1 + 1` times its idf, and the counts of documents longer than the average
+    // document are held against them by `b`, so that a long document isn't buried by every short one
+    pub fn search_bm25<'q>(&self, query: impl Into::<Query<'q>>) -> Result::<Ranks<'_>, QueryError> {
+        let query = query.into();
+        let terms = self.query_terms(&query)?;
+        let Bm25 { k1, b } = self.bm25;
+        let avgdl = self.total_count as f32 / self.docs.len().max(1) as f32;
+
+        let mut scores = HashMap::<&PathBuf, f32, HashState>::default();
+        'terms: for (field, term, list, idf) in terms.iter().filter_map(|(field, term)| Some((*field, term.as_ref(), self.postings.get(term.as_ref())?, self.bm25_idf(term)))) {
+            for posting in list.postings.iter().filter(|posting| query.admits(&posting.path)) {
+                if query.out_of_time() { break 'terms }
+                let Some(doc) = self.docs.get(&*posting.path) else { continue };
+                let n = doc.field_count(field, term) as f32;
+                if n <= 0.0 { continue }
+                let norm = 1.0 - b + b * doc.count as f32 / avgdl.max(1.0);
+                *scores.entry(&*posting.path).or_default() += idf * n * (k1 + 1.0) / (n + k1 * norm)
+            }
+        }
+        scores.retain(|_, score| *score > 0.0);
+
+        let expr = self.expr(&query)?;
+        if !expr.is_plain() {
+            if let Some(matching) = self.matching(&expr, &query)? {
+                scores.retain(|path, _| matching.contains(path))
+            }
+        }
+
+        let mut ranks = scores.into_iter().collect::<Vec::<_>>();
+        ranks.par_sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(ranks)
+    }
+
+    // how many documents score above zero for the query, which is what `search` returns
+    // minus the documents that only contain terms that weigh nothing. this doesn't score anything,
+    // so it stays cheap next to `search_top_k` which never sees most of the matching documents
+    pub fn total<'q>(&self, query: impl Into::<Query<'q>>) -> Result::<usize, QueryError> {
+        let query = query.into();
+        if !self.expr(&query)?.is_plain() {
+            return Ok(self.search(query)?.len())
+        }
+
+        let mut terms = self.query_terms(&query)?.into_iter()
+            .filter(|(_, term)| self.idf(term) > 0.0)
+            .filter_map(|(field, term)| Some((field, self.postings.get(term.as_ref())?, term)))
+            .collect::<Vec::<_>>();
+
+        terms.sort_unstable_by(|(field, _, term), (other_field, _, other)| (field, term).cmp(&(other_field, other)));
+        terms.dedup_by(|(field, _, term), (other_field, _, other)| (field, term) == (other_field, other));
+
+        let total = match terms.as_slice() {
+            [] => 0,
+            [(None, list, _)] => list.postings.iter().filter(|posting| posting.tf > 0.0 && query.admits(&posting.path)).count(),
+            terms => terms.iter()
+                .flat_map(|(field, list, term)| {
+                    list.postings.iter()
+                        .filter(|posting| query.admits(&posting.path) && self.posting_tf(posting, *field, term).is_some())
+                        .map(|posting| posting.id)
+                })
+                .collect::<HashSet::<_, HashState>>()
+                .len()
+        };
+        Ok(total)
+    }
+
+    // the best `k` documents, ranked the same as `search`, with WAND: the documents whose summed bounds can't beat
+    // the worst of the heap are skipped without being scored. past the query's deadline the heap is what's returned
+    pub fn search_top_k<'q>(&self, query: impl Into::<Query<'q>>, k: usize) -> Result::<Ranks<'_>, QueryError> {
+        let query = query.into();
+        if k == 0 { return Ok(Vec::new()) }
+
+        // the bounds don't know about phrases and `AND`, those queries are scored in full
+        if !self.expr(&query)?.is_plain() {
+            let mut ranks = self.search(query)?;
+            ranks.truncate(k);
+            return Ok(ranks)
+        }
+
+        let mut weights = HashMap::<(Option::<Field>, Cow::<str>), f32, HashState>::default();
+        self.query_terms(&query)?.into_iter().for_each(|term| *weights.entry(term).or_default() += 1.0);
+
+        // the title, the tags and the body are all counted in `tf`, so `max_tf` bounds terms scoped to them too
+        let mut cursors = weights.iter().filter_map(|(&(field, ref term), n)| {
+            let (term, list) = (term.as_ref(), self.postings.get(term.as_ref()).filter(|list| !list.postings.is_empty())?);
+            let weight = self.idf(term) * n;
+            let max_tf = if field == Some(Field::Path) { list.max_path_tf } else { list.max_tf };
+            Some(Cursor { postings: &list.postings, at: 0, field, term, weight, bound: max_tf * weight })
+        }).collect::<Vec::<_>>();
+
+        let mut heap = BinaryHeap::<Hit>::with_capacity(k + 1);
+        while !query.out_of_time() {
+            cursors.retain(|cursor| !cursor.done());
+            cursors.sort_unstable_by_key(Cursor::id);
+
+            let threshold = if heap.len() < k { f32::NEG_INFINITY } else { heap.peek().map_or(0.0, |hit| hit.0) };
+
+            // the first cursor at which the summed bounds could beat the threshold
+            let mut bound = 0.0;
+            let Some(pivot) = cursors.iter().position(|cursor| {
+                bound += cursor.bound;
+                bound > threshold
+            }) else { break };
+
+            let pivot_id = cursors[pivot].id();
+            if cursors[0].id() == pivot_id {
+                let path = &*cursors[0].postings[cursors[0].at].path;
+                if !query.admits(path) {
+                    cursors.iter_mut().take_while(|cursor| cursor.id() == pivot_id).for_each(|cursor| cursor.at += 1);
+                    continue
+                }
+
+                let mut matched = false;
+                let score = cursors.iter_mut()
+                    .take_while(|cursor| cursor.id() == pivot_id)
+                    .map(|cursor| {
+                        let posting = &cursor.postings[cursor.at];
+                        cursor.at += 1;
+                        self.posting_tf(posting, cursor.field, cursor.term).map_or(0.0, |tf| {
+                            matched = true;
+                            tf * cursor.weight
+                        })
+                    }).sum::<f32>();
+
+                if matched && score > 0.0 && score > threshold {
+                    heap.push(Hit(score, path));
+                    if heap.len() > k { heap.pop(); }
+                }
+            } else {
+                cursors[..pivot].iter_mut().for_each(|cursor| cursor.seek(pivot_id))
+            }
+        }
+
+        let mut ranks = heap.into_iter().map(|Hit(score, path)| (path, score)).collect::<Vec::<_>>();
+        ranks.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(ranks)
+    }
+
+    // what the words of a body are compared to for the query, the keys of the words that aren't scoped to another
+    // field and of their parts, sorted and without duplicates
+    fn body_keys<'q>(&self, query: &Query<'q>) -> Vec::<Cow::<'q, str>> {
+        let mut keys = self.query_words(query).unwrap_or_default().into_iter()
+            .filter_map(|(field, word)| match field {
+                None | Some(Field::Body) => Some(word),
+                Some(..) => None
+            })
+            .flat_map(|word| word_and_parts(word, &self.tokenizer))
+            .filter_map(|word| self.phrase_key(word, query))
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    // excerpt of at most `len` bytes around the first word of the document's body that matches the query
+    pub fn snippet<'q>(&self, file_path: &PathBuf, query: impl Into::<Query<'q>>, len: usize) -> Option::<&str> {
+        let doc = self.docs.get(file_path)?;
+        let query = query.into();
+        let key = |word| self.phrase_key(word, &query);
+        let tokens = self.body_keys(&query);
+
+        let content = &*doc.content;
+        let hit = split_words(content).find(|word| {
+            word_and_parts(word, &self.tokenizer).any(|word| key(word).is_some_and(|word| tokens.binary_search(&word).is_ok()))
+        })?;
+
+        let at = hit.as_ptr() as usize - content.as_ptr() as usize;
+        Some(window(content, at, len))
+    }
+
+    // an excerpt of at most `len` bytes of the document's body like `snippet`'s, picked as `mode` says. only the
+    // first `MAX_PASSAGE_SCAN` bytes of the body are looked at by `SnippetMode::Densest` and `SnippetMode::Merged`,
+    // which score every window of matches in there instead of stopping at the first one
+    pub fn snippet_with<'q>(&self, file_path: &PathBuf, query: impl Into::<Query<'q>>, len: usize, mode: SnippetMode) -> Option::<Cow::<'_, str>> {
+        let query = query.into();
+        if mode == SnippetMode::First {
+            return self.snippet(file_path, query, len).map(Cow::Borrowed)
+        }
+
+        let doc = self.docs.get(file_path)?;
+        let key = |word| self.phrase_key(word, &query);
+        let words = self.body_keys(&query);
+
+        let content = &*doc.content;
+        let mut scanned = content.len().min(MAX_PASSAGE_SCAN);
+        while !content.is_char_boundary(scanned) { scanned -= 1 }
+
+        let mut hits = Vec::<Match>::new();
+        split_words(&content[..scanned]).for_each(|hit| {
+            let at = hit.as_ptr() as usize - content.as_ptr() as usize;
+            word_and_parts(hit, &self.tokenizer)
+                .filter_map(|word| words.binary_search(&key(word)?).ok())
+                .for_each(|word| hits.push((at, at + hit.len(), word)))
+        });
+
+        let len = if mode == SnippetMode::Merged { len / 2 } else { len };
+        let around = |(from, to): (usize, usize)| {
+            excerpt(content, from.saturating_sub(len.saturating_sub(to - from) / 2), from, to, len)
+        };
+
+        // a single word longer than the window is left to `snippet`, the way it cuts through it
+        let Some(first) = densest(&hits, words.len(), len, None) else {
+            return self.snippet(file_path, query, len).map(Cow::Borrowed)
+        };
+        let second = (mode == SnippetMode::Merged).then(|| densest(&hits, words.len(), len, Some(first))).flatten();
+        Some(match second {
+            None => Cow::Borrowed(around(first)),
+            Some(second) => {
+                let (a, b) = if second.0 < first.0 { (second, first) } else { (first, second) };
+                Cow::Owned(format!("{a}{PASSAGE_SEPARATOR}{b}", a = around(a), b = around(b)))
+            }
+        })
+    }
+
+    // the `from..to` bytes of every word of `snippet` (a `snippet` or `snippet_with` of the query) that matches
+    // the query, the way the words of a body are matched, without the punctuation around them
+    pub fn highlights<'q>(&self, snippet: &str, query: impl Into::<Query<'q>>) -> Vec::<(usize, usize)> {
+        let query = query.into();
+        let keys = self.body_keys(&query);
+        if keys.is_empty() { return Vec::new() }
+
+        split_words(snippet)
+            .filter(|word| {
+                word_and_parts(word, &self.tokenizer).any(|word| self.phrase_key(word, &query).is_some_and(|key| keys.binary_search(&key).is_ok()))
+            })
+            .map(|word| {
+                let trimmed = word.trim_matches(|c: char| !c.is_alphanumeric());
+                let at = trimmed.as_ptr() as usize - snippet.as_ptr() as usize;
+                (at, at + trimmed.len())
+            })
+            .collect()
+    }
+
+    fn print_progress(&self) {
+        if let Ok(at) = self.milestones.binary_search_by_key(&self.count, |(count, _)| *count) {
+            // nobody may be drawing them
+            _ = self.milestones_tx.send(Signal::Progress(self.milestones[at].1))
+        }
+    }
+
+    // the tokenizer of the model with the analyzer `text` asks for
+    #[inline]
+    fn tokenizer_for(&self, text: &Text) -> TokenizerConfig {
+        TokenizerConfig { analyzer: text.analyzer.unwrap_or(self.tokenizer.analyzer), ..self.tokenizer }
+    }
+
+    pub fn add_document(&mut self, file_path: &'a PathBuf, text: &'a Text) {
+        self.rm_document(file_path);
+
+        let tokenizer = self.tokenizer_for(text);
+        let doc = Doc::tokenized(Held::Borrowed(&text.body), &tokenizer, &self.df)
+            .with_fields(text.title.as_deref(), &text.tags, file_path, &tokenizer, &self.df);
+        self.insert_document(Held::Borrowed(file_path), doc);
+
+        self.count += 1;
+        self.print_progress();
+    }
+
+    // give `doc` the next id and put it in `df`, the posting lists and `docs`
+    fn insert_document(&mut self, file_path: Held<'a, PathBuf>, mut doc: Doc<'a>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        doc.id = id;
+
+        doc.tf.keys().for_each(|t| {
+            if let Some(f) = self.df.get_mut(t) {
+                *f += 1;
+            } else {
+                self.df.insert(t.clone(), 1);
+            }
+
+            let tf = Self::tf(t, &doc);
+            let list = self.postings.entry(t.clone()).or_default();
+            list.postings.push(Posting { id, path: file_path.clone(), tf });
+            list.max_tf = list.max_tf.max(tf);
+            list.max_path_tf = list.max_path_tf.max(Self::path_tf(t, &doc));
+        });
+
+        self.total_count += doc.count;
+        self.docs.insert(file_path, doc);
+    }
+
+    // index `text` under `id` without anything on disk, for contents from elsewhere and for files that are reindexed.
+    // the model owns both, they're dropped along with the document
+    pub fn add_text(&mut self, id: impl Into::<PathBuf>, text: impl Into::<Text>) {
+        let (id, text) = (id.into(), text.into());
+        self.rm_document(&id);
+
+        let tokenizer = self.tokenizer_for(&text);
+        let doc = Doc::tokenized(Held::Owned(text.body.into()), &tokenizer, &self.df)
+            .with_copied_fields(text.title.as_deref(), &text.tags, &id, &tokenizer, &self.df);
+        self.insert_document(Held::Owned(Arc::new(id)), doc);
+
+        self.count += 1;
+        self.print_progress();
+    }
+
+    #[inline]
+    pub fn add_contents(&mut self, contents: &'a Contents) {
+        let have_big_files = contents.iter()
+            .take(contents.len() / 2)
+            .any(|content| content.1.body.len() >= GIG as _);
+
+        if have_big_files {
+            let zelf = am!(self);
+            contents.par_iter().for_each(|(file_path, content)| {
+                let mut zelf = unsafe { zelf.lock().unwrap_unchecked() };
+                zelf.add_document(file_path, content);
+            });
+            zelf.lock().unwrap().finish();
+        } else {
+            contents.iter().for_each(|(file_path, content)| {
+                self.add_document(file_path, content);
+            });
+            self.finish()
+        }
+    }
+
+    // what `add_contents` does once it added all of them, for documents that were added one at a time as they
+    // came: `finalize` the model and let whoever draws the progress know that it's done
+    #[inline]
+    pub fn finish(&mut self) {
+        self.finalize();
+        _ = self.milestones_tx.send(Signal::Done);
+    }
+
+    // the document is only looked up in the lists of its own terms, where it's found by binary searching its id.
+    // the terms that no document has anymore go along with it, from `df` and the posting lists
+    #[inline]
+    pub fn rm_document(&mut self, file_path: &PathBuf) {
+        if let Some(doc) = self.docs.remove(file_path) {
+            self.total_count -= doc.count;
+            doc.tf.keys().for_each(|t| {
+                // a `df` out of step with the documents would wrap around and make every idf garbage
+                match self.df.get_mut(t) {
+                    Some(f) if *f > 1 => *f -= 1,
+                    f => {
+                        debug_assert_eq!(f.copied(), Some(1), "df of `{t}` is out of step with the documents");
+                        self.df.remove(t);
+                    }
+                }
+                if let Some(list) = self.postings.get_mut(t) {
+                    if let Ok(i) = list.postings.binary_search_by_key(&doc.id, |p| p.id) {
+                        list.postings.remove(i);
+                    }
+                    if list.postings.is_empty() { self.postings.remove(t); }
+                }
+            });
+        }
+    }
+
+    // prune the rare terms and tighten the bounds of the posting lists, done by `add_contents` once the documents
+    // are in. searches find and score the same without it, it's worth running again after many were removed
+    pub fn finalize(&mut self) {
+        let pruned = self.prune(self.min_df);
+        if pruned > 0 && self.min_df > 1 {
+            info!("dropped {pruned} terms that fewer than {min_df} documents have", min_df = self.min_df)
+        }
+        let docs = &self.docs;
+        self.postings.iter_mut().for_each(|(t, list)| {
+            list.max_tf = list.postings.iter().map(|posting| posting.tf).fold(0.0, f32::max);
+            list.max_path_tf = list.postings.iter()
+                .filter_map(|posting| docs.get(&*posting.path))
+                .map(|doc| Self::path_tf(t, doc))
+                .fold(0.0, f32::max)
+        });
+    }
+
+    // drop every term fewer than `min_df` documents have, for corpora full of one-off tokens, and return how many were.
+    // the documents keep their length, so the terms that are left score the same
+    pub fn prune(&mut self, min_df: usize) -> usize {
+        let before = self.df.len();
+        self.df.retain(|_, f| *f >= min_df.max(1));
+        let pruned = before - self.df.len();
+        if pruned == 0 { return 0 }
+
+        let df = &self.df;
+        self.postings.retain(|t, _| df.contains_key(t));
+        self.docs.values_mut().for_each(|doc| {
+            doc.tf.retain(|t, _| df.contains_key(t));
+            doc.title_tf.retain(|t, _| df.contains_key(t));
+            doc.tags_tf.retain(|t, _| df.contains_key(t));
+            doc.path_tf.retain(|t, _| df.contains_key(t));
+        });
+        pruned
+    }
+
+    // the walked file that `doc_path` was parsed from, itself or the archive it's an entry of, if it's in `stamps`
+    #[inline]
+    fn stamped_file<'p>(&self, doc_path: &'p Path) -> Option::<&'p Path> {
+        if self.stamps.contains_key(doc_path) { return Some(doc_path) }
+        archive::split_virtual_path(doc_path).map(|(archive, _)| archive).filter(|archive| self.stamps.contains_key(*archive))
+    }
+
+    // write the documents of the files in `stamps` to `path` for `load_from`, the posting lists are rebuilt as they're
+    // loaded. files with a path that isn't UTF-8 are left out, a save that fails midway leaves the old index in place
+    pub fn save_to<P: AsRef::<Path>>(&self, path: P, parse: &ParseConfig) -> IoResult::<()> {
+        // `None` for the files that can't be saved
+        let mut files = self.stamps.iter()
+            .map(|(file_path, stamp)| (file_path.as_path(), file_path.to_str().map(|file_path_str| (file_path_str, *stamp, Vec::new()))))
+            .collect::<HashMap::<_, _, HashState>>();
+        self.docs.iter().for_each(|(doc_path, doc)| {
+            let Some(file) = self.stamped_file(doc_path).and_then(|file_path| files.get_mut(file_path)) else { return };
+            match doc_path.to_str() {
+                Some(doc_path) => if let Some((.., docs)) = file { docs.push((doc_path, doc)) },
+                None => *file = None
+            }
+        });
+
+        // in the order they were indexed in, so that the documents get their ids in the same order again
+        let mut files = files.into_values().flatten().collect::<Vec::<_>>();
+        files.iter_mut().for_each(|(.., docs)| docs.sort_unstable_by_key(|(_, doc)| doc.id));
+        files.sort_unstable_by_key(|(.., docs)| docs.first().map_or(0, |(_, doc)| doc.id));
+
+        let mut table = HashMap::<&str, usize, HashState>::default();
+        let mut terms = Vec::new();
+        files.iter().flat_map(|(.., docs)| docs).for_each(|(_, doc)| {
+            [&doc.tf, &doc.title_tf, &doc.tags_tf, &doc.path_tf].into_iter().flat_map(|tf| tf.keys()).chain(doc.positions.keys()).for_each(|t| {
+                table.entry(&**t).or_insert_with(|| {
+                    terms.push(&**t);
+                    terms.len() - 1
+                });
+            })
+        });
+
+        let path = path.as_ref();
+        let mut partial = OsString::from(path.as_os_str());
+        partial.push(".partial");
+        let mut w = BufWriter::new(File::create(&partial)?);
+        w.write_all(SAVED_HEADER)?;
+        write_str(&mut w, &self.tokenizer.fingerprint())?;
+        write_str(&mut w, &parse.fingerprint())?;
+        write_u32(&mut w, terms.len())?;
+        terms.iter().try_for_each(|t| write_str(&mut w, t))?;
+
+        write_u32(&mut w, files.len())?;
+        for (file_path, stamp, docs) in files.iter() {
+            write_str(&mut w, file_path)?;
+            write_u64(&mut w, stamp.len)?;
+            match stamp.modified {
+                None => write_u32(&mut w, 0)?,
+                Some((secs, nanos)) => {
+                    write_u32(&mut w, 1)?;
+                    write_u64(&mut w, secs)?;
+                    write_u32(&mut w, nanos as _)?
+                }
+            }
+
+            write_u32(&mut w, docs.len())?;
+            for (doc_path, doc) in docs.iter() {
+                write_str(&mut w, doc_path)?;
+                write_str(&mut w, &doc.content)?;
+                write_u64(&mut w, doc.path_weight as _)?;
+                write_u64(&mut w, doc.count as _)?;
+                [&doc.tf, &doc.title_tf, &doc.tags_tf, &doc.path_tf].into_iter().try_for_each(|tf| write_tf(&mut w, tf, &table))?;
+                write_u32(&mut w, doc.positions.len())?;
+                for (t, positions) in doc.positions.iter() {
+                    write_u32(&mut w, table[&**t])?;
+                    write_u32(&mut w, positions.len())?;
+                    positions.iter().try_for_each(|at| write_u32(&mut w, *at as _))?
+                }
+            }
+        }
+
+        w.into_inner().map_err(IntoInnerError::into_error)?.sync_all()?;
+        fs::rename(partial, path)
+    }
+
+    // index the documents of the files of `saved` that are in `unchanged`, the ones a walk found as they were
+    // saved (see `Report::unchanged`), and take their stamps. the model owns their paths and bodies as it does
+    // `add_text`'s. they aren't counted in the progress, which is of the files that are parsed
+    pub fn load_from(&mut self, saved: Saved, unchanged: &[PathBuf]) {
+        let unchanged = unchanged.iter().collect::<HashSet::<_, HashState>>();
+        let Saved { terms, files, .. } = saved;
+        // only the terms of the documents that are loaded are copied, each once
+        let mut held = vec![None::<Held<'a, str>>; terms.len()];
+        let mut hold = |t: u32, known: &DocFreq<'a>| held[t as usize].get_or_insert_with(|| Held::copied(&terms[t as usize], known)).clone();
+        for file in files.into_iter().filter(|file| unchanged.contains(&file.path)) {
+            for saved in file.docs {
+                let mut term = |&(t, f): &(u32, usize)| (hold(t, &self.df), f);
+                let doc = Doc {
+                    id: 0,
+                    tf: saved.tf.iter().map(&mut term).collect(),
+                    title_tf: saved.title_tf.iter().map(&mut term).collect(),
+                    tags_tf: saved.tags_tf.iter().map(&mut term).collect(),
+                    path_tf: saved.path_tf.iter().map(&mut term).collect(),
+                    path_weight: saved.path_weight,
+                    positions: saved.positions.into_iter().map(|(t, positions)| (hold(t, &self.df), positions)).collect(),
+                    count: saved.count,
+                    content: Held::Owned(saved.content.into())
+                };
+                self.rm_document(&saved.path);
+                self.insert_document(Held::Owned(Arc::new(saved.path)), doc)
+            }
+            self.stamps.insert(file.path, file.stamp);
+        }
+    }
+
+    #[inline(always)]
+    fn tf(t: &str, doc: &Doc) -> f32 {
+        *doc.tf.get(t).unwrap_or(&0) as f32 / doc.count.max(1) as f32
+    }
+
+    // what a `path:` term scores in `doc`, see `posting_tf`
+    #[inline]
+    fn path_tf(t: &str, doc: &Doc) -> f32 {
+        *doc.path_tf.get(t).unwrap_or(&0) as f32 / doc.count.max(1) as f32
+    }
+
+    // 0 for a term no document has, at least `MIN_IDF` for any other
+    #[inline(always)]
+    fn idf(&self, term: &str) -> f32 {
+        let df = *self.df.get(term).unwrap_or(&0);
+        if df == 0 { return 0.0 }
+        let idf = (self.docs.len() as f32 / df as f32).log10().max(MIN_IDF);
+        if self.numbers != Numbers::Keep && is_numeric(term) { idf * self.numbers.weight() } else { idf }
+    }
+
+    // never below zero, a term every document has still counts for a little
+    #[inline(always)]
+    fn bm25_idf(&self, term: &str) -> f32 {
+        let (n, df) = (self.docs.len() as f32, *self.df.get(term).unwrap_or(&0) as f32);
+        let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+        if self.numbers != Numbers::Keep && is_numeric(term) { idf * self.numbers.weight() } else { idf }
+    }
+}
+
+// what a file written by `Model::save_to` starts with, one of another version of the format isn't loaded.
+// the version goes up when files are parsed differently too, what was saved of them has to be parsed again
+const SAVED_HEADER: &[u8] = b"search index 4\n";
+
+// the documents of a saved index, grouped by the walked file they were parsed from, for `Model::load_from`.
+// the documents refer to their terms by their place in a table, so that a term is written once
+pub struct Saved {
+    terms: Vec::<String>,
+    // in the order they were indexed in
+    files: Vec::<SavedFile>,
+    // the place of every file in `files`
+    places: HashMap::<PathBuf, usize, HashState>
+}
+
+struct SavedFile {
+    path: PathBuf,
+    stamp: Stamp,
+    docs: Vec::<SavedDoc>
+}
+
+// a `Doc` as it's written out, with its terms as places in `Saved::terms`
+struct SavedDoc {
+    path: PathBuf,
+    content: String,
+    path_weight: usize,
+    count: usize,
+    tf: Vec::<(u32, usize)>,
+    title_tf: Vec::<(u32, usize)>,
+    tags_tf: Vec::<(u32, usize)>,
+    path_tf: Vec::<(u32, usize)>,
+    positions: Vec::<(u32, Vec::<u32>)>
+}
+
+impl Saved {
+    // the index `Model::save_to` wrote to `path`, `InvalidData` when it's of another version of the format or was
+    // tokenized or parsed with other options than `tokenizer` and `parse`
+    pub fn read<P: AsRef::<Path>>(path: P, tokenizer: &TokenizerConfig, parse: &ParseConfig) -> IoResult::<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+        let mut header = vec![0; SAVED_HEADER.len()];
+        r.read_exact(&mut header)?;
+        if header != SAVED_HEADER {
+            return Err(IoError::new(IoErrorKind::InvalidData, "it isn't an index of this version"))
+        }
+        if read_str(&mut r)? != tokenizer.fingerprint() {
+            return Err(IoError::new(IoErrorKind::InvalidData, "it was tokenized with other options"))
+        }
+        if read_str(&mut r)? != parse.fingerprint() {
+            return Err(IoError::new(IoErrorKind::InvalidData, "its files were parsed with other options"))
+        }
+
+        let terms = (0..read_u32(&mut r)?).map(|_| read_str(&mut r)).collect::<IoResult::<Vec::<_>>>()?;
+        let files = (0..read_u32(&mut r)?).map(|_| {
+            let path = PathBuf::from(read_str(&mut r)?);
+            let len = read_u64(&mut r)?;
+            let modified = match read_u32(&mut r)? {
+                0 => None,
+                _ => Some((read_u64(&mut r)?, read_u32(&mut r)? as u32))
+            };
+            let docs = (0..read_u32(&mut r)?).map(|_| Ok(SavedDoc {
+                path: PathBuf::from(read_str(&mut r)?),
+                content: read_str(&mut r)?,
+                path_weight: read_u64(&mut r)? as _,
+                count: read_u64(&mut r)? as _,
+                tf: read_tf(&mut r, terms.len())?,
+                title_tf: read_tf(&mut r, terms.len())?,
+                tags_tf: read_tf(&mut r, terms.len())?,
+                path_tf: read_tf(&mut r, terms.len())?,
+                positions: (0..read_u32(&mut r)?).map(|_| {
+                    let term = read_term(&mut r, terms.len())?;
+                    let positions = (0..read_u32(&mut r)?).map(|_| read_u32(&mut r).map(|at| at as u32)).collect::<IoResult::<_>>()?;
+                    Ok((term, positions))
+                }).collect::<IoResult::<_>>()?
+            })).collect::<IoResult::<_>>()?;
+            Ok(SavedFile { path, stamp: Stamp { len, modified }, docs })
+        }).collect::<IoResult::<Vec::<_>>>()?;
+
+        let places = files.iter().enumerate().map(|(i, file)| (file.path.clone(), i)).collect();
+        Ok(Self { terms, files, places })
+    }
+
+    // what `file_path` was like when it was parsed, if it's saved
+    #[inline]
+    pub fn stamp(&self, file_path: &Path) -> Option::<Stamp> {
+        self.places.get(file_path).map(|i| self.files[*i].stamp)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[inline]
+fn read_u32(r: &mut impl Read) -> IoResult::<usize> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes) as _)
+}
+
+#[inline]
+fn read_u64(r: &mut impl Read) -> IoResult::<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// a length that a broken file has wrong is read up to where the file ends, not allocated up front
+#[inline]
+fn read_str(r: &mut impl Read) -> IoResult::<String> {
+    let len = read_u32(r)?;
+    let mut bytes = Vec::new();
+    r.take(len as _).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(IoErrorKind::UnexpectedEof.into())
+    }
+    String::from_utf8(bytes).map_err(|err| IoError::new(IoErrorKind::InvalidData, err))
+}
+
+#[inline]
+fn read_term(r: &mut impl Read, terms: usize) -> IoResult::<u32> {
+    let term = read_u32(r)?;
+    if term >= terms {
+        return Err(IoError::new(IoErrorKind::InvalidData, "a term that isn't in the table"))
+    }
+    Ok(term as _)
+}
+
+#[inline]
+fn read_tf(r: &mut impl Read, terms: usize) -> IoResult::<Vec::<(u32, usize)>> {
+    (0..read_u32(r)?).map(|_| Ok((read_term(r, terms)?, read_u64(r)? as _))).collect()
+}
+
+#[inline]
+fn write_u32(w: &mut impl Write, n: usize) -> IoResult::<()> {
+    let n = u32::try_from(n).map_err(|_| IoError::new(IoErrorKind::InvalidData, "too long to be saved"))?;
+    w.write_all(&n.to_le_bytes())
+}
+
+#[inline]
+fn write_u64(w: &mut impl Write, n: u64) -> IoResult::<()> {
+    w.write_all(&n.to_le_bytes())
+}
+
+#[inline]
+fn write_str(w: &mut impl Write, s: &str) -> IoResult::<()> {
+    write_u32(w, s.len())?;
+    w.write_all(s.as_bytes())
+}
+
+#[inline]
+fn write_tf(w: &mut impl Write, tf: &TermFreq, table: &HashMap::<&str, usize, HashState>) -> IoResult::<()> {
+    write_u32(w, tf.len())?;
+    tf.iter().try_for_each(|(t, f)| {
+        write_u32(w, table[&**t])?;
+        write_u64(w, *f as _)
+    })
+}
+
+// a model that's searched and changed from several threads, clones share the same model. a lock a panicking thread
+// held is taken over as is, a half applied change is still a model that can be searched
+pub struct SharedModel<'a>(Arc::<RwLock::<Model<'a>>>);
+
+impl<'a> SharedModel<'a> {
+    #[inline]
+    pub fn new(model: Model<'a>) -> Self {
+        Self(Arc::new(RwLock::new(model)))
+    }
+
+    #[inline]
+    pub fn read(&self) -> RwLockReadGuard::<'_, Model<'a>> {
+        self.0.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[inline]
+    pub fn write(&self) -> RwLockWriteGuard::<'_, Model<'a>> {
+        self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Clone for SharedModel<'_> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<'a> From<Model<'a>> for SharedModel<'a> {
+    #[inline]
+    fn from(model: Model<'a>) -> Self {
+        Self::new(model)
+    }
+}
+
+// names of the directories `--exclude-common` skips: version control, dependencies and build output
+pub const COMMON_EXCLUDED_DIRS: &[&str] = &[
+    ".git", ".hg", ".svn", "node_modules", "target", "__pycache__", ".venv", ".mypy_cache", ".tox"
+];
+
+#[derive(Clone)]
+pub struct WalkConfig {
+    // fail instead of skipping directories that could not be read, by default with `dir_rec_stop_on_error`
+    pub strict: bool,
+    // names of the directories that are pruned from the walk, see `DirRec::with_excluded`
+    pub excluded_dirs: Vec::<String>,
+    // skip symlinks instead of following them, see `DirRec::with_follow_symlinks`
+    pub skip_symlinks: bool,
+    // how many levels below the roots the walk goes, see `DirRec::with_max_depth`
+    pub max_depth: Option::<usize>,
+    // paths below the roots that are pruned from the walk, see `DirRec::with_ignore`
+    pub exclude: Ignore,
+    // also prune what the `.gitignore` right in a root ignores, `exclude` has the last word
+    pub gitignore: bool
+}
+
+impl Default for WalkConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            strict: cfg!(feature = "dir_rec_stop_on_error"),
+            excluded_dirs: Vec::new(),
+            skip_symlinks: false,
+            max_depth: None,
+            exclude: Ignore::default(),
+            gitignore: false
+        }
+    }
+}
+
+impl WalkConfig {
+    #[inline]
+    pub fn walk(&self, root: &Path) -> DirRec {
+        let walk = DirRec::new(root)
+            .with_ignore(self.ignore(root))
+            .with_stop_on_error(self.strict)
+            .with_excluded(self.excluded_dirs.iter().cloned())
+            .with_follow_symlinks(!self.skip_symlinks);
+        match self.max_depth {
+            Some(depth) => walk.with_max_depth(depth),
+            None => walk
+        }
+    }
+
+    // the patterns that prune the walk of `root`, the ones of its `.gitignore` and `exclude`
+    pub fn ignore(&self, root: &Path) -> Ignore {
+        if !self.gitignore { return self.exclude.clone() }
+        let mut ignore = Ignore::gitignore(root).unwrap_or_else(|err| {
+            warn!("could not read the {GITIGNORE} of {root:?}: {err}");
+            Ignore::default()
+        });
+        ignore.extend(self.exclude.clone());
+        ignore
+    }
+
+    // whether `path`, relative to a walked directory, is a directory that gets pruned or is in one,
+    // or below `max_depth`. the patterns of `ignore` are up to the caller, they're different for every root
+    #[inline]
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.max_depth.is_some_and(|depth| path.components().count() > depth + 1) ||
+        path.components().any(|component| self.excluded_dirs.iter().any(|name| component.as_os_str() == name.as_str()))
+    }
+}
+
+#[derive(Default)]
+pub struct Report {
+    // directories that could not be read, so the index is incomplete
+    pub unreadable_dirs: Vec::<IoError>,
+    // PDFs indexed without some of their pages and archives without some of their entries, with how many failed
+    pub partial_files: Vec::<(PathBuf, usize)>,
+    // files that were found but not indexed, and why
+    pub skipped: Vec::<(PathBuf, ParseError)>,
+    // files that were taken from a checkpoint instead of being parsed
+    pub resumed: usize,
+    // files that are as they were saved, which are left to their saved documents, see `dir_get_contents_since`
+    pub unchanged: Vec::<PathBuf>,
+    // what every file that was parsed was like before it was, for `Model::stamps`
+    pub stamps: Vec::<(PathBuf, Stamp)>
+}
+
+impl Report {
+    // how many files were skipped for every `ParseError::reason`, the most common reason first
+    pub fn skipped_by_reason(&self) -> Vec::<(&'static str, usize)> {
+        let mut reasons = BTreeMap::<_, usize>::new();
+        self.skipped.iter().for_each(|(_, err)| *reasons.entry(err.reason()).or_default() += 1);
+        let mut reasons = reasons.into_iter().collect::<Vec::<_>>();
+        reasons.sort_by_key(|(_, n)| Reverse(*n));
+        reasons
+    }
+}
+
+#[inline]
+pub fn dir_get_contents<P>(dir_paths: &[P], walk: &WalkConfig, config: &ParseConfig) -> IoResult::<(Contents, Report)>
+where
+    P: AsRef::<Path> + Sync
+{
+    get_contents(dir_paths, walk, config, None, None)
+}
+
+// `dir_get_contents` that takes the files that haven't changed since they were written to `checkpoint` from it
+// instead of parsing them again, and writes every file to it as it goes, see `Checkpoint`. the checkpoint
+// is left to be `Checkpoint::finish`ed once the contents are indexed
+#[inline]
+pub fn dir_get_contents_resumed<P>(dir_paths: &[P], walk: &WalkConfig, config: &ParseConfig, checkpoint: &mut Checkpoint) -> IoResult::<(Contents, Report)>
+where
+    P: AsRef::<Path> + Sync
+{
+    get_contents(dir_paths, walk, config, Some(checkpoint), None)
+}
+
+// like `dir_get_contents`, but the files that are the same as they were when `saved` was saved, by their
+// length and modification time, aren't parsed, they're only listed in `Report::unchanged`, for `Model::load_from`
+// to take their documents from `saved`
+#[inline]
+pub fn dir_get_contents_since<P>(dir_paths: &[P], walk: &WalkConfig, config: &ParseConfig, checkpoint: Option::<&mut Checkpoint>, saved: &Saved) -> IoResult::<(Contents, Report)>
+where
+    P: AsRef::<Path> + Sync
+{
+    get_contents(dir_paths, walk, config, checkpoint, Some(saved))
+}
+
+// the documents of a walked file: its own or those of the entries of an archive
+#[inline]
+fn parse_file(file_path: &Path, config: &ParseConfig) -> Result::<(Contents, usize), ParseError> {
+    match archive::Kind::of(file_path) {
+        Some(kind) => parse_archive(file_path, kind, config),
+        None => parse(file_path, config).map(|(text, failed)| (vec![(file_path.to_owned(), text)], failed))
+    }
+}
+
+// like `dir_get_contents_since`, but the documents are sent to `tx` as their files are parsed. `walked` gets about
+// how many files are parsed as soon as the walk found all of them
+#[inline]
+pub fn dir_send_contents<P>(dir_paths: &[P], walk: &WalkConfig, config: &ParseConfig, checkpoint: Option::<&mut Checkpoint>, saved: Option::<&Saved>, tx: &Sender::<(PathBuf, Text)>, walked: impl FnOnce(usize) + Send) -> IoResult::<Report>
+where
+    P: AsRef::<Path> + Sync
+{
+    walk_contents(dir_paths, walk, config, checkpoint, saved, walked, |texts| texts.into_iter().for_each(|content| _ = tx.send(content)))
+}
+
+#[inline]
+fn get_contents<P>(dir_paths: &[P], walk: &WalkConfig, config: &ParseConfig, checkpoint: Option::<&mut Checkpoint>, saved: Option::<&Saved>) -> IoResult::<(Contents, Report)>
+where
+    P: AsRef::<Path> + Sync
+{
+    let contents = Mutex::new(Vec::new());
+    let report = walk_contents(dir_paths, walk, config, checkpoint, saved, |_| {}, |texts| unsafe { contents.lock().unwrap_unchecked() }.extend(texts))?;
+    Ok((contents.into_inner().unwrap_or_default(), report))
+}
+
+// parse the files of the walk in parallel, giving `sink` the documents of every one of them, see `dir_send_contents`
+// for `walked`
+fn walk_contents<P>(dir_paths: &[P], walk: &WalkConfig, config: &ParseConfig, checkpoint: Option::<&mut Checkpoint>, saved: Option::<&Saved>, walked: impl FnOnce(usize) + Send, sink: impl Fn(Contents) + Sync) -> IoResult::<Report>
+where
+    P: AsRef::<Path> + Sync
+{
+    let checkpoint = checkpoint.map(Mutex::new);
+    let resumed = AtomicUsize::new(0);
+    let unrecorded = AtomicBool::new(false);
+    let parse_checkpointed = |file_path: &Path, stamp: Option::<Stamp>, checkpoint: &Mutex::<&mut Checkpoint>| {
+        let taken = stamp.and_then(|stamp| unsafe { checkpoint.lock().unwrap_unchecked() }.take(file_path, stamp));
+        let parsed = match taken {
+            Some(parsed) => {
+                resumed.fetch_add(1, AtomicOrdering::Relaxed);
+                parsed
+            },
+            None => {
+                let (texts, failed) = parse_file(file_path, config)?;
+                let Some(stamp) = stamp else { return Ok((texts, failed)) };
+                Parsed { stamp, texts, failed }
+            }
+        };
+
+        // the checkpoint only saves time, indexing goes on without it
+        let recorded = Checkpoint::line(file_path, &parsed).map(|line| unsafe { checkpoint.lock().unwrap_unchecked() }.record(&line));
+        if let Some(Err(err)) = recorded {
+            if !unrecorded.swap(true, AtomicOrdering::Relaxed) {
+                warn!("could not write the checkpoint: {err}")
+            }
+        }
+        Ok((parsed.texts, parsed.failed))
+    };
+
+    let mut report = Report::default();
+    let partial_files = Mutex::new(Vec::new());
+    let skipped = Mutex::new(Vec::new());
+    let unchanged = Mutex::new(Vec::new());
+    let stamps = Mutex::new(Vec::new());
+    let found = AtomicUsize::new(0);
+    let mut walked = Some(walked);
+    dir_paths.iter()
+        .flat_map(|dir_path| walk.walk(dir_path.as_ref()))
+        .map_while(|entry| match entry {
+            Ok(file_path) => Some(Some(file_path)),
+            Err(err) => {
+                report.unreadable_dirs.push(err);
+                (!walk.strict).then_some(None)
+            }
+        })
+        .flatten()
+        .inspect(|_| _ = found.fetch_add(1, AtomicOrdering::Relaxed))
+        .chain(iter::from_fn(|| {
+            if let Some(walked) = walked.take() {
+                walked(found.load(AtomicOrdering::Relaxed) - unsafe { unchanged.lock().unwrap_unchecked() }.len())
+            }
+            None
+        }))
+        .par_bridge()
+        .for_each(|e| {
+            // taken before the file is parsed, one that changes while it is has another stamp by the next start
+            let stamp = metadata(&e).ok().map(|md| Stamp::of(&md));
+            if stamp.is_some() && saved.is_some_and(|saved| saved.stamp(&e) == stamp) {
+                unsafe { unchanged.lock().unwrap_unchecked() }.push(e);
+                return
+            }
+
+            // an archive is a directory of its own as far as the documents go, the failed entries are its parts
+            let parsed = match &checkpoint {
+                Some(checkpoint) => parse_checkpointed(&e, stamp, checkpoint),
+                None => parse_file(&e, config)
+            };
+            let (texts, failed) = match parsed {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    unsafe { skipped.lock().unwrap_unchecked() }.push((e, err));
+                    return
+                }
+            };
+            if let Some(stamp) = stamp {
+                unsafe { stamps.lock().unwrap_unchecked() }.push((e.clone(), stamp))
+            }
+            if failed > 0 {
+                unsafe { partial_files.lock().unwrap_unchecked() }.push((e, failed))
+            }
+            sink(texts)
+        });
+
+    report.partial_files = partial_files.into_inner().unwrap_or_default();
+    report.skipped = skipped.into_inner().unwrap_or_default();
+    report.resumed = resumed.into_inner();
+    report.unchanged = unchanged.into_inner().unwrap_or_default();
+    report.stamps = stamps.into_inner().unwrap_or_default();
+
+    if walk.strict {
+        if let Some(err) = report.unreadable_dirs.pop() {
+            return Err(err)
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::testing::temp_dir;
+    use crate::query::Deadline;
+
+    fn contents(docs: &[(&str, &str)]) -> Contents {
+        docs.iter().map(|(path, content)| (PathBuf::from(path), Text::from(content.to_string()))).collect()
+    }
+
+    fn paths(ranks: Ranks) -> Vec::<String> {
+        ranks.into_iter().map(|(path, _)| path.display().to_string()).collect()
+    }
+
+    #[test]
+    fn comments_only_indexes_comments() {
+        let source = r#"
+            /// hello docs
+            fn identifier() {
+                let s = "literal // not comment";
+                /* block words */
+                x // trailing remark
+            }
+        "#;
+
+        let syntax = comment_syntax("rs").unwrap();
+        let comments = extract_comments(source, syntax);
+        let doc = Doc::new(&comments, &TokenizerConfig::default());
+
+        ["hello", "doc", "block", "word", "trail", "remark"].iter().for_each(|word| {
+            assert!(doc.tf.contains_key(*word), "{word} must be indexed")
+        });
+        ["fn", "identifi", "let", "liter", "not", "comment", "x"].iter().for_each(|word| {
+            assert!(!doc.tf.contains_key(*word), "{word} must not be indexed")
+        });
+    }
+
+    #[test]
+    fn binary_files_are_detected_by_content() {
+        let dir = temp_dir("binary_files");
+
+        let blob = (0..=255u8).cycle().take(4096).collect::<Vec::<_>>();
+        fs::write(dir.join("blob.txt"), blob).unwrap();
+        let err = parse(&dir.join("blob.txt"), &ParseConfig::default()).unwrap_err();
+        assert!(matches!(err, ParseError::Binary));
+
+        let script = dir.join("run.sh");
+        fs::write(&script, "#!/bin/sh\necho \"héllo wörld\"\n").unwrap();
+        #[cfg(unix)] {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        assert!(parse(&script, &ParseConfig::default()).unwrap().0.body.contains("wörld"));
+        assert!(extract_text(&script).unwrap().contains("wörld"));
+        assert!(matches!(extract_text(&dir.join("blob.txt")), Err(ParseError::Binary)));
+        fs::write(dir.join("notes.unknown"), "plain text").unwrap();
+        assert!(matches!(extract_text(&dir.join("notes.unknown")), Err(ParseError::UnknownExtension)));
+
+        #[cfg(unix)] {
+            use std::os::unix::fs::PermissionsExt;
+            let elf = dir.join("elf.txt");
+            fs::write(&elf, b"\x7fELF but otherwise printable").unwrap();
+            assert!(parse(&elf, &ParseConfig::default()).is_ok());
+            fs::set_permissions(&elf, fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(matches!(parse(&elf, &ParseConfig::default()), Err(ParseError::Binary)));
+        }
+
+        assert!(!is_binary_bytes(b"plain\ttext\r\n\x1b[1mbold\x1b[0m"));
+        assert!(is_binary_bytes(b"\x7fELF\x02\x01\x01\x00"));
+        assert!(is_binary_bytes(&[0x01, 0x02, 0x03, b'a', 0x04]));
+    }
+
+    #[test]
+    fn skipped_files_are_reported() {
+        let dir = temp_dir("skipped_files");
+        fs::write(dir.join("ok.txt"), "fine").unwrap();
+        fs::write(dir.join("blob.txt"), (0..=255u8).cycle().take(4096).collect::<Vec::<_>>()).unwrap();
+        fs::write(dir.join("latin1.txt"), b"caf\xe9 au lait").unwrap();
+        fs::write(dir.join("a.unknown"), "plain text").unwrap();
+        fs::write(dir.join("b.unknown"), "plain text").unwrap();
+
+        let (contents, report) = dir_get_contents(&[&dir], &WalkConfig::default(), &ParseConfig::default()).unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(report.skipped.len(), 4);
+        assert_eq!(report.skipped_by_reason(), [("unknown extension", 2), ("binary", 1), ("could not be decoded", 1)]);
+
+        let encrypted = ParseError::from(IoError::new(IoErrorKind::InvalidData, Encrypted));
+        assert!(matches!(encrypted, ParseError::Encrypted));
+        assert!(matches!(ParseError::from(IoError::from(IoErrorKind::NotFound)), ParseError::Io(..)));
+    }
+
+    #[test]
+    fn excluded_dirs_are_pruned() {
+        let dir = temp_dir("excluded_dirs");
+        fs::create_dir_all(dir.join("src/node_modules/left-pad")).unwrap();
+        fs::create_dir_all(dir.join("target/debug")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("src/node_modules/left-pad/index.js"), "pad").unwrap();
+        fs::write(dir.join("target/debug/build.log"), "log").unwrap();
+        fs::write(dir.join("target.txt"), "not a directory").unwrap();
+
+        let walk = WalkConfig { excluded_dirs: vec!["node_modules".to_owned(), "target".to_owned()], ..Default::default() };
+        let (contents, _) = dir_get_contents(&[&dir], &walk, &ParseConfig::default()).unwrap();
+        let mut paths = contents.iter().map(|(path, _)| path.strip_prefix(&dir).unwrap().to_owned()).collect::<Vec::<_>>();
+        paths.sort_unstable();
+        assert_eq!(paths, [PathBuf::from("src/main.rs"), PathBuf::from("target.txt")]);
+
+        // a root is walked whatever its name
+        let (contents, _) = dir_get_contents(&[dir.join("target")], &walk, &ParseConfig::default()).unwrap();
+        assert_eq!(contents.len(), 1);
+
+        assert!(walk.is_excluded(Path::new("src/node_modules/left-pad")));
+        assert!(!walk.is_excluded(Path::new("src/main.rs")));
+        let shallow = WalkConfig { max_depth: Some(0), ..Default::default() };
+        assert!(shallow.is_excluded(Path::new("src/main.rs")));
+        assert!(!shallow.is_excluded(Path::new("main.rs")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_are_walked_once() {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir("symlink_cycles");
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::write(dir.join("a/b/c.txt"), "kiwi").unwrap();
+        fs::write(dir.join("d.txt"), "lime").unwrap();
+        symlink(&dir, dir.join("a/b/up")).unwrap();
+        symlink(dir.join("a"), dir.join("a/b/back")).unwrap();
+        symlink(dir.join("d.txt"), dir.join("a/e.txt")).unwrap();
+
+        let walked = |follow_symlinks: bool| {
+            let mut paths = DirRec::new(&dir).with_follow_symlinks(follow_symlinks)
+                .map(|path| path.unwrap().strip_prefix(&dir).unwrap().to_owned())
+                .collect::<Vec::<_>>();
+            paths.sort_unstable();
+            paths
+        };
+        assert_eq!(walked(true), [PathBuf::from("a/b/c.txt"), PathBuf::from("a/e.txt"), PathBuf::from("d.txt")]);
+        assert_eq!(walked(false), [PathBuf::from("a/b/c.txt"), PathBuf::from("d.txt")]);
+    }
+
+    #[test]
+    fn ignored_paths_are_pruned() {
+        let dir = temp_dir("ignored_paths");
+        fs::create_dir_all(dir.join(".git/objects")).unwrap();
+        fs::create_dir_all(dir.join("crates/core/target")).unwrap();
+        fs::create_dir_all(dir.join("app/target/debug")).unwrap();
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join(".git/objects/pack.txt"), "kiwi").unwrap();
+        fs::write(dir.join("app/target/debug/out.txt"), "kiwi").unwrap();
+        fs::write(dir.join("app/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("crates/core/target/deep.txt"), "kiwi").unwrap();
+        fs::write(dir.join("logs/today.log"), "kiwi").unwrap();
+        fs::write(dir.join("logs/keep.log"), "kiwi").unwrap();
+        fs::write(dir.join(GITIGNORE), "# build output\n*.log\n!keep.log\n").unwrap();
+
+        let walk = WalkConfig { exclude: Ignore::new(["*/target/*", ".git/"]), gitignore: true, ..Default::default() };
+        let (contents, _) = dir_get_contents(&[&dir], &walk, &ParseConfig::default()).unwrap();
+        let mut paths = contents.iter().map(|(path, _)| path.strip_prefix(&dir).unwrap().to_owned()).collect::<Vec::<_>>();
+        paths.sort_unstable();
+        // `*/target/*` is anchored, the one a level deeper is kept
+        assert_eq!(paths, ["app/main.rs", "crates/core/target/deep.txt", "logs/keep.log"].map(PathBuf::from));
+
+        // without the `.gitignore` both logs are there
+        let walk = WalkConfig { gitignore: false, ..walk };
+        let (contents, _) = dir_get_contents(&[&dir], &walk, &ParseConfig::default()).unwrap();
+        assert!(contents.iter().any(|(path, _)| path.ends_with("logs/today.log")));
+        assert!(!contents.iter().any(|(path, _)| path.starts_with(dir.join("app/target"))));
+    }
+
+    #[test]
+    fn walks_stop_at_the_max_depth() {
+        let dir = temp_dir("max_depth");
+        fs::create_dir_all(dir.join("a/b/c/d")).unwrap();
+        ["root.txt", "a/one.txt", "a/b/two.txt", "a/b/c/three.txt", "a/b/c/d/four.txt"].iter().for_each(|path| fs::write(dir.join(path), "kiwi").unwrap());
+
+        let walked = |depth: usize| {
+            let mut paths = DirRec::new(&dir).with_max_depth(depth)
+                .map(|path| path.unwrap().strip_prefix(&dir).unwrap().to_owned())
+                .collect::<Vec::<_>>();
+            paths.sort_unstable();
+            paths
+        };
+        assert_eq!(walked(0), [PathBuf::from("root.txt")]);
+        assert_eq!(walked(1), [PathBuf::from("a/one.txt"), PathBuf::from("root.txt")]);
+        assert_eq!(walked(3), ["a/b/c/three.txt", "a/b/two.txt", "a/one.txt", "root.txt"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn html_blocks_are_separated() {
+        let dir = temp_dir("html_blocks");
+        let page = dir.join("page.html");
+        fs::write(&page, "<html><head><title>Notes</title></head><body>\
+            <p>foo</p><p>bar</p><div>baz<br>qux</div><ul><li>one</li><li>two</li></ul>\
+            <h1>head</h1>line <b>bold</b><i>italic</i></body></html>").unwrap();
+
+        let (text, _) = parse(&page, &ParseConfig::default()).unwrap();
+        assert_eq!(text.title.as_deref(), Some("Notes"));
+        let words = text.body.split_whitespace().collect::<Vec::<_>>();
+        assert_eq!(words, ["Notes", "foo", "bar", "baz", "qux", "one", "two", "head", "line", "bolditalic"]);
+    }
+
+    #[test]
+    fn gzipped_files_are_parsed_by_their_inner_extension() {
+        use std::io::Write;
+        use flate2::{Compression, write::GzEncoder};
+
+        let dir = temp_dir("gzipped_files");
+        let gzip = |name: &str, contents: &[u8]| {
+            let mut encoder = GzEncoder::new(File::create(dir.join(name)).unwrap(), Compression::default());
+            encoder.write_all(contents).unwrap();
+            encoder.finish().unwrap();
+        };
+        gzip("access.log.gz", b"GET /index.html 200");
+        gzip("feed.xml.gz", b"<feed><entry>hello</entry><entry>world</entry></feed>");
+        gzip("image.png.gz", b"not really a png");
+        gzip("blob.txt.gz", &[0u8; 64]);
+        fs::write(dir.join("broken.txt.gz"), "not gzip at all").unwrap();
+
+        let config = ParseConfig::default();
+        assert!(parse(&dir.join("access.log.gz"), &config).unwrap().0.body.contains("GET /index.html 200"));
+        assert_eq!(parse(&dir.join("feed.xml.gz"), &config).unwrap().0.body, "helloworld");
+        assert!(matches!(parse(&dir.join("image.png.gz"), &config), Err(ParseError::UnknownExtension)));
+        assert!(matches!(parse(&dir.join("blob.txt.gz"), &config), Err(ParseError::Binary)));
+        assert!(matches!(parse(&dir.join("broken.txt.gz"), &config), Err(ParseError::Decode(..))));
+    }
+
+    #[test]
+    fn texts_longer_than_the_cap_are_rejected() {
+        use std::io::Write;
+        use flate2::{Compression, write::GzEncoder};
+
+        let dir = temp_dir("text_cap");
+        let line = "word ".repeat(100);
+        fs::write(dir.join("long.txt"), &line).unwrap();
+        fs::write(dir.join("long.xml"), format!("<doc>{line}</doc>")).unwrap();
+        // a run the parser gives up on before it ever gets to the text
+        fs::write(dir.join("longer.xml"), format!("<doc>{line}{line}</doc>")).unwrap();
+        // far more markup than text, the text is what counts
+        fs::write(dir.join("marked.xml"), format!("<doc>{tags}</doc>", tags = "<a>w</a>".repeat(100))).unwrap();
+        let mut encoder = GzEncoder::new(File::create(dir.join("long.log.gz")).unwrap(), Compression::default());
+        encoder.write_all(line.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let capped = ParseConfig { max_text_len: Some(line.len() - 1), ..Default::default() };
+        for name in ["long.txt", "long.xml", "long.log.gz"] {
+            assert!(matches!(parse(&dir.join(name), &capped), Err(ParseError::Decode(..))), "{name}");
+            assert!(parse(&dir.join(name), &ParseConfig { max_text_len: Some(line.len()), ..Default::default() }).is_ok(), "{name}");
+            assert!(parse(&dir.join(name), &ParseConfig { max_text_len: None, ..Default::default() }).is_ok(), "{name}")
+        }
+        assert!(matches!(parse(&dir.join("longer.xml"), &capped), Err(ParseError::Decode(..))));
+        assert_eq!(parse(&dir.join("marked.xml"), &capped).unwrap().0.body.len(), 100);
+    }
+
+    #[test]
+    fn docx_text_is_parsed_by_paragraph() {
+        use std::io::Write;
+        use zip::{ZipWriter, CompressionMethod, write::SimpleFileOptions};
+
+        let dir = temp_dir("docx");
+        let path = dir.join("report.docx");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file("word/document.xml", options).unwrap();
+        zip.write_all(br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:r><w:t>Quarterly</w:t></w:r><w:r><w:t xml:space="preserve"> report</w:t></w:r></w:p>
+<w:p><w:r><w:t>kiwi</w:t><w:tab/><w:t>sales</w:t><w:br/><w:t>grew</w:t></w:r><w:del><w:r><w:delText>fell</w:delText></w:r></w:del></w:p>
+<w:tbl><w:tr><w:tc><w:p><w:r><w:t>north</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>south</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+<w:p><w:r><w:instrText>PAGE</w:instrText><w:t>end</w:t></w:r></w:p>
+</w:body></w:document>"#).unwrap();
+        zip.start_file("docProps/core.xml", options).unwrap();
+        zip.write_all(br#"<?xml version="1.0" encoding="UTF-8"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>Q3 numbers</dc:title></cp:coreProperties>"#).unwrap();
+        zip.finish().unwrap();
+
+        let (text, failed) = parse(&path, &ParseConfig::default()).unwrap();
+        assert_eq!(failed, 0);
+        assert_eq!(text.title.as_deref(), Some("Q3 numbers"));
+        let words = text.body.split_whitespace().collect::<Vec::<_>>();
+        assert_eq!(words, ["Quarterly", "report", "kiwi", "sales", "grew", "north", "south", "end"]);
+
+        // a docx that's not a zip, or a zip without a document in it
+        fs::write(dir.join("fake.docx"), "not a zip").unwrap();
+        assert!(matches!(parse(&dir.join("fake.docx"), &ParseConfig::default()), Err(ParseError::Decode(..))));
+    }
+
+    #[test]
+    fn epub_chapters_are_indexed_in_reading_order() {
+        use std::io::Write;
+        use zip::{ZipWriter, CompressionMethod, write::SimpleFileOptions};
+
+        let dir = temp_dir("epub");
+        let mut zip = ZipWriter::new(File::create(dir.join("book.epub")).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let chapter = |title: &str, text: &str| format!("<html xmlns=\"http://www.w3.org/1999/xhtml\"><head><title>{title}</title></head><body><h1>{title}</h1><p>{text}</p></body></html>");
+        let files = [
+            ("mimetype", "application/epub+zip".to_owned()),
+            ("META-INF/container.xml", r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#.to_owned()),
+            ("OEBPS/content.opf", r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0"><metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title>The Orchard</dc:title></metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+<item id="one" href="text/one.xhtml" media-type="application/xhtml+xml"/>
+<item id="two" href="text/two.xhtml" media-type="application/xhtml+xml"/>
+<item id="gone" href="text/gone.xhtml" media-type="application/xhtml+xml"/>
+</manifest>
+<spine toc="ncx"><itemref idref="nav"/><itemref idref="two"/><itemref idref="one"/><itemref idref="gone"/></spine></package>"#.to_owned()),
+            ("OEBPS/nav.xhtml", chapter("Contents", "table of contents")),
+            ("OEBPS/toc.ncx", "<ncx>navigation</ncx>".to_owned()),
+            ("OEBPS/text/one.xhtml", chapter("Kiwis", "the kiwi trees")),
+            ("OEBPS/text/two.xhtml", chapter("Plums", "the plum trees"))
+        ];
+        files.iter().for_each(|(name, contents)| {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap()
+        });
+        zip.finish().unwrap();
+
+        let (text, failed) = parse(&dir.join("book.epub"), &ParseConfig::default()).unwrap();
+        assert_eq!(text.title.as_deref(), Some("The Orchard"));
+        // `gone` is in the spine but not in the archive
+        assert_eq!(failed, 1);
+        let words = text.body.split_whitespace().collect::<Vec::<_>>();
+        assert_eq!(words, ["Plums", "Plums", "the", "plum", "trees", "Kiwis", "Kiwis", "the", "kiwi", "trees"]);
+
+        let (contents, _) = dir_get_contents(&[&dir], &WalkConfig::default(), &ParseConfig::default()).unwrap();
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+        assert_eq!(model.search("kiwi").unwrap().len(), 1);
+        assert!(model.search("navigation").unwrap().is_empty());
+        assert_eq!(zip_join("OEBPS/text", "../images/../notes.xhtml"), "OEBPS/notes.xhtml");
+    }
+
+    #[test]
+    fn archive_entries_are_indexed_under_virtual_paths() {
+        use std::io::Write;
+        use flate2::{Compression, write::GzEncoder};
+        use zip::{ZipWriter, CompressionMethod, write::SimpleFileOptions};
+
+        let dir = temp_dir("archive_entries");
+        let mut zip = ZipWriter::new(File::create(dir.join("docs.zip")).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        [("notes/todo.txt", &b"water the plants"[..]), ("../escape.txt", b"outside"), ("logo.png", b"\x89PNG")].iter().for_each(|(name, contents)| {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents).unwrap()
+        });
+        zip.finish().unwrap();
+
+        let tar = |writer: &mut dyn Write| {
+            let mut tar = tar::Builder::new(writer);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(13);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, "src/main.rs", &b"fn feed_cat()"[..]).unwrap();
+            // `append_data` won't write names that point outside of the archive, so these go into the header as they are
+            ["../escape.rs", "/etc/escape.rs"].iter().for_each(|name| {
+                header.as_old_mut().name = [0; 100];
+                header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+                header.set_size(12);
+                header.set_cksum();
+                tar.append(&header, &b"fn outside()"[..]).unwrap()
+            });
+            tar.finish().unwrap()
+        };
+        tar(&mut File::create(dir.join("code.tar")).unwrap());
+        let mut encoder = GzEncoder::new(File::create(dir.join("code.tar.gz")).unwrap(), Compression::default());
+        tar(&mut encoder);
+        encoder.finish().unwrap();
+
+        let (contents, report) = dir_get_contents(&[&dir], &WalkConfig::default(), &ParseConfig::default()).unwrap();
+        let mut paths = contents.iter().map(|(path, _)| path.strip_prefix(&dir).unwrap().to_str().unwrap()).collect::<Vec::<_>>();
+        paths.sort();
+        assert_eq!(paths, ["code.tar!src/main.rs", "code.tar.gz!src/main.rs", "docs.zip!notes/todo.txt"]);
+        assert!(report.skipped.is_empty() && report.partial_files.is_empty());
+
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+        let (todo, _) = contents.iter().find(|(path, _)| path.ends_with("docs.zip!notes/todo.txt")).unwrap();
+        assert_eq!(model.search("plants").unwrap()[0].0, todo);
+        assert_eq!(archive::split_virtual_path(todo), Some((dir.join("docs.zip").as_path(), "notes/todo.txt")));
+        assert_eq!(archive::virtual_path(&dir.join("docs.zip"), "notes/todo.txt"), *todo);
+        assert_eq!(archive::split_virtual_path(Path::new("a!b/c.zip!d!e.txt")), Some((Path::new("a!b/c.zip"), "d!e.txt")));
+        assert_eq!(archive::split_virtual_path(Path::new("plain!name.txt")), None);
+        assert!(archive::is_addressable(Path::new("a!b/c.zip"), "d!e.txt"));
+        assert!(!archive::is_addressable(Path::new("old.zip!/c.zip"), "d.txt"));
+
+        assert_eq!(archive::read_entry(&dir.join("docs.zip"), archive::Kind::Zip, "notes/todo.txt").unwrap(), b"water the plants");
+        assert_eq!(archive::read_entry(&dir.join("code.tar.gz"), archive::Kind::TarGz, "src/main.rs").unwrap(), b"fn feed_cat()");
+        assert_eq!(archive::read_entry(&dir.join("code.tar"), archive::Kind::Tar, "src").unwrap_err().kind(), IoErrorKind::NotFound);
+        assert_eq!(archive::read_entry(&dir.join("docs.zip"), archive::Kind::Zip, "../escape.txt").unwrap_err().kind(), IoErrorKind::NotFound);
+        assert_eq!(archive::read_entry(&dir.join("code.tar"), archive::Kind::Tar, "../escape.rs").unwrap_err().kind(), IoErrorKind::NotFound);
+        assert_eq!(archive::read_entry(&dir.join("code.tar"), archive::Kind::Tar, "etc/escape.rs").unwrap_err().kind(), IoErrorKind::NotFound);
+    }
+
+    #[test]
+    fn numbers_are_weighted_by_policy() {
+        let contents = contents(&[("a", "status 404"), ("b", "status page page"), ("c", "other")]);
+        let ranked = |numbers| {
+            let (tx, _rx) = channel();
+            let mut model = Model::new(tx, contents.len()).with_numbers(numbers);
+            model.add_contents(&contents);
+            let ranks = model.search("404 page").unwrap();
+            assert_eq!(model.search_top_k("404 page", 1).unwrap()[0].0, ranks[0].0, "{numbers:?}");
+            ranks.into_iter().map(|(path, score)| (path.to_str().unwrap().to_owned(), score)).collect::<Vec::<_>>()
+        };
+
+        let keep = ranked(Numbers::Keep);
+        assert_eq!(keep[0].0, "b");
+        let boost = ranked(Numbers::Boost);
+        assert_eq!(boost[0].0, "a");
+        assert!((boost[0].1 - 2.0 * keep[1].1).abs() < 1e-6);
+        assert_eq!(ranked(Numbers::Demote)[1].0, "a");
+        let exclude = ranked(Numbers::Exclude);
+        assert!(exclude.iter().all(|(path, _)| path != "a"));
+
+        assert!(is_numeric("2024") && is_numeric("=42") && !is_numeric("v2") && !is_numeric("="));
+        assert_eq!(Numbers::from_name("Exclude"), Some(Numbers::Exclude));
+    }
+
+    #[test]
+    fn rare_terms_are_pruned() {
+        let contents = contents(&[("a", "apple banana xqzzy"), ("b", "apple banana"), ("c", "apple cherry"), ("d", "date")]);
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+        let banana = model.search("banana").unwrap().into_iter().map(|(path, score)| (path.clone(), score)).collect::<Vec::<_>>();
+
+        assert_eq!(model.prune(DEFAULT_MIN_DF), 0);
+        // `xqzzy`, `cherri`, `date` and the paths
+        assert_eq!(model.prune(2), 7);
+        assert_eq!(model.df.len(), 2);
+        assert!(model.df.iter().all(|(t, f)| model.postings[t].postings.len() == *f));
+        assert!(model.docs.values().all(|doc| doc.tf.keys().chain(doc.path_tf.keys()).all(|t| model.df.contains_key(t))));
+        assert_eq!(model.docs.values().filter(|doc| doc.tf.contains_key("banana")).count(), model.df["banana"]);
+
+        assert!(model.search("banana").unwrap().into_iter().eq(banana.iter().map(|(path, score)| (path, *score))));
+        assert!(model.search("cherry").unwrap().is_empty());
+        assert_eq!(model.docs.len(), 4);
+
+        // terms removed documents were the last to have go with them
+        model.rm_document(&PathBuf::from("a"));
+        model.rm_document(&PathBuf::from("b"));
+        assert!(model.postings.get("banana").is_none() && model.df.len() == 1);
+        assert_eq!(model.prune(1), 0);
+    }
+
+    #[test]
+    fn removing_a_document_added_twice_leaves_nothing_behind() {
+        let mut model = Model::detached();
+        model.add_text("a.txt", "kiwi banana");
+        model.add_text("a.txt", "kiwi plum");
+        model.add_text("b.txt", "kiwi");
+        assert_eq!((model.df["kiwi"], model.df["plum"]), (2, 1));
+        assert!(!model.df.contains_key("banana") && !model.postings.contains_key("banana"));
+
+        model.rm_document(&PathBuf::from("a.txt"));
+        model.rm_document(&PathBuf::from("a.txt"));
+        assert!(model.df.values().all(|f| (1..=model.docs.len()).contains(f)), "{:?}", model.df);
+        assert!(model.df.iter().all(|(t, f)| model.postings[t].postings.len() == *f));
+        assert_eq!(model.df.len(), model.postings.len());
+        assert_eq!(model.df["kiwi"], 1);
+
+        model.rm_document(&PathBuf::from("b.txt"));
+        assert!(model.df.is_empty() && model.postings.is_empty());
+    }
+
+    #[test]
+    fn snippets_find_the_densest_matches() {
+        let filler = "filler ".repeat(30);
+        let body = format!("rust {filler}rust cargo build {filler}cargo build tool {filler}end");
+        let contents = contents(&[("a.txt", &body), ("b.txt", "nothing here")]);
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+
+        let path = PathBuf::from("a.txt");
+        let query = "rust cargo build";
+        assert!(model.snippet_with(&path, query, 40, SnippetMode::First).unwrap().starts_with("rust filler"));
+        let densest = model.snippet_with(&path, query, 40, SnippetMode::Densest).unwrap();
+        assert!(densest.contains("rust cargo build") && densest.len() <= 40, "{densest}");
+
+        let merged = model.snippet_with(&path, query, 40, SnippetMode::Merged).unwrap();
+        let (a, b) = merged.split_once(PASSAGE_SEPARATOR).unwrap();
+        assert!(a.contains("rust cargo build") && a.len() <= 20, "{merged}");
+        assert!(b.contains("cargo build tool") && b.len() <= 20, "{merged}");
+
+        // a single cluster of matches is all there is
+        assert_eq!(model.snippet_with(&path, "tool", 40, SnippetMode::Merged).unwrap().matches(PASSAGE_SEPARATOR).count(), 0);
+        assert_eq!(model.snippet_with(&PathBuf::from("b.txt"), query, 40, SnippetMode::Densest), None);
+    }
+
+    #[test]
+    fn snippets_mark_the_matched_words() {
+        let filler = "the quick brown fox jumps over the lazy dog; ".repeat(10);
+        let body = format!("{filler}Searching (searches) for needles, in haystacks: a search engine. {filler}");
+        let contents = contents(&[("a.txt", &body)]);
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+
+        let path = PathBuf::from("a.txt");
+        for mode in [SnippetMode::First, SnippetMode::Densest] {
+            let snippet = model.snippet_with(&path, "search needle", 200, mode).unwrap();
+            assert!(snippet.len() <= 200 && snippet.contains("needles"), "{snippet}");
+            assert!(!snippet.starts_with(is_split_character) && !snippet.ends_with(is_split_character), "{snippet:?}");
+
+            let marked = model.highlights(&snippet, "search needle").into_iter().map(|(from, to)| &snippet[from..to]).collect::<Vec::<_>>();
+            assert_eq!(marked, ["Searching", "searches", "needles", "search"], "{snippet}");
+        }
+        assert!(model.highlights("nothing to see", "search").is_empty());
+        assert!(model.highlights("a search", "title:search").is_empty());
+    }
+
+    #[test]
+    fn texts_are_indexed_without_files() {
+        let mut model = Model::detached().with_tokenizer(TokenizerConfig { path_weight: 0, ..TokenizerConfig::default() });
+        model.add_text("users/42", "likes rust and cargo");
+        model.add_text("users/43", Text { title: Some("gardener".to_owned()), tags: Vec::new(), body: "likes plants".to_owned(), analyzer: None });
+        model.add_text("users/44", "nothing in common");
+
+        let id = PathBuf::from("users/42");
+        assert_eq!(paths(model.search("rust").unwrap()), ["users/42"]);
+        assert_eq!(model.search("title:gardener").unwrap()[0].0, &PathBuf::from("users/43"));
+        assert!(model.search("users").unwrap().is_empty());
+
+        model.add_text("users/42", "likes go now");
+        assert!(model.search("rust").unwrap().is_empty());
+        assert_eq!(model.docs.len(), 3);
+        model.rm_document(&id);
+        assert!(model.search("go").unwrap().is_empty());
+    }
+
+    #[test]
+    fn queries_are_analyzed_like_the_documents() {
+        let mut model = Model::detached().with_tokenizer(TokenizerConfig { path_weight: 0, ..TokenizerConfig::default() });
+        model.add_text("english", "running shoes");
+        model.add_text("other", Text { analyzer: Some(Analyzer::Unstemmed), ..Text::from("running shoes") });
+
+        let found = |query: Query| paths(model.search(query).unwrap());
+        assert_eq!(found(Query::from("running")), ["english"]);
+        assert_eq!(found(Query::from("running").with_analyzer(Analyzer::Unstemmed)), ["other"]);
+        assert_eq!(found(Query::from(r#""running shoes""#).with_analyzer(Analyzer::Unstemmed)), ["other"]);
+        assert_eq!(found(Query::from(r#""running shoes""#)), ["english"]);
+        assert!(model.snippet(&PathBuf::from("other"), Query::from("shoes").with_analyzer(Analyzer::Unstemmed), 40).is_some());
+        assert_eq!(Analyzer::from_name("Unstemmed"), Some(Analyzer::Unstemmed));
+    }
+
+    #[test]
+    fn documents_and_queries_tokenize_alike() {
+        let config = TokenizerConfig { split_compounds: true, keep_originals: true, ..TokenizerConfig::default() };
+        let text = "Running state-of-the-art Parsers, quickly; x";
+        let tokens = tokenize(text, &config);
+        assert_eq!(text, "Running state-of-the-art Parsers, quickly; x");
+        assert_eq!(tokens[0], "run");
+        assert!(["state", "art", "parser", "quick", "x"].iter().all(|term| tokens.iter().any(|token| token == term)), "{tokens:?}");
+
+        let body = text.to_owned();
+        let doc = Doc::new(&body, &config);
+        let mut indexed = doc.tf.keys().filter(|term| !term.starts_with(ORIGINAL_PREFIX)).map(|term| term.to_string()).collect::<Vec::<_>>();
+        let mut tokenized = tokens.clone();
+        indexed.sort();
+        tokenized.sort();
+        tokenized.dedup();
+        assert_eq!(indexed, tokenized);
+
+        let model = Model::detached().with_tokenizer(config);
+        let query = text.to_owned();
+        let searched = model.query_terms(&Query::from(query.as_str())).unwrap().into_iter().map(|(_, term)| term.into_owned()).collect::<Vec::<_>>();
+        assert_eq!(searched, tokens);
+    }
+
+    #[test]
+    fn tokenizing_leaves_the_text_alone() {
+        let config = TokenizerConfig { split_compounds: true, keep_originals: true, ..TokenizerConfig::default() };
+        let content: &'static str = "The QUICK brown Fox, HTTPServer and CAFÉ";
+        let first = Doc::new(content, &config);
+        let second = Doc::new(content, &config);
+        assert_eq!(first.tf, second.tf);
+        assert_eq!(first.positions, second.positions);
+        assert_eq!(content, "The QUICK brown Fox, HTTPServer and CAFÉ");
+        assert!(["quick", "=quick", "fox", "server", "=café"].iter().all(|term| first.tf.contains_key(*term)), "{:?}", first.tf);
+
+        let mut model = Model::detached().with_tokenizer(config);
+        model.add_text("doc", content.to_owned());
+        assert_eq!(model.search("Quick FOX").unwrap().len(), 1);
+        assert_eq!(model.snippet(&PathBuf::from("doc"), "fox", 64), Some(content));
+    }
+
+    #[test]
+    fn copied_terms_are_shared_and_dropped_with_their_documents() {
+        let config = TokenizerConfig { keep_originals: true, ..TokenizerConfig::default() };
+        let mut model = Model::detached().with_tokenizer(config);
+        model.add_text("a", "Kiwis");
+        model.add_text("b", "Kiwis");
+        let copied = |model: &Model, path: &str, term: &str| match model.docs[&PathBuf::from(path)].tf.get_key_value(term) {
+            Some((Held::Owned(term), _)) => Arc::clone(term),
+            _ => panic!("`{term}` of {path} isn't a copy")
+        };
+        let (stem, original) = (copied(&model, "a", "kiwi"), copied(&model, "a", "=kiwis"));
+        assert!(Arc::ptr_eq(&stem, &copied(&model, "b", "kiwi")) && Arc::ptr_eq(&original, &copied(&model, "b", "=kiwis")));
+
+        model.rm_document(&PathBuf::from("a"));
+        model.rm_document(&PathBuf::from("b"));
+        assert_eq!((Arc::strong_count(&stem), Arc::strong_count(&original)), (1, 1));
+        assert!(model.df.is_empty() && model.postings.is_empty());
+    }
+
+    #[test]
+    fn reindexed_texts_are_dropped() {
+        let mut model = Model::detached();
+        model.add_text("a", "apples");
+        let owned = |model: &Model| match model.docs.get_key_value(&PathBuf::from("a")) {
+            Some((Held::Owned(path), Doc { content: Held::Owned(content), .. })) => (Arc::clone(path), Arc::clone(content)),
+            _ => panic!("the text of `a` isn't the model's own")
+        };
+        let (path, content) = owned(&model);
+        model.add_text("a", "bananas");
+        assert_eq!((Arc::strong_count(&path), Arc::strong_count(&content)), (1, 1));
+
+        let (path, content) = owned(&model);
+        model.rm_document(&PathBuf::from("a"));
+        assert_eq!((Arc::strong_count(&path), Arc::strong_count(&content)), (1, 1));
+        assert!(model.docs.is_empty())
+    }
+
+    #[test]
+    fn indexed_words_are_found_by_themselves() {
+        let words = ["Running", "state-of-the-art", "C++", "naïve", "HTTPServer", "e-mail,", "2024", "snake_case", "Ünïcödé", "tab\tseparated", "non\u{a0}breaking"];
+        for split_compounds in [false, true] {
+            let config = TokenizerConfig { split_compounds, path_weight: 0, ..TokenizerConfig::default() };
+            for word in words {
+                let mut model = Model::detached().with_tokenizer(config);
+                model.add_text("doc", format!("some {word} here"));
+                model.add_text("other", "nothing to see");
+
+                let query = word.to_owned();
+                let found = paths(model.search(query.as_str()).unwrap());
+                assert_eq!(found, ["doc"], "{word:?}, split_compounds: {split_compounds}");
+                // and every word of it on its own
+                for part in query.split(is_split_character).filter(|part| part.chars().any(char::is_alphanumeric)) {
+                    assert!(model.total(part).unwrap() > 0, "{part:?} of {word:?}, split_compounds: {split_compounds}")
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn control_characters_are_sanitized() {
+        let text = sanitize("nul\0separated\x07bell\ttab\r\nnewline\u{85}next".to_owned());
+        assert_eq!(text, "nul separated bell tab  newline next");
+
+        let doc = Doc::new(&text, &TokenizerConfig::default());
+        ["nul", "separ", "bell", "tab", "newlin", "next"].iter().for_each(|word| {
+            assert_eq!(doc.tf.get(*word), Some(&1), "{word}")
+        });
+
+        let clean = "nothing to do here".to_owned();
+        let ptr = clean.as_ptr();
+        let clean = sanitize(clean);
+        assert_eq!(clean.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn compounds_are_split() {
+        assert_eq!(split_compound("state-of-the-art"), ["state", "of", "the", "art"]);
+        assert_eq!(split_compound("snake_case"), ["snake", "case"]);
+        assert_eq!(split_compound("camelCase"), ["camel", "Case"]);
+        assert_eq!(split_compound("HTTPServer"), ["HTTP", "Server"]);
+        assert_eq!(split_compound("parseHTTP2Request"), ["parse", "HTTP2", "Request"]);
+        assert!(split_compound("(__init__)").is_empty());
+        assert_eq!(split_compound("std::fs"), ["std", "fs"]);
+        assert!(split_compound("plain").is_empty());
+
+        let contents = contents(&[
+            ("a.rs", "let parse_config = readConfigFile;"),
+            ("b.txt", "a state-of-the-art parser"),
+            ("c.txt", "nothing here")
+        ]);
+
+        let (tx, _rx) = channel();
+        let tokenizer = TokenizerConfig { split_compounds: true, ..Default::default() };
+        let mut model = Model::new(tx, contents.len()).with_tokenizer(tokenizer);
+        model.add_contents(&contents);
+
+        ["config", "file", "read", "parse_config"].iter().for_each(|query| {
+            assert_eq!(model.search(*query).unwrap()[0].0, &PathBuf::from("a.rs"), "{query}")
+        });
+        ["art", "state-of-the-art"].iter().for_each(|query| {
+            assert_eq!(model.search(*query).unwrap()[0].0, &PathBuf::from("b.txt"), "{query}")
+        });
+    }
+
+    #[test]
+    fn case_sensitive_search() {
+        let contents = contents(&[
+            ("name.txt", "Mark wrote this"),
+            ("verb.txt", "mark the spot"),
+            ("none.txt", "nothing to see")
+        ]);
+
+        let (tx, _rx) = channel();
+        let tokenizer = TokenizerConfig { case_sensitive: true, ..Default::default() };
+        let mut model = Model::new(tx, contents.len()).with_tokenizer(tokenizer);
+        model.add_contents(&contents);
+
+        let ranks = model.search("Mark").unwrap();
+        assert_eq!(ranks[0].0, &PathBuf::from("name.txt"));
+        assert_eq!(ranks.len(), 1);
+
+        let ranks = model.search("mark").unwrap();
+        assert_eq!(ranks[0].0, &PathBuf::from("verb.txt"));
+        assert_eq!(ranks.len(), 1);
+    }
+
+    #[test]
+    fn words_are_lowercased_beyond_ascii() {
+        assert!(matches!(lowercase("hello"), Cow::Borrowed("hello")));
+        assert_eq!(lowercase("HeLLo"), Cow::<str>::Owned("hello".to_owned()));
+        assert!(matches!(lowercase("naïve"), Cow::Borrowed("naïve")));
+        assert_eq!(lowercase("CAFÉ"), Cow::<str>::Owned("café".to_owned()));
+        assert_eq!(lowercase("ЖУК"), "жук");
+        assert_eq!(lowercase("ΟΔΟΣ"), "οδος");
+
+        let contents = contents(&[
+            ("a.txt", "CAFÉ au lait"),
+            ("b.txt", "Ёлка и ЖУК"),
+            ("c.txt", "Straße")
+        ]);
+        let mut model = Model::detached().with_tokenizer(TokenizerConfig { analyzer: Analyzer::Unstemmed, path_weight: 0, ..TokenizerConfig::default() });
+        model.add_contents(&contents);
+
+        let found = |query| paths(model.search(query).unwrap());
+        assert_eq!(found("café"), ["a.txt"]);
+        assert_eq!(found("ёлка жук"), ["b.txt"]);
+        assert_eq!(found("straße"), ["c.txt"]);
+        assert_eq!(found("\"café au\""), ["a.txt"]);
+    }
+
+    #[test]
+    fn top_k_matches_full_scan() {
+        let contents = contents(&[
+            ("a.txt", "apple banana cherry apple"),
+            ("b.txt", "banana banana cherry"),
+            ("c.txt", "cherry"),
+            ("d.txt", "apple cherry durian"),
+            ("e.txt", "durian banana apple apple apple"),
+            ("f.txt", "cherry cherry cherry banana")
+        ]);
+
+        let nothing = Text::from("nothing".to_owned());
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+
+        for query in ["apple", "banana cherry", "apple durian", "apple apple banana", "cherry durian banana apple"] {
+            let full = model.search(query).unwrap().into_iter().filter(|(_, score)| *score > 0.0).collect::<Vec::<_>>();
+            assert_eq!(model.total(query).unwrap(), full.len(), "{query}");
+            for k in 1..=contents.len() {
+                let top = model.search_top_k(query, k).unwrap();
+                assert_eq!(top.len(), full.len().min(k), "{query} {k}");
+                top.iter().zip(full.iter()).for_each(|(top, full)| assert!((top.1 - full.1).abs() < 1e-6, "{query} {k}"))
+            }
+        }
+
+        // removed documents don't come back through stale postings
+        model.add_document(&contents[0].0, &nothing);
+        assert!(model.search_top_k("banana", 10).unwrap().iter().all(|(path, _)| *path != &contents[0].0))
+    }
+
+    #[test]
+    fn fields_are_scoped() {
+        let contents = vec![
+            (PathBuf::from("invoices/acme.txt"), Text { title: Some("Invoice".to_owned()), tags: Vec::new(), body: "please pay, it is overdue".to_owned(), analyzer: None }),
+            (PathBuf::from("letters/overdue.txt"), Text { title: Some("Reminder".to_owned()), tags: Vec::new(), body: "the invoice is attached".to_owned(), analyzer: None }),
+            (PathBuf::from("notes/misc.txt"), Text { title: None, tags: Vec::new(), body: "nothing here".to_owned(), analyzer: None })
+        ];
+
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+
+        let found = |query| paths(model.search(query).unwrap());
+        assert_eq!(found("title:invoice"), ["invoices/acme.txt"]);
+        assert_eq!(found("body:invoice"), ["letters/overdue.txt"]);
+        assert_eq!(found("path:overdue"), ["letters/overdue.txt"]);
+        assert_eq!(found("path:invoices/acme"), ["invoices/acme.txt"]);
+        assert_eq!(found("title:invoice body:overdue"), ["invoices/acme.txt"]);
+        assert_eq!(found("invoice").len(), 2);
+        assert_eq!(model.total("title:invoice").unwrap(), 1);
+        assert_eq!(model.search_top_k("body:overdue", 10).unwrap().len(), 1);
+
+        assert_eq!(model.search("author:me").unwrap_err(), QueryError::UnknownField("author".to_owned()))
+    }
+
+    #[test]
+    fn finalize_prunes_and_tightens_bounds() {
+        let contents = contents(&[("a", "kiwi kiwi kiwi"), ("b", "kiwi lime melon"), ("c", "kiwi lime"), ("d", "plum")]);
+        let mut model = Model::detached().with_min_df(2);
+        model.add_contents(&contents);
+        assert_eq!(model.df.keys().map(|t| &**t).collect::<HashSet::<_>>(), HashSet::from_iter(["kiwi", "lime"]));
+
+        let ranked = |model: &Model| model.search_top_k("kiwi lime", 10).unwrap().into_iter().map(|(path, score)| (path.clone(), score)).collect::<Vec::<_>>();
+        let before = ranked(&model);
+        let highest = |model: &Model| model.postings["kiwi"].postings.iter().map(|posting| posting.tf).fold(0.0, f32::max);
+        model.rm_document(&PathBuf::from("a"));
+        assert!(model.postings["kiwi"].max_tf > highest(&model));
+        let removed = ranked(&model);
+        model.finalize();
+        assert_eq!(model.postings["kiwi"].max_tf, highest(&model));
+        assert_eq!(ranked(&model), removed);
+        assert_ne!(before, removed);
+
+        // terms of documents added since are pruned by the next run
+        model.add_text("e", "fig");
+        assert!(model.df.contains_key("fig"));
+        model.finalize();
+        assert!(!model.df.contains_key("fig") && !model.postings.contains_key("fig"));
+    }
+
+    #[test]
+    fn markdown_is_indexed_without_its_markup() {
+        let markdown = "# Release notes #\n\
+            \n\
+            > **Note:** read the [upgrade guide](https://example.org/upgrade \"guide\") first,\n\
+            > or see ![the diagram](img/flow.png) and [the docs][docs].\n\
+            \n\
+            - [x] ship `parse_file` with <kbd>Ctrl</kbd>+<kbd>C</kbd>\n\
+            1. _quickly_ fix snake_case \\*names\\*\n\
+            \n\
+            | name | kind |\n\
+            |------|:----:|\n\
+            | kiwi | ~~fruit~~ |\n\
+            \n\
+            ***\n\
+            ```rust\n\
+            let url = \"https://example.org\"; // *kept*\n\
+            ```\n\
+            mail <someone@example.org>\n\
+            \n\
+            [docs]: https://docs.example.org/guide\n";
+
+        let stripped = plain_text(markdown);
+        let lines = stripped.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec::<_>>();
+        assert_eq!(lines, [
+            "Release notes",
+            "Note: read the upgrade guide first,",
+            "or see the diagram and the docs.",
+            "ship parse_file with Ctrl+C",
+            "quickly fix snake_case *names*",
+            "name   kind",
+            "kiwi   fruit",
+            "let url = \"https://example.org\"; // *kept*",
+            "mail someone@example.org"
+        ]);
+
+        let config = TokenizerConfig::default();
+        let (text, raw) = (Markdown::parse_str(markdown, "md").unwrap(), Txt::parse_str(markdown, "md").unwrap());
+        let (stripped, raw) = (tokenize(&text.body, &config), tokenize(&raw.body, &config));
+        // the words that are read are all there, and none of them runs into the markup around it
+        for term in ["upgrad", "guid", "diagram", "doc", "ctrl+c", "kiwi", "fruit", "url"] {
+            assert!(stripped.iter().any(|t| t == term), "{term}");
+        }
+        assert!(!raw.iter().any(|t| t == "diagram" || t == "ctrl+c"));
+        // the destinations of the links, the tags and the markers aren't
+        for markup in ["](", "][", "<", "png", "kbd", "guide"] {
+            assert!(!stripped.iter().any(|t| t.contains(markup)) && raw.iter().any(|t| t.contains(markup)), "{markup}");
+        }
+        assert!(stripped.len() < raw.len());
+    }
+
+    // a PDF of a single page that's nothing but an image of `word`, in block letters, the way a scan has no text layer
+    #[cfg(feature = "ocr")]
+    fn write_scanned_pdf(path: &Path, word: &str) {
+        let glyph = |c: char| match c {
+            'C' => [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."],
+            'E' => ["#####", "#....", "#....", "####.", "#....", "#....", "#####"],
+            'I' => ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "#####"],
+            'N' => ["#...#", "##..#", "#.#.#", "#.#.#", "#..##", "#...#", "#...#"],
+            'O' => [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."],
+            'V' => ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."],
+            _ => panic!("no glyph for {c:?}")
+        };
+        let (scale, margin) = (12, 48);
+        let (width, height) = (2 * margin + word.len() * 6 * scale - scale, 2 * margin + 7 * scale);
+        let mut pixels = vec![255u8; width * height];
+        for (i, c) in word.chars().enumerate() {
+            for (row, line) in glyph(c).iter().enumerate() {
+                for (col, _) in line.char_indices().filter(|(_, dot)| *dot == '#') {
+                    for y in 0..scale {
+                        let (x, y) = (margin + (i * 6 + col) * scale, margin + row * scale + y);
+                        pixels[y * width + x..y * width + x + scale].fill(0)
+                    }
+                }
+            }
+        }
+
+        let content = format!("q {w} 0 0 {h} 0 0 cm /Im0 Do Q", w = width / 2, h = height / 2);
+        let objects = [
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+            format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {w} {h}] /Resources << /XObject << /Im0 5 0 R >> >> /Contents 4 0 R >>", w = width / 2, h = height / 2).into_bytes(),
+            [format!("<< /Length {} >>\nstream\n", content.len()).as_bytes(), content.as_bytes(), b"\nendstream"].concat(),
+            [format!("<< /Type /XObject /Subtype /Image /Width {width} /Height {height} /ColorSpace /DeviceGray /BitsPerComponent 8 /Length {} >>\nstream\n", pixels.len()).as_bytes(), &pixels, b"\nendstream"].concat()
+        ];
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n")
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        offsets.iter().for_each(|offset| pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes()));
+        pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", objects.len() + 1).as_bytes());
+        fs::write(path, pdf).unwrap()
+    }
+
+    #[cfg(feature = "ocr")]
+    #[test]
+    #[ignore = "needs pdftoppm and tesseract"]
+    fn scanned_pdfs_are_ocred() {
+        let dir = temp_dir("ocr");
+        write_scanned_pdf(&dir.join("scan.pdf"), "INVOICE");
+        let (text, failed) = parse(&dir.join("scan.pdf"), &ParseConfig::default()).unwrap();
+        assert_eq!(failed, 0);
+        assert!(tokenize(&text.body, &TokenizerConfig::default()).iter().any(|t| t == "invoic"), "{body:?}", body = text.body);
+    }
+
+    #[test]
+    fn rtf_control_words_are_not_indexed() {
+        let dir = temp_dir("rtf");
+        fs::write(dir.join("letter.rtf"), "{\\rtf1\\ansi\\ansicpg1252\\deff0\r\n\
+            {\\fonttbl{\\f0\\froman\\fcharset0 Times New Roman;}{\\f1\\fswiss Arial;}}\r\n\
+            {\\colortbl;\\red255\\green0\\blue0;}\r\n\
+            {\\info{\\title Notice of lease}{\\author Someone}}\r\n\
+            {\\*\\generator Riched20 10.0;}\\viewkind4\\uc1\r\n\
+            \\pard\\sa200\\f0\\fs24 Dear {\\b tenant},\\par\r\n\
+            the rent is due at the caf\\'e9 on the 1st\\emdash in \\u8364?uros only\\par\r\n\
+            {\\pict\\pngblip\\picw10 89504e470d0a1a0a}\\i\\cf1 Regards\\i0\\~\\{landlord\\}\\par\r\n\
+            }\r\n").unwrap();
+
+        let (text, _) = parse(&dir.join("letter.rtf"), &ParseConfig::default()).unwrap();
+        assert_eq!(text.title.as_deref(), Some("Notice of lease"));
+        let lines = rtf::text(&fs::read_to_string(dir.join("letter.rtf")).unwrap()).unwrap().body;
+        assert_eq!(lines.lines().collect::<Vec::<_>>(), ["Dear tenant,", "the rent is due at the café on the 1st—in €uros only", "Regards {landlord}"]);
+
+        let tokens = tokenize(&text.body, &TokenizerConfig::default());
+        for word in ["tenant", "café", "rent", "regard", "landlord"] {
+            assert!(tokens.iter().any(|t| t == word), "{word}");
+        }
+        for control in ["rtf1", "ansi", "fonttbl", "par", "pard", "fs24", "f0", "b", "emdash", "u8364", "pict", "time", "arial", "someon", "riched20", "89504e470d0a1a0a"] {
+            assert!(!tokens.iter().any(|t| t == control), "{control}");
+        }
+        assert!(!tokens.iter().any(|t| t.contains(['\\', '{', '}']) && t != "{landlord}"));
+
+        fs::write(dir.join("plain.rtf"), "not rich at all").unwrap();
+        assert!(parse(&dir.join("plain.rtf"), &ParseConfig::default()).is_err());
+    }
+
+    #[test]
+    fn csv_cells_are_indexed_whole() {
+        let dir = temp_dir("csv");
+        fs::write(dir.join("people.csv"), "name,city,notes\r\n\"Doe, Jane\",\"New York, \"\"NY\"\"\",\"likes\nkiwis\"\r\nRoe,Paris,\r\n").unwrap();
+        fs::write(dir.join("people.tsv"), "name\tcity\nDoe, Jane\t\"Oslo\tNorway\"\n").unwrap();
+
+        // the quoted field is a single one, its comma, quotes and line break included
+        let rows = csv::rows("\"Doe, Jane\",\"New York, \"\"NY\"\"\"\n", ',').collect::<Vec::<_>>();
+        assert_eq!(rows, [["Doe, Jane", "New York, \"NY\""]]);
+
+        let (text, _) = parse(&dir.join("people.csv"), &ParseConfig::default()).unwrap();
+        // line breaks come out as spaces, like every control character does
+        assert_eq!(text.body, "name city notes Doe, Jane New York, \"NY\" likes kiwis Roe Paris  ");
+        assert!(text.tags.is_empty());
+
+        let config = ParseConfig { csv_headers: true, ..Default::default() };
+        let (text, _) = parse(&dir.join("people.csv"), &config).unwrap();
+        assert_eq!(text.tags, ["name", "city", "notes"]);
+        assert!(!text.body.contains("notes"));
+        let tokens = tokenize(&text.body, &TokenizerConfig::default());
+        assert_eq!(tokens[..5], ["doe", "jane", "new", "york", "ny"]);
+        assert!(!tokens.iter().any(|t| t.contains('"')));
+
+        let (text, _) = parse(&dir.join("people.tsv"), &config).unwrap();
+        assert_eq!(text.tags, ["name", "city"]);
+        assert_eq!(text.body, "Doe, Jane Oslo Norway ");
+
+        let (contents, _) = dir_get_contents(&[&dir], &WalkConfig::default(), &config).unwrap();
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+        let found = |query| {
+            let mut names = model.search(query).unwrap().into_iter().map(|(path, _)| path.file_name().unwrap().to_str().unwrap()).collect::<Vec::<_>>();
+            names.sort();
+            names
+        };
+        assert_eq!(found("\"doe jane\""), ["people.csv", "people.tsv"]);
+        assert_eq!(found("\"new york ny\""), ["people.csv"]);
+        // a cell is whole within its row, not apart from it: the cells of a row follow each other in the body,
+        // so a phrase goes on from the end of one into the start of the next, the way it does across sentences
+        assert_eq!(found("\"jane new\""), ["people.csv"]);
+        assert!(found("\"jane doe\"").is_empty());
+    }
+
+    #[test]
+    fn markdown_frontmatter_is_indexed_as_fields() {
+        let dir = temp_dir("frontmatter");
+        fs::write(dir.join("post.md"), "---\ntitle: \"Shipping the indexer\"\ntags: [rust, search]\ndate: 2024-05-01\nauthor:\n  name: someone\n---\n# Notes\nwritten in a weekend\n").unwrap();
+        fs::write(dir.join("list.md"), "---\r\ntags:\r\n  - gardening\r\n  - 'rust' # the fungus\r\n...\r\nplants get rust too\r\n").unwrap();
+        fs::write(dir.join("rule.md"), "---\nnot: closed\n\nrust belt\n").unwrap();
+
+        let config = ParseConfig::default();
+        let (post, _) = parse(&dir.join("post.md"), &config).unwrap();
+        assert_eq!(post.title.as_deref(), Some("Shipping the indexer"));
+        assert_eq!(post.tags, ["rust", "search"]);
+        assert_eq!(post.body, "Notes written in a weekend ");
+        let (list, _) = parse(&dir.join("list.md"), &config).unwrap();
+        assert_eq!((list.title, list.tags.as_slice()), (None, &["gardening".to_owned(), "rust".to_owned()][..]));
+        // frontmatter that doesn't end is the body
+        assert!(parse(&dir.join("rule.md"), &config).unwrap().0.body.trim_start().starts_with("not: closed"));
+        assert!(split_frontmatter("---\njust a rule\n---\n").is_none());
+        assert!(split_frontmatter("---\ntitle: \"unclosed\n---\n").is_none());
+        assert!(split_frontmatter("text\n---\ntitle: late\n---\n").is_none());
+
+        let (contents, _) = dir_get_contents(&[&dir], &WalkConfig::default(), &config).unwrap();
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+        let paths = |query| {
+            let mut paths = model.search(query).unwrap().into_iter().map(|(path, _)| path.file_name().unwrap().to_str().unwrap()).collect::<Vec::<_>>();
+            paths.sort_unstable();
+            paths
+        };
+        assert_eq!(paths("tags:rust"), ["list.md", "post.md"]);
+        assert_eq!(paths("body:rust"), ["list.md", "rule.md"]);
+        assert_eq!(paths("title:indexer"), ["post.md"]);
+        assert_eq!(paths("tags:someone").len() + paths("someone").len(), 0);
+    }
+
+    #[test]
+    fn phrases_and_operators_narrow_down_results() {
+        let contents = contents(&[
+            ("a.txt", "machine learning in python"),
+            ("b.txt", "learning about the machine, in rust"),
+            ("c.txt", "python and rust"),
+            ("d.txt", "machine learning with rust")
+        ]);
+
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+
+        let found = |query| {
+            let mut found = paths(model.search(query).unwrap());
+            found.sort_unstable();
+            found
+        };
+
+        assert_eq!(found("machine learning"), ["a.txt", "b.txt", "d.txt"]);
+        assert_eq!(found(r#""machine learning""#), ["a.txt", "d.txt"]);
+        assert_eq!(found(r#""machine learning" AND (python OR rust)"#), ["a.txt", "d.txt"]);
+        assert_eq!(found("python AND rust"), ["c.txt"]);
+        assert_eq!(found("machine AND (python OR nowhere)"), ["a.txt"]);
+        assert_eq!(model.total(r#""machine learning" AND rust"#).unwrap(), 1);
+        assert_eq!(model.search_top_k(r#""machine learning" AND rust"#, 10).unwrap()[0].0, &PathBuf::from("d.txt"));
+        assert!(model.snippet(&PathBuf::from("b.txt"), r#""in rust""#, 16).is_some_and(|snippet| snippet.contains("in rust")));
+        assert_eq!(model.search("a AND (b").unwrap_err(), QueryError::UnbalancedParens);
+
+        let and = |text| Query::from(text).with_operator(Operator::And);
+        assert_eq!(model.total(and("machine rust")).unwrap(), 2);
+        assert_eq!(model.total(and("machine python")).unwrap(), 1);
+        assert_eq!(model.total(and("python rust OR learning")).unwrap(), 4);
+
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len()).with_operator(Operator::And);
+        model.add_contents(&contents);
+        let mut found = paths(model.search("machine rust").unwrap());
+        found.sort_unstable();
+        assert_eq!(found, ["b.txt", "d.txt"]);
+        assert_eq!(model.total(Query::from("machine rust").with_operator(Operator::Or)).unwrap(), 4);
+    }
+
+    #[test]
+    fn searches_stop_at_the_deadline() {
+        let contents = contents(&[("a.txt", "alpha beta"), ("b.txt", "beta gamma"), ("c.txt", "gamma")]);
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+
+        let deadline = Deadline::after(Duration::from_secs(60));
+        assert_eq!(model.search_top_k(Query::from("alpha gamma").with_deadline(&deadline), 10).unwrap().len(), 3);
+        assert!(!deadline.passed());
+
+        for query in ["alpha gamma", r#""beta gamma""#] {
+            let deadline = Deadline::after(Duration::ZERO);
+            let query = Query::from(query).with_deadline(&deadline);
+            assert!(model.search_top_k(query, 10).unwrap().is_empty());
+            assert!(model.search(query).unwrap().is_empty());
+            assert!(deadline.passed());
+        }
+    }
+
+    #[test]
+    fn milestones_cover_every_corpus_size() {
+        let milestones = |docs_count, every| Model::calculate_milestones(docs_count, every);
+        for every in [Milestones::Percent(5), Milestones::Documents(10)] {
+            assert_eq!(milestones(0, every), []);
+            assert_eq!(milestones(1, every), [(1, 100)]);
+            assert_eq!(milestones(3, every), [(1, 33), (2, 66), (3, 100)]);
+        }
+
+        let every_percent = milestones(1000, Milestones::Percent(1));
+        assert_eq!(every_percent.len(), 100);
+        assert_eq!((every_percent[0], every_percent[99]), ((10, 1), (1000, 100)));
+        assert_eq!(milestones(1000, Milestones::Percent(30)), [(300, 30), (600, 60), (900, 90), (1000, 100)]);
+        assert_eq!(milestones(250, Milestones::Documents(100)), [(100, 40), (200, 80), (250, 100)]);
+        // below `PER_DOCUMENT_BELOW` every document is a milestone, however coarse they are
+        assert_eq!(milestones(PER_DOCUMENT_BELOW - 1, Milestones::Percent(50)).len(), PER_DOCUMENT_BELOW - 1);
+        assert_eq!(milestones(PER_DOCUMENT_BELOW, Milestones::Percent(50)).len(), 2);
+
+        let (tx, rx) = channel();
+        let contents = contents(&[("a.txt", "alpha"), ("b.txt", "beta"), ("c.txt", "gamma")]);
+        let mut model = Model::new(tx, contents.len()).with_milestones(Milestones::Percent(50));
+        model.add_contents(&contents);
+        drop(model);
+        let progress = rx.iter().filter_map(|signal| match signal {
+            Signal::Progress(percentage) => Some(percentage),
+            Signal::Done => None
+        }).collect::<Vec::<_>>();
+        assert_eq!(progress, [33, 66, 100]);
+
+        // a count that's only known once some documents are indexed sends the progress they made, then goes on
+        let (tx, rx) = channel();
+        let mut model = Model::new(tx, 0);
+        model.add_text("a.txt", "alpha");
+        model.add_text("b.txt", "beta");
+        model.expect(4);
+        model.add_text("c.txt", "gamma");
+        drop(model);
+        assert!(rx.iter().map(|signal| match signal { Signal::Progress(percentage) => percentage, Signal::Done => 0 }).eq([50, 75]));
+
+        assert_eq!(Milestones::parse("1%"), Some(Milestones::Percent(1)));
+        assert_eq!(Milestones::parse("500"), Some(Milestones::Documents(500)));
+        for invalid in ["0%", "101%", "0", "-1", "%", "five"] {
+            assert_eq!(Milestones::parse(invalid), None, "{invalid}")
+        }
+    }
+
+    #[test]
+    fn searches_are_scoped_to_a_subtree() {
+        let contents = contents(&[
+            ("docs/2024/a.txt", "quarterly revenue report"),
+            ("docs/2024/b.txt", "revenue forecast"),
+            ("docs/2024-old/c.txt", "revenue"),
+            ("notes/d.txt", "revenue report"),
+            ("notes/e.txt", "minutes")
+        ]);
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+
+        let sorted = |ranks: Ranks| {
+            let mut sorted = paths(ranks);
+            sorted.sort();
+            sorted
+        };
+        let scope = Path::new("docs/2024");
+        for text in ["revenue", "revenue report", "revenue AND report", "\"revenue forecast\""] {
+            let query = Query::from(text).with_scope(scope);
+            let found = sorted(model.search(query).unwrap());
+            assert!(!found.is_empty() && found.iter().all(|path| path.starts_with("docs/2024/")), "{text}: {found:?}");
+            assert_eq!(sorted(model.search_top_k(query, 10).unwrap()), found, "{text}");
+            assert_eq!(model.total(query).unwrap(), found.len(), "{text}")
+        }
+        assert_eq!(sorted(model.search(Query::from("revenue").with_scope(Path::new("docs"))).unwrap()).len(), 3);
+        assert!(model.search(Query::from("revenue").with_scope(Path::new("elsewhere"))).unwrap().is_empty());
+    }
+
+    #[test]
+    fn searches_are_filtered_by_extension() {
+        let contents = contents(&[
+            ("2024/invoice.pdf", "invoice for march"),
+            ("2024/invoice.txt", "invoice draft"),
+            ("2024/notes.md", "send the invoice"),
+            ("2023/old.PDF", "invoice for june"),
+            ("archive.zip!scans/invoice.pdf", "scanned invoice"),
+            ("README", "nothing to see")
+        ]);
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+
+        let found = |query: Query| {
+            let mut found = paths(model.search(query).unwrap());
+            found.sort();
+            assert_eq!(model.search_top_k(query, 10).unwrap().len(), found.len());
+            assert_eq!(model.total(query).unwrap(), found.len());
+            found
+        };
+        let pdf = ["pdf".to_owned()];
+        assert_eq!(found(Query::from("invoice").with_extensions(&pdf)), ["2023/old.PDF", "2024/invoice.pdf", "archive.zip!scans/invoice.pdf"]);
+        assert_eq!(found(Query::from("invoice").with_extensions(&[".md".to_owned(), "txt".to_owned()])), ["2024/invoice.txt", "2024/notes.md"]);
+        assert_eq!(found(Query::from("invoice").with_extensions(&pdf).with_scope(Path::new("2024"))), ["2024/invoice.pdf"]);
+        assert_eq!(found(Query::from("\"invoice for\"").with_extensions(&pdf)), ["2023/old.PDF", "2024/invoice.pdf"]);
+        assert_eq!(found(Query::from("invoice")).len(), 5);
+        assert!(found(Query::from("invoice").with_extensions(&["docx".to_owned()])).is_empty());
+    }
+
+    #[test]
+    fn phrases_match_adjacent_words_only() {
+        let contents = contents(&[
+            ("a.txt", "notes on learning, then machine learning"),
+            ("b.txt", "learning the machine"),
+            ("c.txt", "machine - learning"),
+            ("d.txt", "a machine for learning"),
+            ("e.txt", "nothing")
+        ]);
+        let mut model = Model::detached().with_tokenizer(TokenizerConfig { keep_originals: true, ..TokenizerConfig::default() });
+        model.add_contents(&contents);
+
+        let found = |query: Query| {
+            let mut found = paths(model.search(query).unwrap());
+            found.sort_unstable();
+            found
+        };
+        assert_eq!(found(Query::from(r#""machine learning""#)), ["a.txt", "c.txt"]);
+        assert_eq!(found(Query::from(r#""machines learned""#)), ["a.txt", "c.txt"]);
+        assert!(found(Query::from(r#""machines learned""#).with_exact(true)).is_empty());
+        assert_eq!(found(Query::from(r#""machine learning""#).with_exact(true)), ["a.txt", "c.txt"]);
+        assert_eq!(found(Query::from(r#""learning machine""#)), Vec::<&str>::new());
+        assert_eq!(found(Query::from(r#""learning the machine""#)), ["b.txt"]);
+        assert_eq!(found(Query::from("machine learning")), ["a.txt", "b.txt", "c.txt", "d.txt"]);
+        assert_eq!(found(Query::from(r#""machine learning" OR nothing"#)), ["a.txt", "c.txt", "e.txt"]);
+    }
+
+    #[test]
+    fn stop_words_are_neither_indexed_nor_searched() {
+        let stop_words = Box::leak(Box::new(StopWords::new(["the", "And", "have", ""])));
+        assert_eq!(stop_words.len(), 3);
+        let contents = contents(&[
+            ("a.txt", "The cat and the dog"),
+            ("b.txt", "having a dog, the end"),
+            ("c.txt", "nothing")
+        ]);
+        let mut model = Model::detached().with_tokenizer(TokenizerConfig { stop_words: Some(stop_words), ..TokenizerConfig::default() });
+        model.add_contents(&contents);
+
+        for word in ["the", "and", "have", "having"] {
+            assert!(!model.df.contains_key(word), "{word}");
+            assert!(model.postings.get(word).is_none(), "{word}");
+        }
+        assert!(model.df.contains_key("dog"));
+
+        let found = |query| {
+            let mut found = paths(model.search(query).unwrap());
+            found.sort_unstable();
+            found
+        };
+        assert!(found("the").is_empty());
+        assert_eq!(found("the dog"), found("dog"));
+        assert_eq!(found("\"cat and the dog\""), ["a.txt"]);
+        assert_eq!(found("\"having a dog\""), ["b.txt"]);
+        assert_eq!(model.discarded("the dog").unwrap(), [("the", Discard::StopWord)]);
+    }
+
+    #[test]
+    fn terms_every_document_has_dont_drown_out_rare_ones() {
+        let contents = contents(&[
+            ("a.txt", "report"),
+            ("b.txt", "report report"),
+            ("c.txt", "the quarterly report and the numbers in it")
+        ]);
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+
+        let ranks = model.search("report quarterly").unwrap();
+        assert_eq!(ranks.len(), 3);
+        assert_eq!(ranks[0].0.display().to_string(), "c.txt");
+        assert!(ranks.iter().all(|(_, score)| score.is_finite() && *score > 0.0));
+        assert_eq!(model.search_top_k("report quarterly", 1).unwrap()[0].0, ranks[0].0);
+        assert_eq!(model.total("report quarterly").unwrap(), 3);
+
+        // a term no document has adds nothing
+        assert_eq!(model.idf("absent"), 0.0);
+        assert!(model.search("report absent").unwrap().iter().all(|(_, score)| score.is_finite() && *score > 0.0));
+    }
+
+    #[test]
+    fn only_documents_that_score_are_returned() {
+        let paths = (0..50).map(|i| format!("doc{i}.txt")).collect::<Vec::<_>>();
+        let contents = paths.iter().enumerate().map(|(i, path)| {
+            let body = if i % 20 == 7 { format!("a kiwi and filler {i}") } else { format!("just filler {i}") };
+            (PathBuf::from(path), Text::from(body))
+        }).collect::<Contents>();
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+
+        assert_eq!(model.search("kiwi").unwrap().len(), 3);
+        assert_eq!(model.search_bm25("kiwi").unwrap().len(), 3);
+        assert_eq!(model.search_top_k("kiwi", 20).unwrap().len(), 3);
+        assert_eq!(model.total("kiwi").unwrap(), 3);
+
+        // a number that weighs nothing finds nothing
+        let model = model.with_numbers(Numbers::Exclude);
+        assert!(model.search("7").unwrap().is_empty());
+        assert!(model.search_top_k("7", 20).unwrap().is_empty());
+        assert_eq!(model.total("7").unwrap(), 0);
+    }
+
+    #[test]
+    fn bm25_doesnt_bury_long_documents() {
+        let long = format!("{} {}", "rust ".repeat(20), "words about other things ".repeat(45));
+        let contents = contents(&[
+            ("long.txt", long.as_str()),
+            ("short.txt", "rust is fast"),
+            ("a.txt", "nothing about it here"),
+            ("b.txt", "or here either")
+        ]);
+        let mut model = Model::detached();
+        model.add_contents(&contents);
+
+        assert_eq!(paths(model.search("rust").unwrap()), ["short.txt", "long.txt"]);
+        assert_eq!(paths(model.search_bm25("rust").unwrap()), ["long.txt", "short.txt"]);
+
+        // without length normalization the long document only gets further ahead
+        let model = Model { bm25: Bm25 { k1: 1.2, b: 0.0 }, ..model };
+        let ranks = model.search_bm25("rust").unwrap();
+        assert!(ranks[0].1 > ranks[1].1 && ranks[1].1 > 0.0);
+
+        // a term every document has still scores
+        let ranks = model.search_bm25("txt").unwrap();
+        assert_eq!(ranks.len(), 4);
+        assert!(ranks.iter().all(|(_, score)| *score > 0.0));
+
+        // the average length is kept as documents come and go
+        let mut model = model;
+        model.rm_document(&PathBuf::from("long.txt"));
+        model.add_text("c.txt", "rust again");
+        assert_eq!(model.total_count, model.docs.values().map(|doc| doc.count).sum::<usize>());
+    }
+
+    #[test]
+    fn shared_model_is_searched_while_written() {
+        let contents = contents(&[("a.txt", "alpha"), ("b.txt", "beta")]);
+        let (tx, _rx) = channel();
+        let shared = SharedModel::new(Model::new(tx, contents.len()));
+
+        std::thread::scope(|scope| {
+            let (writer, contents) = (shared.clone(), &contents);
+            scope.spawn(move || contents.iter().for_each(|(path, text)| writer.write().add_document(path, text)));
+            scope.spawn(|| (0..100).for_each(|_| assert!(shared.read().total("alpha beta").unwrap() <= 2)));
+        });
+
+        assert_eq!(shared.read().total("alpha beta").unwrap(), 2);
+    }
+
+    #[test]
+    fn path_weight_is_configurable() {
+        let contents = vec![
+            (PathBuf::from("2023/invoices/acme.pdf"), Text::default()),
+            (PathBuf::from("notes/todo.txt"), Text::from("send acme the invoice".to_owned())),
+            (PathBuf::from("notes/misc.txt"), Text::from("nothing here".to_owned()))
+        ];
+
+        let model = |path_weight| {
+            let (tx, _rx) = channel();
+            let mut model = Model::new(tx, contents.len()).with_tokenizer(TokenizerConfig { path_weight, ..Default::default() });
+            model.add_contents(&contents);
+            model
+        };
+        let found = |model: &Model, query| paths(model.search_top_k(query, 10).unwrap());
+
+        let weighted = model(1);
+        assert_eq!(found(&weighted, "acme invoice").len(), 2);
+        assert_eq!(weighted.total("acme invoice").unwrap(), 2);
+        assert_eq!(found(&weighted, "body:acme"), ["notes/todo.txt"]);
+
+        let unweighted = model(0);
+        assert_eq!(found(&unweighted, "acme invoice"), ["notes/todo.txt"]);
+        assert_eq!(unweighted.total("acme").unwrap(), 1);
+        assert_eq!(found(&unweighted, "path:acme"), ["2023/invoices/acme.pdf"]);
+        // the body of a document whose path doesn't count has none of the words of its path,
+        // which a single result mustn't be pruned for
+        let contents = vec![
+            (PathBuf::from("x/acme.txt"), Text::from("a body that is a good deal longer than the other one".to_owned())),
+            (PathBuf::from("y/acme.pdf"), Text::from("short".to_owned()))
+        ];
+        let (tx, _rx) = channel();
+        let mut pruned = Model::new(tx, contents.len()).with_tokenizer(TokenizerConfig { path_weight: 0, ..Default::default() });
+        pruned.add_contents(&contents);
+        let best = pruned.search("path:acme").unwrap()[0].0.clone();
+        assert_eq!(best, PathBuf::from("y/acme.pdf"));
+        assert_eq!(paths(pruned.search_top_k("path:acme", 1).unwrap()), ["y/acme.pdf"]);
+        assert_eq!(found(&unweighted, "body:acme"), ["notes/todo.txt"]);
+
+        let heavy = model(5);
+        assert_eq!(found(&heavy, "acme")[0], "2023/invoices/acme.pdf");
+        assert_eq!(found(&heavy, "body:acme"), ["notes/todo.txt"]);
+
+        // the words of a path the model borrows are slices of it, unless stemming changed them
+        let (path, doc) = heavy.docs.iter().find(|(path, _)| path.ends_with("acme.pdf")).unwrap();
+        let bytes = path.as_os_str().as_encoded_bytes().as_ptr_range();
+        let sliced = |t| matches!(doc.path_tf.get_key_value(t), Some((Held::Borrowed(t), _)) if bytes.contains(&t.as_ptr()));
+        assert!(sliced("2023") && sliced("pdf"), "{:?}", doc.path_tf);
+    }
+
+    #[test]
+    fn exact_mode_matches_originals() {
+        let contents = contents(&[
+            ("testing.txt", "we are testing it"),
+            ("tested.txt", "we tested it")
+        ]);
+
+        let (tx, _rx) = channel();
+        let tokenizer = TokenizerConfig { keep_originals: true, ..Default::default() };
+        let mut model = Model::new(tx, contents.len()).with_tokenizer(tokenizer);
+        model.add_contents(&contents);
+
+        let mut stemmed = paths(model.search("testing").unwrap());
+        stemmed.sort();
+        assert_eq!(stemmed, ["tested.txt", "testing.txt"]);
+
+        let exact = model.search(Query::from("testing").with_exact(true)).unwrap();
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].0, &PathBuf::from("testing.txt"));
+
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+        assert_eq!(model.search(Query::from("testing").with_exact(true)).unwrap_err(), QueryError::OriginalsNotKept)
+    }
+
+    #[test]
+    fn max_token_len_is_configurable() {
+        let long = "a1".repeat(50);
+        let contents = contents(&[("long.txt", &format!("short {long}"))]);
+
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+        assert!(model.search(long.as_str()).unwrap().is_empty());
+
+        for max_token_len in [100, 0] {
+            let (tx, _rx) = channel();
+            let tokenizer = TokenizerConfig { max_token_len, ..Default::default() };
+            let mut model = Model::new(tx, contents.len()).with_tokenizer(tokenizer);
+            model.add_contents(&contents);
+            assert_eq!(model.search(long.as_str()).unwrap()[0].0, &PathBuf::from("long.txt"), "{max_token_len}")
+        }
+    }
+
+    #[test]
+    fn absent_query_words_are_explained() {
+        let contents = contents(&[("a.txt", "runs quickly"), ("b.txt", "walked slowly")]);
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len()).with_tokenizer(TokenizerConfig {keep_originals: true, ..Default::default()});
+        model.add_contents(&contents);
+
+        let explained = model.search_explained("swimming flying").unwrap();
+        assert!(explained.ranks.is_empty());
+        assert_eq!(explained.absent, [("swimming", Cow::from("swim")), ("flying", Cow::from("fli"))]);
+
+        assert_eq!(model.absent("running swimming").unwrap(), [("swimming", Cow::from("swim"))]);
+        assert_eq!(model.absent(Query::from("running").with_exact(true)).unwrap(), [("running", Cow::from("running"))]);
+        assert!(model.search_explained("runs swimming").unwrap().absent.is_empty());
+    }
+
+    #[test]
+    fn misspelled_queries_are_corrected() {
+        let contents = contents(&[("a.txt", "overdue invoices from acme"), ("b.txt", "invoice the payment"), ("c.txt", "happy customers")]);
+        let mut stems = Model::detached();
+        stems.add_contents(&contents);
+        let mut originals = Model::detached().with_tokenizer(TokenizerConfig {keep_originals: true, ..Default::default()});
+        originals.add_contents(&contents);
+
+        for model in [&stems, &originals] {
+            let suggest = |query: &str| model.suggest(query).unwrap();
+            assert_eq!(suggest("invioces").as_deref(), Some("invoices"));
+            assert_eq!(suggest("paymnet AND \"overdue invoices\"").as_deref(), Some("payment AND \"overdue invoices\""));
+            // `hapy` is stemmed to `hapi`, which isn't the start of `happy`, the word is found in the document
+            assert_eq!(suggest("hapy").as_deref(), Some("happy"));
+            assert_eq!(suggest("invoices"), None);
+            assert_eq!(suggest("acm"), None);
+            assert_eq!(suggest("zzzzzzzz"), None);
+        }
+        assert_eq!(originals.suggest(Query::from("invoicess").with_exact(true)).unwrap().as_deref(), Some("invoices"));
+
+        let explained = originals.search_explained("custmoers").unwrap();
+        assert!(explained.ranks.is_empty());
+        assert_eq!(explained.suggestion.as_deref(), Some("customers"));
+        assert_eq!(stems.suggest("custmoers").unwrap(), None);
+        assert_eq!(originals.search_explained("customers").unwrap().suggestion, None);
+
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("ab", "ba", 1), Some(1));
+        assert_eq!(edit_distance("abc", "xyz", 2), None);
+        assert_eq!(edit_distance("naïve", "naive", 1), Some(1));
+    }
+
+    #[test]
+    fn terms_are_listed_by_frequency() {
+        let contents = contents(&[("a.txt", "apple apricot banana"), ("b.txt", "apple banana"), ("c.txt", "apple")]);
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len()).with_tokenizer(TokenizerConfig {keep_originals: true, ..Default::default()});
+        model.add_contents(&contents);
+
+        // the path tokens are terms too
+        let (terms, total) = model.terms("", 3);
+        assert_eq!(terms, [("appl", 3), ("txt", 3), ("banana", 2)]);
+        assert!(total > 3);
+        assert_eq!(model.terms("ap", 10), (vec![("appl", 3), ("apricot", 1)], 2));
+        assert_eq!(model.terms("=ap", 10).0, [("=apple", 3), ("=apricot", 1)]);
+
+        model.rm_document(&PathBuf::from("a.txt"));
+        assert_eq!(model.terms("apr", 10), (vec![], 0));
+    }
+
+    #[test]
+    fn discarded_query_words_are_explained() {
+        let contents = contents(&[("a.txt", "short words only")]);
+
+        let (tx, _rx) = channel();
+        let mut model = Model::new(tx, contents.len());
+        model.add_contents(&contents);
+
+        let long = "x".repeat(70);
+        let query = format!("short {long} --");
+        let explained = model.search_explained(query.as_str()).unwrap();
+        assert_eq!(explained.ranks.len(), 1);
+        assert_eq!(explained.discarded, [(long.as_str(), Discard::TooLong), ("--", Discard::Empty)]);
+    }
+
+    #[test]
+    fn saved_indexes_rank_like_the_ones_they_were_saved_from() {
+        let dir = temp_dir("saved_index");
+        let root = dir.join("docs");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("a.txt"), "the quick brown fox jumps over the lazy dog").unwrap();
+        fs::write(root.join("b.md"), "---\ntitle: Foxes\ntags: [animals]\n---\nfoxes and hounds, the fox hunt").unwrap();
+        fs::write(root.join("c.txt"), "a lazy afternoon with a brown dog").unwrap();
+        let path = dir.join("index");
+        let (walk, parse) = (WalkConfig::default(), ParseConfig::default());
+
+        let (contents, report) = dir_get_contents(&[&root], &walk, &parse).unwrap();
+        let contents = Box::leak(Box::new(contents));
+        let mut model = Model::detached();
+        model.stamps.extend(report.stamps);
+        model.add_contents(contents);
+        model.save_to(&path, &parse).unwrap();
+
+        let saved = Saved::read(&path, &TokenizerConfig::default(), &parse).unwrap();
+        assert_eq!(saved.len(), 3);
+        let (parsed, report) = dir_get_contents_since(&[&root], &walk, &parse, None, &saved).unwrap();
+        assert!(parsed.is_empty());
+        let mut loaded = Model::detached();
+        loaded.load_from(saved, &report.unchanged);
+
+        let ranks = |model: &Model, query: &str| {
+            let mut ranks = model.search(query).unwrap().into_iter().map(|(path, score)| (path.clone(), score)).collect::<Vec::<_>>();
+            ranks.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            ranks
+        };
+        for query in ["fox", "lazy dog", "\"brown dog\"", "title:foxes", "tags:animals", "txt", "hounds OR afternoon"] {
+            assert_eq!(ranks(&model, query), ranks(&loaded, query), "{query}");
+        }
+        assert_eq!(loaded.snippet(&root.join("a.txt"), "lazy", 20), model.snippet(&root.join("a.txt"), "lazy", 20));
+        assert_eq!(loaded.df, model.df);
+
+        // only what changed is parsed again, and what's gone isn't loaded
+        loaded.save_to(&path, &parse).unwrap();
+        fs::remove_file(root.join("a.txt")).unwrap();
+        fs::write(root.join("c.txt"), "a lazy afternoon with a brown cat").unwrap();
+        fs::write(root.join("d.txt"), "the quick hound").unwrap();
+        let saved = Saved::read(&path, &TokenizerConfig::default(), &parse).unwrap();
+        let (parsed, report) = dir_get_contents_since(&[&root], &walk, &parse, None, &saved).unwrap();
+        let mut parsed_paths = parsed.iter().map(|(path, _)| path.clone()).collect::<Vec::<_>>();
+        parsed_paths.sort();
+        assert_eq!(parsed_paths, [root.join("c.txt"), root.join("d.txt")]);
+        assert_eq!(report.unchanged, [root.join("b.md")]);
+
+        let mut reloaded = Model::detached();
+        reloaded.load_from(saved, &report.unchanged);
+        reloaded.add_contents(Box::leak(Box::new(parsed)));
+        assert_eq!(ranks(&reloaded, "quick").into_iter().map(|(path, _)| path).collect::<Vec::<_>>(), [root.join("d.txt")]);
+        assert_eq!(ranks(&reloaded, "cat").len(), 1);
+        assert!(ranks(&reloaded, "dog").is_empty());
+        assert_eq!(ranks(&reloaded, "hunt").len(), 1);
+
+        // another tokenizer would tokenize the queries to other terms than the saved documents have
+        let tokenizer = TokenizerConfig { keep_originals: true, ..TokenizerConfig::default() };
+        assert_eq!(Saved::read(&path, &tokenizer, &parse).err().map(|err| err.kind()), Some(IoErrorKind::InvalidData));
+        // and the unchanged files would keep what they were parsed to with the options they were saved with
+        let csv_headers = ParseConfig { csv_headers: true, ..ParseConfig::default() };
+        assert_eq!(Saved::read(&path, &TokenizerConfig::default(), &csv_headers).err().map(|err| err.kind()), Some(IoErrorKind::InvalidData));
+    }
 }
@@ -21,30 +21,114 @@
 // OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
-use std::fs::read_dir;
+use std::fs::{canonicalize, read_dir};
+use std::ffi::OsString;
 use std::path::PathBuf;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::io::{Result as IoResult, Error as IoError, ErrorKind as IoErrorKind};
+
+use crate::ignore::Ignore;
 
 pub struct DirRec {
-    stack: VecDeque::<PathBuf>
+    root: PathBuf,
+    // paths to visit, with the depth of what's in them when they're directories,
+    // 0 for the files right in the root
+    stack: VecDeque::<(PathBuf, usize)>,
+    stop_on_error: bool,
+    // names of the directories that are never descended into
+    excluded: HashSet::<OsString>,
+    // patterns of the paths below the root that are skipped, directories along with everything in them
+    ignore: Ignore,
+    follow_symlinks: bool,
+    max_depth: Option::<usize>,
+    // canonical paths of the directories entered so far, a symlink back to one of them
+    // (or a second one to the same directory) is not entered again
+    visited: HashSet::<PathBuf>
 }
 
 impl DirRec {
     #[inline(always)]
     pub fn new<P: Into::<PathBuf>>(root: P) -> DirRec {
-        DirRec {stack: vec![root.into()].into()}
+        let root = root.into();
+        DirRec {
+            stack: vec![(root.clone(), 0)].into(),
+            root,
+            stop_on_error: cfg!(feature = "dir_rec_stop_on_error"),
+            excluded: HashSet::new(),
+            ignore: Ignore::default(),
+            follow_symlinks: true,
+            max_depth: None,
+            visited: HashSet::new()
+        }
+    }
+
+    // skip the directories with any of these names, along with everything in them,
+    // the root itself is walked whatever its name
+    #[inline(always)]
+    pub fn with_excluded<I>(mut self, names: I) -> DirRec
+    where
+        I: IntoIterator,
+        I::Item: Into::<OsString>
+    {
+        self.excluded = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // skip the files and directories whose paths `ignore` matches, relative to the root. nothing below a directory
+    // that's skipped is looked at, not even what a negated pattern would take back
+    #[inline(always)]
+    pub fn with_ignore(mut self, ignore: Ignore) -> DirRec {
+        self.ignore = ignore;
+        self
+    }
+
+    // without following, symlinks are skipped, to files and to directories alike, the root itself is walked
+    // whatever it is. either way no directory is walked twice, so cycles of symlinks end
+    #[inline(always)]
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> DirRec {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    // don't descend into directories more than `depth` levels below the root, 0 walks only the files right in it
+    #[inline(always)]
+    pub fn with_max_depth(mut self, depth: usize) -> DirRec {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    // stop the walk after yielding the first unreadable directory
+    #[inline(always)]
+    pub fn with_stop_on_error(mut self, stop_on_error: bool) -> DirRec {
+        self.stop_on_error = stop_on_error;
+        self
     }
 }
 
 impl Iterator for DirRec {
-    type Item = PathBuf;
+    // directories that could not be read are yielded as errors,
+    // except for the ones that disappeared mid-walk, these are skipped silently
+    type Item = IoResult::<PathBuf>;
 
     fn next(&mut self) -> Option::<Self::Item> {
-        while let Some(p) = self.stack.pop_front() {
-            if p.is_file() { return Some(p) }
-            let Ok(es) = read_dir(&p) else { continue };
+        while let Some((p, depth)) = self.stack.pop_front() {
+            if p.is_file() { return Some(Ok(p)) }
+            if self.max_depth.is_some_and(|max_depth| depth > max_depth) { continue }
+            if !self.visited.insert(canonicalize(&p).unwrap_or_else(|_| p.clone())) { continue }
+            let es = match read_dir(&p) {
+                Ok(es) => es,
+                Err(err) if matches!(err.kind(), IoErrorKind::NotFound | IoErrorKind::NotADirectory) => continue,
+                Err(err) => {
+                    if self.stop_on_error { self.stack.clear() }
+                    return Some(Err(IoError::new(err.kind(), format!("could not read {p:?}: {err}"))))
+                }
+            };
             es.filter_map(Result::ok).for_each(|e| {
-                self.stack.push_back(e.path())
+                if !self.follow_symlinks && e.file_type().is_ok_and(|t| t.is_symlink()) { return }
+                let path = e.path();
+                if self.excluded.contains(&e.file_name()) && path.is_dir() { return }
+                if !self.ignore.is_empty() && self.ignore.is_ignored(path.strip_prefix(&self.root).unwrap_or(&path), path.is_dir()) { return }
+                self.stack.push_back((path, depth + 1))
             });
         } None
     }
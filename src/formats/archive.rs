@@ -0,0 +1,228 @@
+use std::fs::File;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf, Component};
+use std::io::{BufReader, Read, Result as IoResult, Error as IoError, ErrorKind as IoErrorKind};
+
+use log::warn;
+use zip::ZipArchive;
+use zip::read::ZipFile;
+use tar::{Archive as TarArchive, Entry as TarEntry};
+use flate2::read::GzDecoder;
+
+use crate::core::GIG;
+
+// between the path of an archive and the name of an entry in the path it's indexed under, `docs.zip!notes/todo.txt`.
+// it's the first `!` right after a path `Kind::of` takes for an archive, any other `!` is part of a name
+pub const SEPARATOR: &str = "!";
+
+// decompressed bytes of a single entry, the same as a file of its own may have
+pub const MAX_ENTRY_LEN: u64 = GIG;
+// decompressed bytes of all the entries of an archive together, whatever comes after that is skipped
+pub const MAX_ARCHIVE_LEN: u64 = 4 * GIG;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Zip,
+    Tar,
+    // `.tar.gz` or `.tgz`
+    TarGz
+}
+
+impl Kind {
+    // by the name of the file, which is all there is to go on before opening it
+    pub fn of(path: &Path) -> Option::<Kind> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(Kind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Kind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Kind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+// the path an entry of `archive` is indexed under
+pub fn virtual_path(archive: &Path, entry: &str) -> PathBuf {
+    let mut path = OsString::from(archive.as_os_str());
+    path.push(SEPARATOR);
+    path.push(entry);
+    path.into()
+}
+
+// the archive and the name of the entry in it that `virtual_path` made `path` of: split at the first
+// `SEPARATOR` that follows the name of an archive, so a `!` in a directory or an entry name stays where it is
+pub fn split_virtual_path(path: &Path) -> Option::<(&Path, &str)> {
+    let path = path.to_str()?;
+    path.match_indices(SEPARATOR)
+        .map(|(i, _)| (Path::new(&path[..i]), &path[i + SEPARATOR.len()..]))
+        .find(|(archive, entry)| !entry.is_empty() && Kind::of(archive).is_some())
+}
+
+// whether the virtual path of `entry` in `archive` splits back into the two of them
+#[inline]
+pub fn is_addressable(archive: &Path, entry: &str) -> bool {
+    split_virtual_path(&virtual_path(archive, entry)) == Some((archive, entry))
+}
+
+// the name `for_each_entry` gives a zip entry, `None` for the ones it passes over
+#[inline]
+fn zip_entry_name(entry: &ZipFile::<impl Read>) -> Option::<String> {
+    // names that would point outside of the archive (`../..`, `/etc`) have no path to be indexed under
+    let name = entry.enclosed_name().filter(|_| entry.is_file())?;
+    Some(name.to_string_lossy().replace('\\', "/"))
+}
+
+// the name `for_each_entry` gives a tar entry, `None` for the ones it passes over, like `zip_entry_name` does
+fn tar_entry_name(entry: &TarEntry::<impl Read>) -> IoResult::<Option::<String>> {
+    let path = entry.path()?;
+    let mut parts = Vec::new();
+    for part in path.components() {
+        match part {
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            Component::CurDir => {},
+            // the same names that `enclosed_name` leaves out of zip archives, `../..` and `/etc`
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return Ok(None)
+        }
+    }
+    Ok((!parts.is_empty()).then(|| parts.join("/")))
+}
+
+#[inline]
+fn tar_reader(file: BufReader::<File>, kind: Kind) -> Box::<dyn Read> {
+    if kind == Kind::TarGz { Box::new(GzDecoder::new(file)) } else { Box::new(file) }
+}
+
+// the contents of the entry `for_each_entry` names `name`, what the server streams for a virtual path.
+// zip archives are read at the entry straight away, tar archives have no index and are read up to it
+pub fn read_entry(path: &Path, kind: Kind, name: &str) -> IoResult::<Vec::<u8>> {
+    let not_found = || IoError::new(IoErrorKind::NotFound, format!("there is no `{name}` in {path:?}"));
+    let file = BufReader::new(File::open(path)?);
+    let mut bytes = Vec::new();
+    match kind {
+        Kind::Zip => {
+            let mut zip = ZipArchive::new(file).map_err(invalid)?;
+            let named = |zip: &mut ZipArchive::<_>, i| zip.by_index(i).ok().and_then(|entry| zip_entry_name(&entry)).is_some_and(|n| n == name);
+            // the names that `zip_entry_name` rewrote are looked for one by one
+            let i = match zip.index_for_name(name).filter(|&i| named(&mut zip, i)) {
+                Some(i) => i,
+                None => (0..zip.len()).find(|&i| named(&mut zip, i)).ok_or_else(not_found)?
+            };
+            zip.by_index(i).map_err(invalid)?.take(MAX_ENTRY_LEN).read_to_end(&mut bytes)?;
+        },
+        Kind::Tar | Kind::TarGz => {
+            let mut tar = TarArchive::new(tar_reader(file, kind));
+            let mut entry = tar.entries()?.find_map(|entry| {
+                entry.and_then(|entry| Ok((tar_entry_name(&entry)?.is_some_and(|n| n == name) && entry.header().entry_type().is_file()).then_some(entry))).transpose()
+            }).ok_or_else(not_found)??;
+            (&mut entry).take(MAX_ENTRY_LEN).read_to_end(&mut bytes)?;
+        }
+    }
+    if bytes.len() as u64 >= MAX_ENTRY_LEN {
+        return Err(IoError::new(IoErrorKind::InvalidData, format!("`{name}` in {path:?} is too big")))
+    }
+    Ok(bytes)
+}
+
+// what it takes to read the entries of an archive within `MAX_ENTRY_LEN` and `MAX_ARCHIVE_LEN`
+struct Budget {
+    read: u64,
+    // entries that were skipped for their size
+    skipped: usize
+}
+
+impl Budget {
+    // the contents of `entry`, or `None` when it's too big, in which case it isn't read any further than the limit
+    fn read(&mut self, entry: impl Read) -> IoResult::<Option::<Vec::<u8>>> {
+        let limit = MAX_ENTRY_LEN.min(MAX_ARCHIVE_LEN - self.read);
+        let mut bytes = Vec::new();
+        entry.take(limit).read_to_end(&mut bytes)?;
+        self.read += bytes.len() as u64;
+        if bytes.len() as u64 >= limit {
+            self.skipped += 1;
+            return Ok(None)
+        }
+        Ok(Some(bytes))
+    }
+
+    // whether to go on with the next entry after `read` of `name` came out as it did
+    fn keep_going(&mut self, read: IoResult::<Option::<Vec::<u8>>>, path: &Path, name: &str, on_entry: &mut impl FnMut(&str, Vec::<u8>)) -> bool {
+        match read {
+            Ok(Some(bytes)) => on_entry(name, bytes),
+            Ok(None) => warn!("skipping `{name}` in {path:?}, the entry is too big"),
+            Err(err) => {
+                warn!("could not read {path:?} past `{name}`: {err}");
+                self.skipped += 1;
+                return false
+            }
+        }
+        true
+    }
+
+    #[inline]
+    fn exhausted(&self) -> bool {
+        self.read >= MAX_ARCHIVE_LEN
+    }
+}
+
+#[inline]
+fn invalid(err: impl std::error::Error + Send + Sync + 'static) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, err)
+}
+
+// `on_entry` gets the name, with `/` separators, and the contents of every file in the archive. an archive that
+// breaks past its start keeps the entries read until then, returns how many were skipped
+pub fn for_each_entry(path: &Path, kind: Kind, mut on_entry: impl FnMut(&str, Vec::<u8>)) -> IoResult::<usize> {
+    let mut budget = Budget { read: 0, skipped: 0 };
+    let file = BufReader::new(File::open(path)?);
+    match kind {
+        Kind::Zip => {
+            let mut zip = ZipArchive::new(file).map_err(invalid)?;
+            for i in 0..zip.len() {
+                if budget.exhausted() {
+                    warn!("skipping the last {n} entries of {path:?}, the archive is too big", n = zip.len() - i);
+                    budget.skipped += 1;
+                    break
+                }
+
+                let mut entry = match zip.by_index(i) {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        warn!("could not read {path:?} past its entry #{i}: {err}");
+                        budget.skipped += 1;
+                        break
+                    }
+                };
+                let Some(name) = zip_entry_name(&entry) else { continue };
+                let read = budget.read(&mut entry);
+                if !budget.keep_going(read, path, &name, &mut on_entry) { break }
+            }
+        },
+        Kind::Tar | Kind::TarGz => {
+            let mut tar = TarArchive::new(tar_reader(file, kind));
+            for entry in tar.entries()? {
+                if budget.exhausted() {
+                    warn!("skipping the rest of {path:?}, the archive is too big");
+                    budget.skipped += 1;
+                    break
+                }
+
+                let entry = entry.and_then(|entry| Ok((tar_entry_name(&entry)?, entry)));
+                let (name, mut entry) = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        warn!("could not read {path:?} any further: {err}");
+                        budget.skipped += 1;
+                        break
+                    }
+                };
+                let Some(name) = name.filter(|_| entry.header().entry_type().is_file()) else { continue };
+                let read = budget.read(&mut entry);
+                if !budget.keep_going(read, path, &name, &mut on_entry) { break }
+            }
+        }
+    }
+    Ok(budget.skipped)
+}
@@ -0,0 +1,101 @@
+// lightweight comment extraction for source code, no real lexing involved:
+// only string literals are skipped so that `"// not a comment"` stays out
+
+pub struct CommentSyntax {
+    line: &'static [&'static str],
+    block: &'static [(&'static str, &'static str)],
+    quotes: &'static [char]
+}
+
+const C_LIKE: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    quotes: &['"']
+};
+
+const C_LIKE_SINGLE_QUOTES: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    quotes: &['"', '\'', '`']
+};
+
+const HASH: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: &[],
+    quotes: &['"', '\'']
+};
+
+const PYTHON: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: &[("\"\"\"", "\"\"\""), ("'''", "'''")],
+    quotes: &['"', '\'']
+};
+
+const DASH_DASH: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: &[("--[[", "]]"), ("{-", "-}"), ("/*", "*/")],
+    quotes: &['"', '\'']
+};
+
+const SEMICOLON: CommentSyntax = CommentSyntax {
+    line: &[";"],
+    block: &[],
+    quotes: &['"']
+};
+
+// `None` for extensions that aren't code, these are indexed as a whole
+#[inline]
+pub fn comment_syntax(ext: &str) -> Option::<&'static CommentSyntax> {
+    match ext {
+          "rs"   | "c"     | "cpp"  | "h"     | "hpp" | "c++" | "java" | "go"
+        | "cs"   | "swift" | "dart" | "scss"  | "less" | "sol" | "groovy" | "zig"
+        | "v"    | "d"     | "proto" | "styl" | "hx"  | "css" => Some(&C_LIKE),
+        "js" | "ts" | "php" | "coffee" => Some(&C_LIKE_SINGLE_QUOTES),
+        "py" | "wsgi" | "sage" => Some(&PYTHON),
+        "rb" | "sh" | "zsh" | "pl" | "r" | "jl" | "nim" | "raku" | "tf" => Some(&HASH),
+        "lua" | "sql" | "hs" | "cabal" => Some(&DASH_DASH),
+        "asm" | "nasm" | "lisp" | "el" | "clj" | "cljs" => Some(&SEMICOLON),
+        _ => None
+    }
+}
+
+// text of every comment in `source`, one comment per line
+pub fn extract_comments(source: &str, syntax: &CommentSyntax) -> String {
+    let mut comments = String::new();
+    let mut i = 0;
+    while i < source.len() {
+        let rest = &source[i..];
+
+        if let Some((open, close)) = syntax.block.iter().find(|(open, _)| rest.starts_with(open)) {
+            let body = &rest[open.len()..];
+            let end = body.find(close).unwrap_or(body.len());
+            comments.push_str(&body[..end]);
+            comments.push('\n');
+            i += (open.len() + end + close.len()).min(rest.len());
+            continue
+        }
+
+        if let Some(line) = syntax.line.iter().find(|line| rest.starts_with(*line)) {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            comments.push_str(&rest[line.len()..end]);
+            comments.push('\n');
+            i += end;
+            continue
+        }
+
+        let c = unsafe { rest.chars().next().unwrap_unchecked() };
+        i += c.len_utf8();
+
+        if syntax.quotes.contains(&c) {
+            let mut escaped = false;
+            let end = source[i..].char_indices().find(|(_, ch)| {
+                let end = !escaped && *ch == c;
+                escaped = !escaped && *ch == '\\';
+                end
+            }).map_or(source.len() - i, |(j, ch)| j + ch.len_utf8());
+            i += end
+        }
+    }
+
+    comments
+}
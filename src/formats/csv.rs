@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+
+// the rows of comma (or tab) separated values, RFC 4180 style: a field in double quotes may have the delimiter,
+// line breaks and doubled quotes (`""`) in it. nothing is an error: a quote that's never closed takes the rest of
+// the input, a quote in the middle of an unquoted field is kept as it is and rows may have any number of fields
+pub struct Rows<'s> {
+    input: &'s str,
+    delimiter: char
+}
+
+#[inline]
+pub fn rows(input: &str, delimiter: char) -> Rows<'_> {
+    Rows { input, delimiter }
+}
+
+impl<'s> Iterator for Rows<'s> {
+    type Item = Vec::<Cow::<'s, str>>;
+
+    fn next(&mut self) -> Option::<Self::Item> {
+        if self.input.is_empty() { return None }
+        let mut fields = Vec::new();
+        loop {
+            let (field, rest, end_of_row) = self.field();
+            fields.push(field);
+            self.input = rest;
+            if end_of_row { return Some(fields) }
+        }
+    }
+}
+
+impl<'s> Rows<'s> {
+    // the field the input starts with, what comes after it and whether it was the last one of its row
+    fn field(&self) -> (Cow::<'s, str>, &'s str, bool) {
+        let Some(mut rest) = self.input.strip_prefix('"') else {
+            let (field, rest, end_of_row) = self.unquoted(self.input);
+            return (Cow::Borrowed(field), rest, end_of_row)
+        };
+
+        let mut field = String::new();
+        loop {
+            let Some(i) = rest.find('"') else {
+                field.push_str(rest);
+                return (Cow::Owned(field), "", true)
+            };
+            field.push_str(&rest[..i]);
+            rest = &rest[i + 1..];
+            match rest.strip_prefix('"') {
+                Some(after) => {
+                    field.push('"');
+                    rest = after
+                },
+                // whatever is between the closing quote and the delimiter is part of the field too
+                None => {
+                    let (tail, rest, end_of_row) = self.unquoted(rest);
+                    field.push_str(tail);
+                    return (Cow::Owned(field), rest, end_of_row)
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn unquoted(&self, input: &'s str) -> (&'s str, &'s str, bool) {
+        match input.find([self.delimiter, '\n']) {
+            Some(i) if input[i..].starts_with(self.delimiter) => (&input[..i], &input[i + self.delimiter.len_utf8()..], false),
+            Some(i) => (input[..i].trim_end_matches('\r'), &input[i + 1..], true),
+            None => (input.trim_end_matches('\r'), "", true)
+        }
+    }
+}
@@ -0,0 +1,285 @@
+// YAML frontmatter of markdown files, between a `---` on the first line and the next `---` or `...`. only `key: value`
+// lines and lists are understood, of which `title` and `tags` are taken. a block with anything else stays in the body
+
+const OPEN: &str = "---";
+const CLOSE: &[&str] = &["---", "..."];
+
+pub struct Frontmatter<'s> {
+    pub title: Option::<String>,
+    pub tags: Vec::<String>,
+    // everything after the closing line
+    pub body: &'s str
+}
+
+pub fn split_frontmatter(input: &str) -> Option::<Frontmatter<'_>> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut lines = input.split_inclusive('\n');
+    let first = lines.next()?;
+    if first.trim_end() != OPEN { return None }
+
+    let mut title = None;
+    let mut tags = Vec::new();
+    // the key that the indented lines and list items after it belong to
+    let mut block = None;
+    let mut at = first.len();
+    for line in lines {
+        at += line.len();
+        let line = line.trim_end();
+        if CLOSE.contains(&line) {
+            return Some(Frontmatter { title, tags, body: &input[at..] })
+        }
+
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') { continue }
+        if let Some(item) = trimmed.strip_prefix("- ").or((trimmed == "-").then_some("")) {
+            if block? == "tags" {
+                tags.extend(Some(scalar(item)?).filter(|tag| !tag.is_empty()))
+            }
+            continue
+        }
+        if line.starts_with([' ', '\t']) {
+            block?;
+            continue
+        }
+
+        let (key, value) = line.split_once(':')?;
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() { return None }
+        // a nested map or list, or a `|` or `>` block of text
+        block = (value.is_empty() || value.starts_with(['|', '>'])).then_some(key);
+        match key {
+            _ if block.is_some() => {},
+            "title" => title = Some(scalar(value)?).filter(|title| !title.is_empty()),
+            "tags" => tags.extend(list(value)?),
+            _ => {}
+        }
+    }
+    None
+}
+
+// a plain value without its comment, or a quoted one without its quotes, `None` when a quote isn't closed
+fn scalar(value: &str) -> Option::<String> {
+    let value = value.trim();
+    if let Some(rest) = value.strip_prefix('"') {
+        let end = rest.rfind('"')?;
+        return Some(rest[..end].replace("\\\"", "\"").replace("\\\\", "\\"))
+    }
+    if let Some(rest) = value.strip_prefix('\'') {
+        let end = rest.rfind('\'')?;
+        return Some(rest[..end].replace("''", "'"))
+    }
+    Some(value.split(" #").next().unwrap_or_default().trim_end().to_owned())
+}
+
+// `[a, b]` or `a, b`, empty items are dropped
+fn list(value: &str) -> Option::<Vec::<String>> {
+    let items = match value.strip_prefix('[') {
+        Some(rest) => rest.strip_suffix(']')?,
+        None => value
+    };
+    let mut list = Vec::new();
+    for item in items.split(',') {
+        let item = scalar(item)?;
+        if !item.is_empty() { list.push(item) }
+    }
+    Some(list)
+}
+
+// the text of a markdown document the way it reads once rendered: markers, link destinations, HTML tags and emphasis
+// are dropped, code is kept as it is. it isn't a full CommonMark parser, what it doesn't recognize is kept as text
+pub fn plain_text(input: &str) -> String {
+    let mut text = String::with_capacity(input.len());
+    // the character and the length of the fence of the code block the lines are in
+    let mut fence = None;
+    for line in input.lines() {
+        if let Some((c, len)) = fence {
+            if fence_of(line.trim_start()).is_some_and(|(closing, n)| closing == c && n >= len && line.trim_start().trim_start_matches(c).trim().is_empty()) {
+                fence = None
+            } else {
+                text.push_str(line)
+            }
+            text.push('\n');
+            continue
+        }
+
+        let mut rest = line.trim_start();
+        if let Some(opened) = fence_of(rest) {
+            fence = Some(opened);
+            text.push('\n');
+            continue
+        }
+        if is_rule(rest) || is_delimiter_row(rest) || is_link_definition(rest) {
+            text.push('\n');
+            continue
+        }
+
+        // block quotes and list items, nested ones too
+        loop {
+            if let Some(quoted) = rest.strip_prefix('>') {
+                rest = quoted.trim_start()
+            } else if let Some(item) = list_item(rest) {
+                rest = item
+            } else {
+                break
+            }
+        }
+        inline(heading(rest), &mut text);
+        text.push('\n')
+    }
+    text
+}
+
+// the character of a code fence that `line` opens or closes, and how many of them there are
+#[inline]
+fn fence_of(line: &str) -> Option::<(char, usize)> {
+    let c = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = line.chars().take_while(|d| *d == c).count();
+    // an info string with a backtick is a code span, not a fence
+    (len >= 3 && !(c == '`' && line[len..].contains('`'))).then_some((c, len))
+}
+
+// `***`, `---`, `___` (spaces in between are fine) and the `===` under setext headings
+#[inline]
+fn is_rule(line: &str) -> bool {
+    let mut chars = line.chars().filter(|c| !c.is_whitespace());
+    let Some(c) = chars.next().filter(|c| matches!(c, '-' | '*' | '_' | '=')) else { return false };
+    1 + chars.clone().count() >= 3 && chars.all(|d| d == c)
+}
+
+// `| --- | :-: |`
+#[inline]
+fn is_delimiter_row(line: &str) -> bool {
+    line.contains('|') && line.contains('-') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+// `[id]: destination`, footnotes (`[^1]: text`) are text
+#[inline]
+fn is_link_definition(line: &str) -> bool {
+    line.strip_prefix('[').and_then(|rest| rest.split_once("]:")).is_some_and(|(label, _)| !label.is_empty() && !label.starts_with('^'))
+}
+
+// what's after the marker of a list item, and after its task box if it has one
+fn list_item(line: &str) -> Option::<&str> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = match line.as_bytes().get(digits)? {
+        b'-' | b'*' | b'+' if digits == 0 => &line[1..],
+        b'.' | b')' if (1..=9).contains(&digits) => &line[digits + 1..],
+        _ => return None
+    };
+    if !(rest.is_empty() || rest.starts_with([' ', '\t'])) { return None }
+    let rest = rest.trim_start();
+    Some(["[ ]", "[x]", "[X]"].iter().find_map(|task| rest.strip_prefix(task)).map_or(rest, str::trim_start))
+}
+
+// the text of an ATX heading, without the `#`s in front of it and the ones that close it
+fn heading(line: &str) -> &str {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) { return line }
+    let rest = &line[level..];
+    if !(rest.is_empty() || rest.starts_with([' ', '\t'])) { return line }
+    let rest = rest.trim();
+    match rest.trim_end_matches('#') {
+        closed if closed.is_empty() => closed,
+        closed if closed.ends_with([' ', '\t']) => closed.trim_end(),
+        _ => rest
+    }
+}
+
+fn inline(line: &str, text: &mut String) {
+    let mut at = 0;
+    while let Some(c) = line[at..].chars().next() {
+        let rest = &line[at + c.len_utf8()..];
+        let next = rest.chars().next();
+        match c {
+            '\\' if next.is_some_and(|next| next.is_ascii_punctuation()) => {
+                text.push(next.unwrap_or_default());
+                at += 2;
+                continue
+            },
+            '`' => {
+                let len = line[at..].chars().take_while(|d| *d == '`').count();
+                let ticks = &line[at..at + len];
+                let code = &line[at + len..];
+                // the closing run is exactly as long as the opening one
+                let close = code.match_indices(ticks).find(|(i, _)| !code[i + len..].starts_with('`') && !code[..*i].ends_with('`'));
+                match close {
+                    Some((i, _)) => {
+                        text.push_str(&code[..i]);
+                        at += len + i + len
+                    },
+                    None => {
+                        text.push_str(ticks);
+                        at += len
+                    }
+                }
+                continue
+            },
+            '!' if next == Some('[') => {},
+            '[' => if let Some((label, after)) = link(&line[at..]) {
+                inline(label, text);
+                at += after;
+                continue
+            },
+            ']' => {},
+            '<' => if let Some(len) = rest.find('>').filter(|len| is_tag(&rest[..*len])) {
+                let inner = &rest[..len];
+                if is_autolink(inner) { text.push_str(inner) }
+                at += len + 2;
+                continue
+            } else {
+                text.push('<')
+            },
+            '*' | '~' => {},
+            '|' => text.push(' '),
+            // inside of a word it's part of it
+            '_' if text.chars().next_back().is_some_and(char::is_alphanumeric) && next.is_some_and(char::is_alphanumeric) => text.push('_'),
+            '_' => {},
+            c => text.push(c)
+        }
+        at += c.len_utf8()
+    }
+}
+
+// the text of the link (or image) `s` starts with and the length of all of it, `[text](destination "title")`,
+// `[text][id]`, `[text][]` or `[text]`, brackets and parentheses nest
+fn link(s: &str) -> Option::<(&str, usize)> {
+    let close = closing(s, '[', ']')?;
+    let label = &s[1..close];
+    let after = &s[close + 1..];
+    let len = match after.chars().next() {
+        Some('(') => closing(after, '(', ')').map_or(0, |end| end + 1),
+        Some('[') => closing(after, '[', ']').map_or(0, |end| end + 1),
+        _ => 0
+    };
+    Some((label, close + 1 + len))
+}
+
+// where the `close` that matches the `open` that `s` starts with is, escaped ones aside
+fn closing(s: &str, open: char, close: char) -> Option::<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 { return Some(i) }
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+// what's between a `<` and a `>`: `div class="x"`, `/div`, `!-- comment --`, `br/` or an autolink
+#[inline]
+fn is_tag(inner: &str) -> bool {
+    inner.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!')
+}
+
+#[inline]
+fn is_autolink(inner: &str) -> bool {
+    !inner.contains(char::is_whitespace) && (inner.contains("://") || inner.contains('@'))
+}
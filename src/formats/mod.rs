@@ -0,0 +1,6 @@
+// plain text extraction for formats that need more than reading the file
+pub mod archive;
+pub mod comments;
+pub mod csv;
+pub mod markdown;
+pub mod rtf;
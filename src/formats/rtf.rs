@@ -0,0 +1,196 @@
+// the text of an RTF document and the title of its `\info`
+pub struct Document {
+    pub title: Option::<String>,
+    pub body: String
+}
+
+// groups whose text isn't part of the document, the ones marked with `\*` are left out as well
+const DESTINATIONS: &[&str] = &[
+    "fonttbl", "colortbl", "stylesheet", "info", "pict", "shppict", "nonshppict", "object", "objdata",
+    "listtable", "listoverridetable", "rsidtbl", "revtbl", "filetbl", "generator", "xmlnstbl",
+    "themedata", "colorschememapping", "datastore", "latentstyles", "pgdsctbl", "fldinst"
+];
+
+// the characters 0x80 to 0x9F of Windows-1252, the rest of the upper half is Latin-1. the five it leaves
+// undefined are kept as the control characters they are in Latin-1
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ'
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Dest {
+    Body,
+    Title,
+    Skipped
+}
+
+#[derive(Clone, Copy)]
+struct Group {
+    dest: Dest,
+    // how many characters stand in for every `\u`, for the readers that don't know it
+    uc: usize
+}
+
+struct Reader {
+    title: String,
+    body: String,
+    group: Group,
+    stack: Vec::<Group>,
+    // characters of the stand-in of the last `\u` that are still to be skipped
+    fallback: usize
+}
+
+// the text of `input` without the control words and the groups that aren't text, hex escapes are read as
+// Windows-1252. `None` when `input` isn't RTF at all
+pub fn text(input: &str) -> Option::<Document> {
+    if !input.trim_start().starts_with("{\\rtf") { return None }
+
+    let mut reader = Reader {
+        title: String::new(),
+        body: String::with_capacity(input.len() / 2),
+        group: Group { dest: Dest::Body, uc: 1 },
+        stack: Vec::new(),
+        fallback: 0
+    };
+
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        rest = match c {
+            '{' => {
+                reader.stack.push(reader.group);
+                reader.fallback = 0;
+                &rest[1..]
+            },
+            '}' => {
+                reader.group = reader.stack.pop().unwrap_or(reader.group);
+                reader.fallback = 0;
+                &rest[1..]
+            },
+            '\\' => reader.control(&rest[1..]),
+            // line breaks of the file itself aren't ones of the text
+            '\r' | '\n' => &rest[1..],
+            _ => {
+                let end = rest.find(['{', '}', '\\', '\r', '\n']).unwrap_or(rest.len());
+                let mut run = &rest[..end];
+                while reader.fallback > 0 {
+                    let Some(c) = run.chars().next() else { break };
+                    run = &run[c.len_utf8()..];
+                    reader.fallback -= 1
+                }
+                reader.push(run);
+                &rest[end..]
+            }
+        }
+    }
+
+    let title = reader.title.trim();
+    Some(Document { title: (!title.is_empty()).then(|| title.to_owned()), body: reader.body })
+}
+
+impl Reader {
+    #[inline]
+    fn push(&mut self, s: &str) {
+        match self.group.dest {
+            Dest::Body => self.body.push_str(s),
+            Dest::Title => self.title.push_str(s),
+            Dest::Skipped => {}
+        }
+    }
+
+    // a character that's written escaped, which can be a stand-in of a `\u` too
+    #[inline]
+    fn push_char(&mut self, c: char) {
+        if self.fallback > 0 {
+            self.fallback -= 1;
+            return
+        }
+        self.push(c.encode_utf8(&mut [0; 4]))
+    }
+
+    // what comes after the `\` of a control word or a control symbol, the rest of the input after it is returned
+    fn control<'s>(&mut self, rest: &'s str) -> &'s str {
+        let Some(c) = rest.chars().next() else { return rest };
+        let after = &rest[c.len_utf8()..];
+        match c {
+            'a'..='z' | 'A'..='Z' => {
+                let (name, after) = rest.split_at(rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len()));
+                let sign = usize::from(after.starts_with('-'));
+                let len = sign + after[sign..].find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len() - sign);
+                let (param, after) = if len > sign {
+                    (after[..len].parse::<i32>().ok(), &after[len..])
+                } else {
+                    (None, after)
+                };
+                // a space right after a control word only ends it
+                self.word(name, param, after.strip_prefix(' ').unwrap_or(after))
+            },
+            '\'' => match after.get(..2).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    self.push_char(match byte {
+                        0x80..=0x9F => WINDOWS_1252[(byte - 0x80) as usize],
+                        _ => byte as char
+                    });
+                    &after[2..]
+                },
+                None => after
+            },
+            '*' => {
+                self.group.dest = Dest::Skipped;
+                after
+            },
+            '\\' | '{' | '}' => {
+                self.push_char(c);
+                after
+            },
+            '~' => {
+                self.push_char(' ');
+                after
+            },
+            '_' => {
+                self.push_char('-');
+                after
+            },
+            // an escaped line break is a `\par`
+            '\r' | '\n' => {
+                self.push("\n");
+                after
+            },
+            // optional hyphens, index entries and the like
+            _ => after
+        }
+    }
+
+    fn word<'s>(&mut self, name: &str, param: Option::<i32>, after: &'s str) -> &'s str {
+        match name {
+            "par" | "line" | "sect" | "page" | "row" => self.push("\n"),
+            "tab" | "cell" => self.push("\t"),
+            "emdash" => self.push("—"),
+            "endash" => self.push("–"),
+            "bullet" => self.push("•"),
+            "lquote" => self.push("‘"),
+            "rquote" => self.push("’"),
+            "ldblquote" => self.push("“"),
+            "rdblquote" => self.push("”"),
+            "emspace" | "enspace" | "qmspace" => self.push(" "),
+            "u" => if let Some(code) = param {
+                // code points past 32767 are written as negative numbers
+                let code = if code < 0 { code + 0x10000 } else { code };
+                let c = char::from_u32(code as u32).unwrap_or(char::REPLACEMENT_CHARACTER);
+                self.push(c.encode_utf8(&mut [0; 4]));
+                self.fallback = self.group.uc
+            },
+            "uc" => self.group.uc = param.unwrap_or(1).max(0) as usize,
+            // `<n>` bytes of binary data
+            "bin" => {
+                let mut len = (param.unwrap_or(0).max(0) as usize).min(after.len());
+                while !after.is_char_boundary(len) { len += 1 }
+                return &after[len..]
+            },
+            "title" => self.group.dest = Dest::Title,
+            _ if DESTINATIONS.contains(&name) => self.group.dest = Dest::Skipped,
+            _ => {}
+        }
+        after
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::{Component, Path};
+use std::io::{Result as IoResult, ErrorKind as IoErrorKind};
+
+// the name of the file of patterns that `Ignore::gitignore` reads from the root of a walk
+pub const GITIGNORE: &str = ".gitignore";
+
+// a pattern of `--exclude` or a line of a `.gitignore`, read the way git reads them (`*`, `?`, `[a-z]`, `**`,
+// a trailing `/`, `!`). a directory that's matched is pruned with everything in it
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    glob: String,
+    anchored: bool,
+    dir_only: bool,
+    negated: bool
+}
+
+impl Pattern {
+    // `None` for blank lines and comments
+    pub fn parse(line: &str) -> Option::<Self> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let line = match line.trim_end() {
+            // a trailing space is only kept escaped
+            trimmed if trimmed.ends_with('\\') && line.len() > trimmed.len() => &line[..trimmed.len() + 1],
+            trimmed => trimmed
+        };
+        if line.is_empty() || line.starts_with('#') { return None }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line)
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line)
+        };
+        let anchored = line.contains('/');
+        let glob = line.strip_prefix('/').unwrap_or(line);
+        if glob.is_empty() { return None }
+        Some(Self { glob: glob.to_owned(), anchored, dir_only, negated })
+    }
+
+    // `path` is relative to the root, with `/` between its components
+    #[inline]
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir { return false }
+        let subject = if self.anchored {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        glob_match(self.glob.as_bytes(), subject.as_bytes())
+    }
+}
+
+// the patterns that prune a walk, in the order they were given in, the last one that matches a path decides
+#[derive(Clone, Default, Debug)]
+pub struct Ignore {
+    patterns: Vec::<Pattern>
+}
+
+impl Ignore {
+    #[inline]
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef::<str>
+    {
+        Self { patterns: patterns.into_iter().filter_map(|pattern| Pattern::parse(pattern.as_ref())).collect() }
+    }
+
+    // the patterns of the `.gitignore` right in `root`, none when there's no such file.
+    // the ones of the directories below it aren't read
+    pub fn gitignore(root: &Path) -> IoResult::<Self> {
+        match fs::read_to_string(root.join(GITIGNORE)) {
+            Ok(patterns) => Ok(Self::new(patterns.lines())),
+            Err(err) if err.kind() == IoErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    #[inline]
+    pub fn extend(&mut self, other: Ignore) {
+        self.patterns.extend(other.patterns)
+    }
+
+    // whether the file or directory at `path`, relative to the root, is ignored,
+    // whatever the directories it's in are
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.patterns.is_empty() { return false }
+        let path = slashed(path);
+        self.patterns.iter().rev()
+            .find(|pattern| pattern.matches(&path, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    // whether `path`, relative to the root, is ignored or is in a directory that is. what `path` itself is isn't
+    // looked at, it may not exist anymore, so it's taken to be a directory when only a directory would be ignored
+    pub fn is_pruned(&self, path: &Path) -> bool {
+        path.ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.is_ignored(ancestor, true))
+    }
+}
+
+fn slashed(path: &Path) -> String {
+    path.components().filter_map(|component| match component {
+        Component::Normal(name) => Some(name.to_string_lossy()),
+        _ => None
+    }).collect::<Vec::<_>>().join("/")
+}
+
+fn glob_match(glob: &[u8], path: &[u8]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => match rest.strip_prefix(b"/") {
+            // no directories at all, or any number of whole ones
+            Some(rest) => (0..=path.len()).filter(|&i| i == 0 || path[i - 1] == b'/').any(|i| glob_match(rest, &path[i..])),
+            None => (0..=path.len()).any(|i| glob_match(rest, &path[i..]))
+        },
+        [b'*', rest @ ..] => (0..=path.len()).take_while(|&i| i == 0 || path[i - 1] != b'/').any(|i| glob_match(rest, &path[i..])),
+        [b'?', rest @ ..] => match path {
+            [first, ..] if *first != b'/' => glob_match(rest, &path[char_len(path)..]),
+            _ => false
+        },
+        [b'[', rest @ ..] => match class(rest) {
+            Some((set, negated, rest)) => match path {
+                [first, ..] if *first != b'/' => {
+                    let len = char_len(path);
+                    let c = std::str::from_utf8(&path[..len]).ok().and_then(|c| c.chars().next());
+                    c.is_some_and(|c| in_class(set, c) != negated) && glob_match(rest, &path[len..])
+                },
+                _ => false
+            },
+            // a `[` that's never closed is just a `[`
+            None => path.first() == Some(&b'[') && glob_match(rest, &path[1..])
+        },
+        [b'\\', c, rest @ ..] | [c, rest @ ..] => path.first() == Some(c) && glob_match(rest, &path[1..])
+    }
+}
+
+// the bytes of the UTF-8 character `path` starts with
+#[inline]
+fn char_len(path: &[u8]) -> usize {
+    1 + path[1..].iter().take_while(|b| (**b & 0xC0) == 0x80).count()
+}
+
+// the characters of a `[...]` whose `[` was just taken, whether it starts with `!` (or `^`),
+// and the glob after its `]`
+fn class(glob: &[u8]) -> Option::<(&str, bool, &[u8])> {
+    let (negated, glob) = match glob {
+        [b'!' | b'^', rest @ ..] => (true, rest),
+        _ => (false, glob)
+    };
+    // a `]` right at the start is one of the characters
+    let end = 1 + glob.get(1..)?.iter().position(|b| *b == b']')?;
+    let set = std::str::from_utf8(&glob[..end]).ok()?;
+    Some((set, negated, &glob[end + 1..]))
+}
+
+fn in_class(set: &str, c: char) -> bool {
+    let chars = set.chars().collect::<Vec::<_>>();
+    let mut i = 0;
+    while i < chars.len() {
+        if i + 2 < chars.len() && chars[i + 1] == '-' {
+            if (chars[i]..=chars[i + 2]).contains(&c) { return true }
+            i += 3
+        } else {
+            if chars[i] == c { return true }
+            i += 1
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_like_gitignore() {
+        let matches = |pattern: &str, path: &str, is_dir: bool| Pattern::parse(pattern).unwrap().matches(path, is_dir);
+
+        assert!(matches("*.log", "build.log", false));
+        assert!(matches("*.log", "a/b/build.log", false));
+        assert!(!matches("*.log", "build.log.txt", false));
+
+        // a trailing `/` is for directories only
+        assert!(matches(".git/", ".git", true));
+        assert!(matches(".git/", "vendor/.git", true));
+        assert!(!matches(".git/", ".git", false));
+
+        // anchored by a `/` in them
+        assert!(matches("/target", "target", true));
+        assert!(!matches("/target", "crates/target", true));
+        assert!(matches("*/target/*", "crates/target/debug", true));
+        assert!(!matches("*/target/*", "a/b/target/debug", true));
+        assert!(matches("docs/*.md", "docs/intro.md", false));
+        assert!(!matches("docs/*.md", "docs/guide/intro.md", false));
+
+        assert!(matches("**/target", "a/b/target", true));
+        assert!(matches("**/target", "target", true));
+        assert!(!matches("**/target", "a/btarget", true));
+        assert!(matches("a/**/b", "a/b", true));
+        assert!(matches("a/**/b", "a/x/y/b", true));
+        assert!(matches("logs/**", "logs/2024/01.txt", false));
+
+        assert!(matches("file?.txt", "file1.txt", false));
+        assert!(matches("file?.txt", "fileé.txt", false));
+        assert!(!matches("file?.txt", "file10.txt", false));
+        assert!(matches("[a-c]*.rs", "bin.rs", false));
+        assert!(!matches("[!a-c]*.rs", "bin.rs", false));
+        assert!(matches("\\#notes", "#notes", false));
+
+        assert!(Pattern::parse("# a comment").is_none());
+        assert!(Pattern::parse("   ").is_none());
+    }
+
+    #[test]
+    fn the_last_matching_pattern_decides() {
+        let ignore = Ignore::new(["*.log", "!keep.log", "build/"]);
+        assert!(ignore.is_ignored(Path::new("a/debug.log"), false));
+        assert!(!ignore.is_ignored(Path::new("a/keep.log"), false));
+        assert!(!ignore.is_ignored(Path::new("build"), false));
+        assert!(ignore.is_pruned(Path::new("build/out/keep.log")));
+        assert!(!ignore.is_pruned(Path::new("src/main.rs")));
+    }
+}
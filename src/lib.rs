@@ -0,0 +1,15 @@
+#[macro_use]
+pub mod core;
+pub mod term;
+pub mod query;
+pub mod server;
+pub mod watch;
+pub mod checkpoint;
+pub mod dir_rec;
+pub mod ignore;
+pub mod formats;
+#[cfg(feature = "ocr")]
+pub mod ocr;
+mod snowball;
+#[cfg(test)]
+mod testing;
@@ -1,23 +1,32 @@
-use std::env;
+use std::{env, io, iter};
+use std::time::Duration;
 #[cfg(feature = "dbg")]
 use std::time::Instant;
-use std::path::PathBuf;
-use std::thread::spawn;
+use std::path::{Path, PathBuf};
+use std::thread::{self, Scope, spawn};
 use std::process::ExitCode;
-use std::sync::mpsc::channel;
-
-#[macro_use]
-mod core;
-use core::*;
-mod term;
-use term::*;
-mod server;
-use server::*;
-mod dir_rec;
-mod snowball;
+use std::sync::mpsc::{Sender, channel};
+
+use log::{debug, error, info, warn};
+use rayon::ThreadPoolBuilder;
+use env_logger::Env;
+
+use search::core::*;
+use search::term::*;
+use search::query::*;
+use search::server::*;
+use search::watch::{Indexing, Watch};
+use search::checkpoint::Checkpoint;
+use search::ignore::Ignore;
+
+mod args;
+use args::*;
 
 const ADDR: &str = "localhost";
-const DEFAULT_PORT: &str = "6969";
+// what gets logged when `RUST_LOG` isn't set, lopdf warns about every malformed object it can recover from
+const DEFAULT_LOG_FILTER: &str = "info,lopdf=error";
+// how many of the documents parsed with `--while-indexing` are indexed at a time, searches wait for them
+const INDEX_BATCH: usize = 256;
 
 macro_rules! msg_print {
     ($msgs: expr, $($tt: tt) *) => {{
@@ -27,35 +36,382 @@ macro_rules! msg_print {
 }
 
 fn main() -> ExitCode {
-    let args = env::args().collect::<Vec::<_>>();
-    if args.len() < 2 {
-        eprintln!("usage: {program} <directory to search in> [port to serve at]", program = args[0]);
-        return ExitCode::FAILURE
+    let args = match Args::parse() {
+        Ok(args) => args,
+        // the usage and mistakes in the arguments are for whoever is typing them, not for the logs
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE
+        }
+    };
+
+    env_logger::Builder::from_env(Env::default().default_filter_or(DEFAULT_LOG_FILTER)).init();
+
+    if args.threads == 0 {
+        return run(args)
+    }
+
+    // everything that `run` does in parallel, including the server's searches, happens inside of this pool
+    match ThreadPoolBuilder::new().num_threads(args.threads).build() {
+        Ok(pool) => pool.install(|| run(args)),
+        Err(err) => {
+            error!("could not build a thread pool of {n} threads: {err}", n = args.threads);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// `--index`: what was saved at `path`, `None` when nothing was yet or it can't be loaded, every file is parsed then
+fn read_saved(path: Option::<&str>, tokenizer: &TokenizerConfig, parse_config: &ParseConfig) -> Option::<Saved> {
+    let path = path?;
+    match Saved::read(path, tokenizer, parse_config) {
+        Ok(saved) => Some(saved),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => {
+            warn!("not loading the index at {path}, every file is parsed again: {err}");
+            None
+        }
+    }
+}
+
+// where the documents are sent as they're parsed and what's told how many files the walk found
+type Sending<'s> = (&'s Sender::<(PathBuf, Text)>, &'s (dyn Fn(usize) + Sync));
+
+// with `send` the documents are sent there as they're parsed, and the contents returned are empty
+fn get_contents(roots: &[&String], stdin: bool, walk_config: &WalkConfig, parse_config: &ParseConfig, checkpoint: Option::<&str>, saved: Option::<&Saved>, send: Option::<Sending>) -> Option::<(Contents, Report)> {
+    let mut checkpoint = match checkpoint.map(|path| (path, Checkpoint::open(Path::new(path), parse_config))) {
+        None => None,
+        Some((_, Ok(checkpoint))) => Some(checkpoint),
+        Some((path, Err(err))) => {
+            error!("could not open the checkpoint at {path}: {err}");
+            return None
+        }
+    };
+
+    let contents = match (send, &mut checkpoint, saved) {
+        (Some((tx, walked)), checkpoint, saved) => dir_send_contents(roots, walk_config, parse_config, checkpoint.as_mut(), saved, tx, walked).map(|report| (Contents::new(), report)),
+        (None, checkpoint, Some(saved)) => dir_get_contents_since(roots, walk_config, parse_config, checkpoint.as_mut(), saved),
+        (None, Some(checkpoint), None) => dir_get_contents_resumed(roots, walk_config, parse_config, checkpoint),
+        (None, None, None) => dir_get_contents(roots, walk_config, parse_config)
+    };
+    match contents {
+        Ok((mut contents, report)) => {
+            if !report.unchanged.is_empty() {
+                info!("took {n} unchanged files from the index", n = report.unchanged.len())
+            }
+            if report.resumed > 0 {
+                info!("took {n} unchanged files from the checkpoint", n = report.resumed)
+            }
+            if let Some(Err(err)) = checkpoint.map(Checkpoint::finish) {
+                warn!("could not write the checkpoint: {err}")
+            }
+            if !report.unreadable_dirs.is_empty() {
+                warn!("{n} directories could not be read, the index is incomplete:", n = report.unreadable_dirs.len());
+                report.unreadable_dirs.iter().for_each(|err| warn!("    {err}"));
+            }
+            if !report.partial_files.is_empty() {
+                warn!("{n} files were indexed without some of their parts (PDF pages, EPUB chapters, archive entries):", n = report.partial_files.len());
+                report.partial_files.iter().for_each(|(path, failed)| warn!("    {path:?}: {failed} could not be extracted"));
+            }
+            if !report.skipped.is_empty() {
+                let reasons = report.skipped_by_reason().iter().map(|(reason, n)| format!("{n} {reason}")).collect::<Vec::<_>>();
+                info!("skipped {n} files: {reasons}", n = report.skipped.len(), reasons = reasons.join(", "));
+                report.skipped.iter().for_each(|(path, err)| debug!("    {path:?}: {err}"));
+            }
+            if stdin {
+                match parse_reader(io::stdin().lock()) {
+                    Ok(text) => match send {
+                        Some((tx, _)) => _ = tx.send((PathBuf::from(STDIN_PATH), text)),
+                        None => contents.push((PathBuf::from(STDIN_PATH), text))
+                    },
+                    Err(err) => {
+                        error!("could not read stdin: {err}");
+                        return None
+                    }
+                }
+            }
+            Some((contents, report))
+        },
+        Err(err) => {
+            error!("{err}");
+            None
+        }
+    }
+}
+
+// index `contents` along with the documents of `saved` that are still up to date, and save them all
+// to `--index` when it's given
+fn add_contents<'a>(model: &mut Model<'a>, contents: &'a Contents, saved: Option::<Saved>, report: Report, parse_config: &ParseConfig, index: Option::<&str>) {
+    if let Some(saved) = saved {
+        model.load_from(saved, &report.unchanged)
+    }
+    model.stamps.extend(report.stamps);
+    model.add_contents(contents);
+    save(model, parse_config, index)
+}
+
+// `--index`: save `model` to `index` when it's given
+fn save(model: &Model, parse_config: &ParseConfig, index: Option::<&str>) {
+    let Some(path) = index else { return };
+    if let Err(err) = model.save_to(path, parse_config) {
+        warn!("could not save the index to {path}: {err}")
+    }
+}
+
+fn server<'a>(model: impl Into::<SharedModel<'a>>, curr_dir: &'a PathBuf, canonical_roots: Vec::<PathBuf>, roots: &[&String], parse_config: &ParseConfig, args: &Args) -> Option::<Server<'a>> {
+    let server = Server::new(model, curr_dir, canonical_roots)
+        .with_limit(args.limit)
+        .with_path_style(args.path_style)
+        .with_parse_config(parse_config.clone())
+        .with_indexed_roots(roots.iter().map(PathBuf::from).collect())
+        .with_headers(args.headers.iter().cloned())
+        .with_timeout((args.search_timeout > 0).then(|| Duration::from_millis(args.search_timeout)));
+    let Some(assets) = &args.assets else { return Some(server) };
+    if !Path::new(assets).is_dir() {
+        error!("`{assets}` is not a valid directory");
+        return None
+    }
+    Some(server.with_assets(PathBuf::from(assets)))
+}
+
+// serve until the server fails, with `--watch` the model is kept up to date from another thread of `scope`,
+// which stops once the server does and lets the scope end
+fn serve<'scope, 'a: 'scope>(scope: &'scope Scope<'scope, '_>, server: &mut Server<'a>, roots: &[&String], indexing: Indexing<'scope>, args: &Args) -> ExitCode {
+    let addr = if args.port.starts_with(UNIX_PREFIX) {
+        args.port.clone()
+    } else {
+        format!("{ADDR}:{port}", port = args.port)
+    };
+
+    let watch = match args.watch.then(|| Watch::applying(scope, server.model(), roots, indexing)).transpose() {
+        Ok(watch) => watch,
+        Err(err) => {
+            error!("could not watch the directories: {err}");
+            return ExitCode::FAILURE
+        }
+    };
+    let served = server.serve(addr.as_str());
+    drop(watch);
+    match served {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{err}");
+            ExitCode::FAILURE
+        }
     }
+}
 
-    let ref dir_path = args[1];
-    let dir_path_buf = Into::<PathBuf>::into(dir_path);
+// what the queries from the command line are searched with, their text aside
+#[inline]
+fn options(args: &Args) -> Query<'_> {
+    Query::from("").with_exact(args.exact).with_extensions(&args.extensions)
+}
 
-    let ref port = if args.len() > 2 {
-        let port = args[2].as_str();
-        if port.len() != 4 || port.parse::<u16>().is_err() {
-            eprintln!("`{port}` is not a valid port to serve at");
+// `--watch`: changes are applied on this thread, in between the queries that `read_queries` sends in `--repl` mode
+fn watch(model: &mut Model, roots: &[&String], printer: &Printer, indexing: Indexing, args: &Args) -> ExitCode {
+    let (tx, rx) = channel();
+    let changes = tx.clone();
+    let _watch = match Watch::new(roots, indexing.walk, move |path| _ = changes.send(Input::Changed(path))) {
+        Ok(watch) => watch,
+        Err(err) => {
+            error!("could not watch the directories: {err}");
             return ExitCode::FAILURE
-        } port
+        }
+    };
+
+    if args.repl {
+        read_queries(tx)
     } else {
-        DEFAULT_PORT
+        drop(tx)
+    }
+
+    match live(model, printer, indexing, options(args), rx, args.query.clone(), args.repl) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> ExitCode {
+    let mut roots = Vec::<&String>::with_capacity(args.dir_paths.len());
+    let mut canonical_roots = Vec::<PathBuf>::with_capacity(args.dir_paths.len());
+    // `-` reads a single document from stdin, which is searched next to the directories and listed as `<stdin>`.
+    // on its own it can only be the one hit and every word weighs `MIN_IDF`, so it's listed with a tiny score whenever
+    // it contains a query word and not at all when it doesn't
+    let stdin = args.dir_paths.iter().any(|dir_path| dir_path == STDIN_ROOT);
+    for dir_path in args.dir_paths.iter().filter(|dir_path| *dir_path != STDIN_ROOT) {
+        let dir_path_buf = Path::new(dir_path);
+        if !(dir_path_buf.exists() && dir_path_buf.is_dir()) {
+            error!("`{dir_path}` is not a valid directory");
+            return ExitCode::FAILURE
+        }
+
+        // the same directory given twice would be walked twice under different-looking paths
+        let canonical = dir_path_buf.canonicalize().unwrap_or_else(|_| dir_path_buf.to_owned());
+        if !canonical_roots.contains(&canonical) {
+            canonical_roots.push(canonical);
+            roots.push(dir_path)
+        }
+    }
+
+    let printer = Printer {
+        limit: args.limit,
+        json: args.json,
+        ndjson: args.ndjson,
+        color: Printer::use_color(args.no_color)
     };
 
-    if !(dir_path_buf.exists() && dir_path_buf.is_dir()) {
-        eprintln!("`{dir_path}` is not a valid directory");
-        return ExitCode::FAILURE
+    let mut excluded_dirs = args.exclude_dirs.clone();
+    if args.exclude_common {
+        excluded_dirs.extend(COMMON_EXCLUDED_DIRS.iter().map(|name| name.to_string()))
+    }
+    let walk_config = WalkConfig {
+        strict: args.strict || WalkConfig::default().strict,
+        excluded_dirs,
+        skip_symlinks: args.no_follow_symlinks,
+        max_depth: args.max_depth,
+        exclude: Ignore::new(&args.exclude),
+        gitignore: args.gitignore
+    };
+
+    let default_filter = PdfFilter::default();
+    let parse_config = ParseConfig {
+        comments_only: args.comments_only,
+        csv_headers: args.csv_headers,
+        pdf_filter: PdfFilter {
+            ignore: args.pdf_ignore.clone().unwrap_or(default_filter.ignore),
+            strip_keys: args.pdf_strip_keys.clone().unwrap_or(default_filter.strip_keys)
+        },
+        pdf_timeout: (args.pdf_timeout > 0).then(|| Duration::from_secs(args.pdf_timeout)),
+        max_text_len: (args.max_text_len > 0).then_some(args.max_text_len)
+    };
+
+    // tokenizers are copied around, the list they share lives as long as the program
+    let stop_words = match args.stop_words.as_deref() {
+        None => None,
+        Some("english") => Some(StopWords::english()),
+        Some(path) => match StopWords::from_file(Path::new(path)) {
+            Ok(stop_words) => Some(stop_words),
+            Err(err) => {
+                error!("could not read the stop words of `{path}`: {err}");
+                return ExitCode::FAILURE
+            }
+        }
+    }.map(|stop_words| &*Box::leak(Box::new(stop_words)));
+
+    let tokenizer = TokenizerConfig {
+        case_sensitive: args.case_sensitive,
+        split_compounds: args.split_compounds,
+        keep_originals: args.keep_originals,
+        max_token_len: args.max_token_len,
+        path_weight: args.path_weight,
+        analyzer: args.analyzer,
+        stop_words
+    };
+
+    let saved = read_saved(args.index.as_deref(), &tokenizer, &parse_config);
+
+    // `--while-indexing`: the server starts right away and searches whatever is indexed by then. the files are
+    // parsed on one thread of the scope and sent to another one that indexes them as they come
+    if let Some(while_indexing) = args.while_indexing {
+        let Ok(curr_dir) = env::current_dir() else {
+            error!("could not get current directory");
+            return ExitCode::FAILURE
+        };
+
+        let mut msgs = String::new();
+        msg_print!(msgs, "indexing while serving..");
+
+        // the milestones are drawn and make the progress that the server reports
+        let (tx, rx) = channel();
+        let (draw_tx, draw_rx) = channel();
+        let term_thread = spawn(move || draw_percentage(draw_rx, msgs));
+        let progress = IndexingProgress::default();
+        let reported = progress.clone();
+        spawn(move || for signal in rx {
+            match signal {
+                Signal::Progress(percentage) => reported.set(percentage),
+                Signal::Done => reported.finish()
+            }
+            _ = draw_tx.send(signal)
+        });
+
+        // how many documents there are is only known once the walk is done, the progress is of those
+        let model = Model::new(tx, 0).with_tokenizer(tokenizer).with_operator(args.operator).with_numbers(args.numbers).with_min_df(args.min_df).with_milestones(args.progress);
+        let Some(server) = server(model, &curr_dir, canonical_roots, &roots, &parse_config, &args) else {
+            return ExitCode::FAILURE
+        };
+        let mut server = server.with_indexing(progress, while_indexing);
+        let model = server.model();
+
+        let (docs_tx, docs_rx) = channel();
+        let served = thread::scope(|scope| {
+            scope.spawn(|| {
+                let walked = |count| model.write().expect(count + stdin as usize);
+                // what couldn't be walked was logged, the server goes on with what was indexed
+                if let Some((_, report)) = get_contents(&roots, stdin, &walk_config, &parse_config, args.checkpoint.as_deref(), saved.as_ref(), Some((&docs_tx, &walked))) {
+                    let mut model = model.write();
+                    if let Some(saved) = saved {
+                        model.load_from(saved, &report.unchanged)
+                    }
+                    model.stamps.extend(report.stamps)
+                }
+                drop(docs_tx)
+            });
+            let (model, config, index) = (&model, &parse_config, args.index.as_deref());
+            scope.spawn(move || {
+                while let Ok(doc) = docs_rx.recv() {
+                    let mut model = model.write();
+                    for (file_path, text) in iter::once(doc).chain(docs_rx.try_iter().take(INDEX_BATCH - 1)) {
+                        model.add_text(file_path, text)
+                    }
+                }
+                let mut model = model.write();
+                model.finish();
+                save(&model, config, index)
+            });
+            serve(scope, &mut server, &roots, Indexing {walk: &walk_config, parse: &parse_config}, &args)
+        });
+        term_thread.join().unwrap();
+        return served
+    }
+
+    if let Some(query) = &args.query {
+        let Some((contents, report)) = get_contents(&roots, stdin, &walk_config, &parse_config, args.checkpoint.as_deref(), saved.as_ref(), None) else {
+            return ExitCode::FAILURE
+        };
+
+        // nobody draws the progress in query mode, `rx` only has to outlive the indexing
+        let (tx, rx) = channel();
+        let mut model = Model::new(tx, contents.len()).with_tokenizer(tokenizer).with_operator(args.operator).with_numbers(args.numbers).with_min_df(args.min_df).with_milestones(args.progress);
+        add_contents(&mut model, &contents, saved, report, &parse_config, args.index.as_deref());
+        drop(rx);
+
+        if args.watch {
+            return watch(&mut model, &roots, &printer, Indexing {walk: &walk_config, parse: &parse_config}, &args)
+        }
+
+        return match model.search_explained(Query { text: query, ..options(&args) }) {
+            Ok(explained) => {
+                printer.print_explained(&explained, query);
+                ExitCode::SUCCESS
+            },
+            Err(err) => {
+                error!("{err}");
+                ExitCode::FAILURE
+            }
+        }
     }
 
     let mut msgs = String::new();
 
     msg_print!(msgs, "reading files..\n");
 
-    let contents = dir_get_contents(dir_path);
+    let Some((contents, report)) = get_contents(&roots, stdin, &walk_config, &parse_config, args.checkpoint.as_deref(), saved.as_ref(), None) else {
+        return ExitCode::FAILURE
+    };
 
     msg_print!(msgs, "starting indexing {count} files..", count = contents.len());
 
@@ -65,34 +421,39 @@ fn main() -> ExitCode {
     #[cfg(feature = "dbg")]
     let start = Instant::now();
 
-    let mut model = Model::new(tx, contents.len());
-    model.add_contents(&contents);
+    let mut model = Model::new(tx, contents.len()).with_tokenizer(tokenizer).with_operator(args.operator).with_numbers(args.numbers).with_min_df(args.min_df).with_milestones(args.progress);
+    add_contents(&mut model, &contents, saved, report, &parse_config, args.index.as_deref());
 
     #[cfg(feature = "dbg")] {
         let end = start.elapsed().as_millis();
-        println!("indexing took: {end} millis");
+        info!("indexing took: {end} millis");
+    }
+
+    if args.watch && args.repl {
+        term_thread.join().unwrap();
+        return watch(&mut model, &roots, &printer, Indexing {walk: &walk_config, parse: &parse_config}, &args)
+    }
+
+    if args.repl {
+        term_thread.join().unwrap();
+        if let Err(err) = repl(&model, &printer, options(&args)) {
+            error!("{err}");
+            return ExitCode::FAILURE
+        }
+        return ExitCode::SUCCESS
     }
 
     let Ok(curr_dir) = env::current_dir() else {
-        eprintln!("could not get current directory");
+        error!("could not get current directory");
         return ExitCode::FAILURE
     };
-
-    let mut server = Server::new(model, &curr_dir);
-
-    let addr = format!("{ADDR}:{port}");
-    if let Err(err) = server.serve(addr.as_str()) {
-        eprintln!("{err}");
+    let Some(mut server) = server(model, &curr_dir, canonical_roots, &roots, &parse_config, &args) else {
         return ExitCode::FAILURE
-    }
-
-    term_thread.join().unwrap();
+    };
 
-    ExitCode::SUCCESS
+    let served = thread::scope(|scope| serve(scope, &mut server, &roots, Indexing {walk: &walk_config, parse: &parse_config}, &args));
+    if served == ExitCode::SUCCESS {
+        term_thread.join().unwrap()
+    }
+    served
 }
-
-/* TODO:
-    Parallelize indexing process and start the server instantly,
-    then if user sends a query request the server should respond with
-    a data we have indexed right now, even though the indexing process is not finished yet.
-*/
@@ -0,0 +1,74 @@
+use std::thread;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::io::{Write, Result as IoResult, Error as IoError};
+
+use log::warn;
+
+// the pages of a PDF are rendered by poppler's `pdftoppm` and read by `tesseract`, both have to be on the `PATH`
+pub const RASTERIZER: &str = "pdftoppm";
+pub const ENGINE: &str = "tesseract";
+
+// the resolution pages are rendered at, the one tesseract reads best
+pub const DPI: u32 = 300;
+
+// a page whose text layer has fewer letters and digits than this is taken to be a scan
+pub const MIN_CHARS: usize = 16;
+
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+#[inline]
+pub fn is_near_empty(text: &str) -> bool {
+    text.chars().filter(|c| c.is_alphanumeric()).take(MIN_CHARS).count() < MIN_CHARS
+}
+
+// `extracted` when there's text in it, what OCR reads off page `npage` (counted from 1) of the PDF at `path`
+// otherwise. when that fails the page keeps `extracted`, which is only warned about once, it's likely to be
+// every page failing for the same reason, a program that isn't installed
+pub fn recover(path: &Path, npage: u32, extracted: String) -> String {
+    if !is_near_empty(&extracted) { return extracted }
+    match page(path, npage) {
+        Ok(text) => text,
+        Err(err) => {
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                warn!("could not OCR page {npage} of {path:?}, scanned pages are indexed without their text: {err}")
+            }
+            extracted
+        }
+    }
+}
+
+// the text tesseract reads off page `npage` of the PDF at `path`
+pub fn page(path: &Path, npage: u32) -> IoResult::<String> {
+    let (npage, dpi) = (npage.to_string(), DPI.to_string());
+    // without the root of the names of the images to write, the only page there is goes to stdout
+    let image = run(Command::new(RASTERIZER)
+        .args(["-r", &dpi, "-f", &npage, "-l", &npage, "-gray", "-png", "-singlefile"])
+        .arg(path), None)?;
+    let text = run(Command::new(ENGINE).args(["stdin", "stdout"]), Some(image))?;
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
+
+// the stdout of `command`, with `input` on its stdin
+fn run(command: &mut Command, input: Option::<Vec::<u8>>) -> IoResult::<Vec::<u8>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| IoError::new(err.kind(), format!("could not run {program}: {err}")))?;
+
+    // written from a thread of its own, a child that fills up its stdout before it's done reading would wait forever
+    let writer = input.zip(child.stdin.take()).map(|(input, mut stdin)| thread::spawn(move || stdin.write_all(&input)));
+    let output = child.wait_with_output()?;
+    // a child that exits without reading all of it breaks the pipe, which its status says more about
+    if let Some(writer) = writer { _ = writer.join() }
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(IoError::other(format!("{program} failed ({status}): {stderr}", status = output.status, stderr = stderr.trim())))
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,429 @@
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::core::Analyzer;
+
+// parts of a document a query term can be scoped to with a `field:` prefix, unscoped terms match all of them
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Field {
+    Title,
+    Tags,
+    Body,
+    Path
+}
+
+impl Field {
+    const NAMES: &[(&str, Field)] = &[("title", Field::Title), ("tags", Field::Tags), ("body", Field::Body), ("path", Field::Path)];
+
+    #[inline]
+    fn from_name(name: &str) -> Option::<Field> {
+        Self::NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, field)| *field)
+    }
+}
+
+// what words next to each other without an operator between them are combined with
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Operator {
+    And,
+    // every document with any of the words, the more of them the better
+    #[default]
+    Or
+}
+
+impl Operator {
+    #[inline]
+    pub fn from_name(name: &str) -> Option::<Operator> {
+        match name {
+            _ if name.eq_ignore_ascii_case("and") => Some(Operator::And),
+            _ if name.eq_ignore_ascii_case("or") => Some(Operator::Or),
+            _ => None
+        }
+    }
+}
+
+// when a search has to stop scoring and make do with what it has, checked every `CHECK_EVERY` postings.
+// the first check that sees the time is up flags it for all of the threads the search is split over
+#[derive(Debug)]
+pub struct Deadline {
+    at: Instant,
+    checks: AtomicU32,
+    passed: AtomicBool
+}
+
+impl Deadline {
+    // looking at the clock for every posting would cost more than scoring some of them
+    const CHECK_EVERY: u32 = 256;
+
+    #[inline]
+    pub fn after(timeout: Duration) -> Self {
+        Deadline { at: Instant::now() + timeout, checks: AtomicU32::new(0), passed: AtomicBool::new(false) }
+    }
+
+    // whether the search should stop, which is only true once the time is up
+    #[inline]
+    pub fn check(&self) -> bool {
+        if self.passed.load(Ordering::Relaxed) { return true }
+        if !self.checks.fetch_add(1, Ordering::Relaxed).is_multiple_of(Self::CHECK_EVERY) { return false }
+        let passed = Instant::now() >= self.at;
+        if passed { self.passed.store(true, Ordering::Relaxed) }
+        passed
+    }
+
+    // whether a search stopped early because of this deadline, so its results are partial
+    #[inline]
+    pub fn passed(&self) -> bool {
+        self.passed.load(Ordering::Relaxed)
+    }
+}
+
+// a query along with how it should be matched, plain strings are queries with the defaults
+#[derive(Clone, Copy, Debug)]
+pub struct Query<'q> {
+    pub text: &'q str,
+    // match the original (lowercased, unstemmed) words instead of their stems,
+    // which needs a model that kept them, see `TokenizerConfig::keep_originals`
+    pub exact: bool,
+    // the operator between bare words, `None` leaves it to the model, see `Model::with_operator`
+    pub operator: Option::<Operator>,
+    // scoring stops there with the best documents found so far, `None` scores everything
+    pub deadline: Option::<&'q Deadline>,
+    // what the words are analyzed with, the model's analyzer when `None`. the documents analyzed the same way
+    // are the ones that are found, see `Analyzer`
+    pub analyzer: Option::<Analyzer>,
+    // only the documents whose paths start with this one, a whole component at a time, are scored. it's a prefix
+    // of the paths as they were indexed, `docs/2024` has `docs/2024/q1.txt` but not `docs/2024-old/q1.txt`
+    pub scope: Option::<&'q Path>,
+    // only the documents with one of these extensions are scored, given with or without the dot and in any case,
+    // all of them when it's empty. an entry of an archive has the extension of its own name
+    pub extensions: &'q [String]
+}
+
+impl<'q> Query<'q> {
+    #[inline]
+    pub fn with_exact(mut self, exact: bool) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    #[inline]
+    pub fn with_operator(mut self, operator: Operator) -> Self {
+        self.operator = Some(operator);
+        self
+    }
+
+    #[inline]
+    pub fn with_analyzer(mut self, analyzer: Analyzer) -> Self {
+        self.analyzer = Some(analyzer);
+        self
+    }
+
+    #[inline]
+    pub fn with_deadline(mut self, deadline: &'q Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    #[inline]
+    pub fn with_scope(mut self, scope: &'q Path) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    #[inline]
+    pub fn with_extensions(mut self, extensions: &'q [String]) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    // whether the document at `path` may be found at all, which is whether it's in the `scope` and of one of the
+    // `extensions`, decided before it's scored
+    #[inline]
+    pub fn admits(&self, path: &Path) -> bool {
+        self.scope.is_none_or(|scope| path.starts_with(scope)) && (self.extensions.is_empty() || {
+            let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+            self.extensions.iter().any(|extension| extension.trim_start_matches('.').eq_ignore_ascii_case(ext))
+        })
+    }
+
+    #[inline]
+    pub fn out_of_time(&self) -> bool {
+        self.deadline.is_some_and(Deadline::check)
+    }
+}
+
+impl<'q> From<&'q str> for Query<'q> {
+    #[inline]
+    fn from(text: &'q str) -> Self {
+        Query { text, exact: false, operator: None, deadline: None, analyzer: None, scope: None, extensions: &[] }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QueryError {
+    UnknownField(String),
+    OriginalsNotKept,
+    UnbalancedParens,
+    EmptyGroup,
+    UnclosedQuote,
+    // an operator without something to combine on one of its sides
+    MissingOperand(&'static str)
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::UnknownField(name) => write!(f, "unknown field `{name}`, expected one of `title`, `tags`, `body` or `path`"),
+            QueryError::OriginalsNotKept => write!(f, "exact matching needs the original words, which this index didn't keep"),
+            QueryError::UnbalancedParens => write!(f, "unbalanced parentheses"),
+            QueryError::EmptyGroup => write!(f, "there's nothing between the parentheses"),
+            QueryError::UnclosedQuote => write!(f, "a phrase is missing its closing quote"),
+            QueryError::MissingOperand(op) => write!(f, "`{op}` needs a word, a phrase or a group on both sides")
+        }
+    }
+}
+
+// a parsed query: words, `"quoted phrases"` and `(groups)` combined with `AND`, which binds tighter, and `OR`.
+// words without an operator between them are combined with the implicit `Operator`
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expr<'q> {
+    // a word as it was written, which may be scoped to a field and still split into several terms
+    Word(&'q str),
+    // the text between the quotes, whose words must follow each other in the body of a document
+    Phrase(&'q str),
+    And(Vec::<Expr<'q>>),
+    Or(Vec::<Expr<'q>>)
+}
+
+impl<'q> Expr<'q> {
+    // the words and phrases of the expression, in query order
+    pub fn leaves(&self) -> Vec::<&Expr<'q>> {
+        match self {
+            Expr::Word(..) | Expr::Phrase(..) => vec![self],
+            Expr::And(operands) | Expr::Or(operands) => operands.iter().flat_map(Expr::leaves).collect()
+        }
+    }
+
+    // words combined with `OR` only, which can be searched straight from the posting lists
+    pub fn is_plain(&self) -> bool {
+        match self {
+            Expr::Word(..) => true,
+            Expr::Phrase(..) | Expr::And(..) => false,
+            Expr::Or(operands) => operands.iter().all(Expr::is_plain)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Token<'q> {
+    Open,
+    Close,
+    And,
+    Or,
+    Word(&'q str),
+    Phrase(&'q str)
+}
+
+fn lex(text: &str) -> Result::<Vec::<Token<'_>>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '(' => { tokens.push(Token::Open); 1 },
+            ')' => { tokens.push(Token::Close); 1 },
+            '"' => {
+                let end = rest[1..].find('"').ok_or(QueryError::UnclosedQuote)?;
+                tokens.push(Token::Phrase(&rest[1..1 + end]));
+                end + 2
+            },
+            _ => {
+                let end = rest.find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"')).unwrap_or(rest.len());
+                tokens.push(match &rest[..end] {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    word => Token::Word(word)
+                });
+                end
+            }
+        };
+        rest = rest[len..].trim_start()
+    }
+    Ok(tokens)
+}
+
+struct Parser<'q> {
+    tokens: Vec::<Token<'q>>,
+    at: usize,
+    implicit: Operator
+}
+
+impl<'q> Parser<'q> {
+    #[inline]
+    fn peek(&self) -> Option::<Token<'q>> {
+        self.tokens.get(self.at).copied()
+    }
+
+    #[inline]
+    fn next(&mut self) -> Option::<Token<'q>> {
+        let token = self.peek();
+        self.at += 1;
+        token
+    }
+
+    // whether the next token starts an operand without an operator before it
+    #[inline]
+    fn implicit(&self, operator: Operator) -> bool {
+        self.implicit == operator && matches!(self.peek(), Some(Token::Word(..) | Token::Phrase(..) | Token::Open))
+    }
+
+    fn or(&mut self) -> Result::<Expr<'q>, QueryError> {
+        let mut operands = vec![self.and(None)?];
+        loop {
+            match self.peek() {
+                Some(Token::Or) => {
+                    self.at += 1;
+                    operands.push(self.and(Some("OR"))?)
+                },
+                _ if self.implicit(Operator::Or) => operands.push(self.and(None)?),
+                _ => break
+            }
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::Or(operands) })
+    }
+
+    // `op` is the operator waiting for this operand, if any, for the error when there's none
+    fn and(&mut self, op: Option::<&'static str>) -> Result::<Expr<'q>, QueryError> {
+        let mut operands = vec![self.atom(op)?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.at += 1;
+                    operands.push(self.atom(Some("AND"))?)
+                },
+                _ if self.implicit(Operator::And) => operands.push(self.atom(None)?),
+                _ => break
+            }
+        }
+        Ok(if operands.len() == 1 { operands.remove(0) } else { Expr::And(operands) })
+    }
+
+    fn atom(&mut self, op: Option::<&'static str>) -> Result::<Expr<'q>, QueryError> {
+        let after_open = self.at > 0 && self.tokens[self.at - 1] == Token::Open;
+        match (self.next(), op) {
+            (Some(Token::Word(word)), _) => Ok(Expr::Word(word)),
+            (Some(Token::Phrase(phrase)), _) => Ok(Expr::Phrase(phrase)),
+            (Some(Token::Open), _) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(QueryError::UnbalancedParens)
+                }
+            },
+            (Some(Token::Close) | None, Some(op)) => Err(QueryError::MissingOperand(op)),
+            (Some(Token::Close), None) if after_open => Err(QueryError::EmptyGroup),
+            (Some(Token::Close) | None, None) => Err(QueryError::UnbalancedParens),
+            (Some(Token::And), _) => Err(QueryError::MissingOperand("AND")),
+            (Some(Token::Or), _) => Err(QueryError::MissingOperand("OR"))
+        }
+    }
+}
+
+// an empty query is an `OR` of nothing, which matches nothing
+pub fn parse(text: &str, implicit: Operator) -> Result::<Expr<'_>, QueryError> {
+    let tokens = lex(text)?;
+    if tokens.is_empty() {
+        return Ok(Expr::Or(Vec::new()))
+    }
+
+    let mut parser = Parser {tokens, at: 0, implicit};
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(..) => Err(QueryError::UnbalancedParens)
+    }
+}
+
+// `field:term` -> `(Some(field), term)`. only letters directly followed by a colon and something alphanumeric
+// make a field, so `std::io`, `http://..` or a trailing `note:` stay unscoped terms
+pub fn split_field(word: &str) -> Result::<(Option::<Field>, &str), QueryError> {
+    let Some((name, term)) = word.split_once(':') else {
+        return Ok((None, word))
+    };
+
+    let is_field = !name.is_empty() &&
+        name.chars().all(|c| c.is_ascii_alphabetic()) &&
+        term.starts_with(char::is_alphanumeric);
+
+    if !is_field {
+        return Ok((None, word))
+    }
+
+    match Field::from_name(name) {
+        Some(field) => Ok((Some(field), term)),
+        None => Err(QueryError::UnknownField(name.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_parsed() {
+        use Expr::*;
+
+        fn parse(text: &str) -> Result::<Expr<'_>, QueryError> {
+            super::parse(text, Operator::Or)
+        }
+
+        assert_eq!(parse("rust parser"), Ok(Or(vec![Word("rust"), Word("parser")])));
+        assert_eq!(parse("a OR b AND c"), Ok(Or(vec![Word("a"), And(vec![Word("b"), Word("c")])])));
+        assert_eq!(parse("a b AND c"), Ok(Or(vec![Word("a"), And(vec![Word("b"), Word("c")])])));
+        assert_eq!(parse(r#""machine learning" AND (python OR rust)"#), Ok(And(vec![
+            Phrase("machine learning"),
+            Or(vec![Word("python"), Word("rust")])
+        ])));
+        assert_eq!(parse(r#"title:x("a b")"#), Ok(Or(vec![Word("title:x"), Phrase("a b")])));
+        assert_eq!(parse("this and that"), Ok(Or(vec![Word("this"), Word("and"), Word("that")])));
+        assert_eq!(parse("  "), Ok(Or(vec![])));
+
+        assert!(parse("a OR b").unwrap().is_plain());
+        assert!(parse("(a b) c").unwrap().is_plain());
+        assert!(!parse("a AND b").unwrap().is_plain());
+        assert!(!parse(r#""a b""#).unwrap().is_plain());
+        assert_eq!(parse(r#"a "b c" AND d"#).unwrap().leaves(), [&Word("a"), &Phrase("b c"), &Word("d")]);
+
+        assert_eq!(parse("(a OR b"), Err(QueryError::UnbalancedParens));
+        assert_eq!(parse("a) b"), Err(QueryError::UnbalancedParens));
+        assert_eq!(parse(")"), Err(QueryError::UnbalancedParens));
+        assert_eq!(parse(r#"a "b c"#), Err(QueryError::UnclosedQuote));
+        assert_eq!(parse("a AND"), Err(QueryError::MissingOperand("AND")));
+        assert_eq!(parse("OR a"), Err(QueryError::MissingOperand("OR")));
+        assert_eq!(parse("a AND OR b"), Err(QueryError::MissingOperand("OR")));
+        assert_eq!(parse("()"), Err(QueryError::EmptyGroup));
+        assert_eq!(parse("a (OR b)"), Err(QueryError::MissingOperand("OR")));
+        assert_eq!(parse("a ("), Err(QueryError::UnbalancedParens));
+
+        assert_eq!(super::parse("rust parser", Operator::And), Ok(And(vec![Word("rust"), Word("parser")])));
+        assert_eq!(super::parse("a b OR c", Operator::And), Ok(Or(vec![And(vec![Word("a"), Word("b")]), Word("c")])));
+        assert_eq!(super::parse(r#"a "b c" (d OR e)"#, Operator::And), Ok(And(vec![
+            Word("a"),
+            Phrase("b c"),
+            Or(vec![Word("d"), Word("e")])
+        ])));
+        assert_eq!(super::parse("a", Operator::And), Ok(Word("a")));
+    }
+
+    #[test]
+    fn fields_are_split() {
+        assert_eq!(split_field("title:invoice"), Ok((Some(Field::Title), "invoice")));
+        assert_eq!(split_field("Body:overdue"), Ok((Some(Field::Body), "overdue")));
+        assert_eq!(split_field("path:2023"), Ok((Some(Field::Path), "2023")));
+        assert_eq!(split_field("invoice"), Ok((None, "invoice")));
+        assert_eq!(split_field("std::io"), Ok((None, "std::io")));
+        assert_eq!(split_field("http://x"), Ok((None, "http://x")));
+        assert_eq!(split_field("note:"), Ok((None, "note:")));
+        assert_eq!(split_field("author:me"), Err(QueryError::UnknownField("author".to_owned())));
+    }
+}
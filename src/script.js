@@ -10,6 +10,21 @@ MOON.addEventListener("click", () => {
     document.body.classList.toggle("dark-mode");
 });
 
+// `highlights` are byte offsets into the UTF-8 of `snippet`
+function mark(context, snippet, highlights) {
+    const bytes = new TextEncoder().encode(snippet);
+    const decoder = new TextDecoder();
+    let at = 0;
+    for (const [from, to] of highlights) {
+        context.appendChild(document.createTextNode(decoder.decode(bytes.subarray(at, from))));
+        const word = document.createElement("mark");
+        word.textContent = decoder.decode(bytes.subarray(from, to));
+        context.appendChild(word);
+        at = to;
+    }
+    context.appendChild(document.createTextNode(decoder.decode(bytes.subarray(at))));
+}
+
 async function search(prompt) {
     const results = document.getElementById("results");
     results.innerHTML = "";
@@ -21,18 +36,55 @@ async function search(prompt) {
         headers: { 'Content-Type': 'text/plain' },
         body: prompt,
     });
+    if (response.status === 503) {
+        results.appendChild(document.createTextNode(`[${await response.text()}]`));
+        return;
+    }
     const json = await response.json();
-    if (json.length === 0) {
-        results.innerHTML = "[no matches]";
+    if (json.indexing) {
+        let notice = document.createElement("div");
+        notice.classList.add("summary");
+        notice.textContent = `still indexing, ${json.progress}% so far`;
+        results.appendChild(notice);
+    }
+    for (const {word, reason} of json.discarded) {
+        let notice = document.createElement("div");
+        notice.classList.add("summary");
+        notice.textContent = `ignoring "${word}": ${reason}`;
+        results.appendChild(notice);
+    }
+    if (json.hits.length === 0) {
+        results.appendChild(document.createTextNode("[no matches]"));
+        if (json.suggestion) {
+            let suggestion = document.createElement("div");
+            suggestion.classList.add("summary");
+            suggestion.textContent = `did you mean "${json.suggestion}"?`;
+            suggestion.style.cursor = "pointer";
+            suggestion.addEventListener("click", () => {
+                QUERY.value = json.suggestion;
+                curr_prompt = curr_prompt.then(() => search(QUERY.value));
+            });
+            results.appendChild(suggestion);
+        }
         return;
     }
-    for (const [full_path, path] of json) {
+    let summary = document.createElement("div");
+    summary.classList.add("summary");
+    summary.textContent = `showing ${json.hits.length} of ${json.total}`;
+    results.appendChild(summary);
+    for (const {path, snippet, highlights} of json.hits) {
         let item = document.createElement("span");
         item.textContent = path;
+        if (snippet) {
+            let context = document.createElement("div");
+            context.classList.add("snippet");
+            mark(context, snippet, highlights || []);
+            item.appendChild(context);
+        }
 
         item.addEventListener("mouseenter", () => {
             item.style.cursor = "copy";
-            PATH_PREVIEW.textContent = full_path;
+            PATH_PREVIEW.textContent = path;
             PATH_PREVIEW.style.display = "block";
             PATH_PREVIEW.style.width = "auto";
             PATH_PREVIEW.style.height = "auto";
@@ -48,7 +100,7 @@ async function search(prompt) {
         });
 
         item.addEventListener("click", () => {
-            navigator.clipboard.writeText(full_path).catch(err => {
+            navigator.clipboard.writeText(path).catch(err => {
                 console.error('could not to copy: ', err);
             });
         });
@@ -1,37 +1,492 @@
 use std::str;
-use std::path::PathBuf;
-use std::io::{Result as IoResult, Error as IoError, ErrorKind as IoErrorKind};
+use std::iter;
+use std::fs::{self, File};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::io::{Read, Result as IoResult, Error as IoError, ErrorKind as IoErrorKind};
 
+use log::{error, info, warn};
 use tiny_http::{Server as TinyServer, Method, Header, Request, Response, StatusCode};
 
-use crate::core::Model;
+use serde_json::{json, Value};
 
-const DELIM: char = if cfg!(windows) { '\\' } else { '/' };
+use crate::core::{Analyzer, ParseConfig, SharedModel, SnippetMode};
+use crate::formats::archive::{self, Kind, SEPARATOR};
+use crate::query::{Deadline, Operator, Query};
+use crate::watch;
+
+// the value of `$result`, or the request answered with a 500 for its error, which the handler returns with
+macro_rules! or_500 {
+    ($server: expr, $request: expr, $result: expr) => {
+        match $result {
+            Ok(ok) => ok,
+            Err(err) => {
+                error!("could not answer `{url}`: {err}", url = $request.url());
+                return $server.serve_500($request, &err.to_string())
+            }
+        }
+    }
+}
+
+pub const DEFAULT_SNIPPET_LEN: usize = 200;
+pub const MIN_SNIPPET_LEN: usize = 16;
+pub const MAX_SNIPPET_LEN: usize = 1000;
+
+// how many results `/api/search` returns: the request's `limit` if it has one, otherwise the limit the server
+// was started with (`Server::with_limit`), otherwise `DEFAULT_LIMIT`. both are clamped to `1..=MAX_LIMIT`
+pub const DEFAULT_LIMIT: usize = 20;
+pub const MAX_LIMIT: usize = 1000;
+
+// how many of the best results a page of `/api/search` can start after, every page is ranked from the first
+// result on, so deeper ones only cost more without finding anything the query couldn't be narrowed down to
+pub const MAX_OFFSET: usize = 10_000;
+
+// terms listed by `/api/terms` when the request doesn't say, also clamped to `MAX_LIMIT`
+pub const DEFAULT_TERMS_LIMIT: usize = 100;
+
+// responses with more results than this are serialized a result at a time while they're being sent,
+// instead of all at once before
+pub const STREAM_RESULTS: usize = 100;
+
+pub const NDJSON: &str = "application/x-ndjson";
+
+// how long a search may score before `/api/search` answers with the best results found by then,
+// see `Server::with_timeout`
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+// set to `true` on responses whose results are partial because the search ran out of time,
+// which is how NDJSON responses say it, JSON ones have `"truncated"` in the body too
+pub const TRUNCATED_HEADER: &str = "X-Search-Truncated";
+
+// the percentage of the documents indexed so far on responses to searches made while indexing,
+// the `"indexing"` and `"progress"` of JSON responses
+pub const INDEXING_HEADER: &str = "X-Search-Indexing";
+
+// how many seconds a client is told to wait in the `Retry-After` of a search the server is unavailable for
+pub const RETRY_AFTER: u64 = 1;
+
+// how far the indexing of the documents being served has gotten, shared by whatever indexes them and the server,
+// see `Server::with_indexing`. the percentages are the ones of `Signal::Progress`, the index is only done once
+// it's `finish`ed, which is after the last of them
+#[derive(Clone, Default)]
+pub struct IndexingProgress(Arc::<AtomicU8>);
+
+impl IndexingProgress {
+    const DONE: u8 = u8::MAX;
+
+    #[inline]
+    pub fn set(&self, percentage: u8) {
+        self.0.store(percentage.min(100), Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn finish(&self) {
+        self.0.store(Self::DONE, Ordering::Relaxed)
+    }
+
+    // the percentage indexed so far, `None` once it's done
+    #[inline]
+    pub fn get(&self) -> Option::<u8> {
+        let percentage = self.0.load(Ordering::Relaxed);
+        (percentage != Self::DONE).then_some(percentage)
+    }
+}
+
+// how searches made before the index is done are answered
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum WhileIndexing {
+    // from the documents indexed so far, flagged as such
+    #[default]
+    Partial,
+    // `503 Service Unavailable` with a `Retry-After` until at least this percentage is indexed, `Partial` after that
+    Unavailable(u8)
+}
+
+impl WhileIndexing {
+    // `partial`, `unavailable` until it's all indexed or `<n>%`, unavailable until <n> percent is
+    #[inline]
+    pub fn parse(value: &str) -> Option::<Self> {
+        match value {
+            "partial" => Some(WhileIndexing::Partial),
+            "unavailable" => Some(WhileIndexing::Unavailable(100)),
+            _ => value.strip_suffix('%')?.parse().ok().filter(|percent| *percent <= 100).map(WhileIndexing::Unavailable)
+        }
+    }
+}
+
+// how the paths in the results are reported, relative to the indexed directory they are in, so that clients
+// get the same paths however the server was started and nothing about the layout of the server's file system,
+// or as absolute paths
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum PathStyle {
+    #[default]
+    Relative,
+    Absolute
+}
+
+impl PathStyle {
+    #[inline]
+    pub fn from_name(name: &str) -> Option::<Self> {
+        match name {
+            "relative" => Some(PathStyle::Relative),
+            "absolute" => Some(PathStyle::Absolute),
+            _ => None
+        }
+    }
+}
+
+// the headers of a response that either the response itself or tiny_http sets, which `Server::with_headers`
+// leaves alone, a second `Content-Type` would overwrite the one of the response and the rest would break it
+pub const RESERVED_HEADERS: &[&str] = &["Content-Type", "Content-Length", "Transfer-Encoding", "Connection", "Trailer", "Upgrade"];
+
+// `Name: value` as a header for `Server::with_headers`
+pub fn parse_header(line: &str) -> Result::<Header, String> {
+    let header = line.parse::<Header>().ok()
+        .filter(|header| !header.field.as_str().is_empty())
+        // nothing that would end the header and start another one, or the body
+        .filter(|header| !header.value.as_str().chars().any(|c| c.is_control() && c != '\t'))
+        .ok_or_else(|| format!("`{line}` is not a header, expected `Name: value`"))?;
+    if let Some(name) = RESERVED_HEADERS.iter().find(|name| header.field.equiv(name)) {
+        return Err(format!("`{name}` is set by the server itself"))
+    }
+    Ok(header)
+}
+
+// `Server::serve` listens on the unix socket at `unix:<path>` instead of a TCP address
+pub const UNIX_PREFIX: &str = "unix:";
+
+// the body of `POST /api/search`, either the query as plain text or a JSON object with `query` and the options.
+// `GET /api/search?q=..` has the plain text query in `q`
+struct SearchRequest<'b> {
+    query: Cow::<'b, str>,
+    snippet_len: usize,
+    // `first`, `densest` or `merged`
+    snippet_mode: SnippetMode,
+    exact: bool,
+    // between the bare words of the query, the model's when not set
+    operator: Option::<Operator>,
+    // what the query is stemmed with, the model's when not set
+    analyzer: Option::<Analyzer>,
+    limit: usize,
+    // how many of the best results to skip, clamped to `MAX_OFFSET`
+    offset: usize,
+    // list the query words that aren't in any document when nothing was found
+    explain: bool,
+    // the directory to search in, see `Server::scope`
+    scope: Option::<String>,
+    // of the only files to search, see `Query::extensions`
+    extensions: Vec::<String>
+}
+
+impl<'b> SearchRequest<'b> {
+    // the query with everything else left as it is by default
+    #[inline]
+    fn plain(query: Cow::<'b, str>, default_limit: usize) -> Self {
+        Self {query, snippet_len: DEFAULT_SNIPPET_LEN, snippet_mode: SnippetMode::default(), exact: false, operator: None, analyzer: None, limit: default_limit, offset: 0, explain: false, scope: None, extensions: Vec::new()}
+    }
+
+    fn parse(body: &'b str, default_limit: usize) -> Result::<Self, &'static str> {
+        let Ok(Value::Object(mut object)) = serde_json::from_str::<Value>(body) else {
+            return Ok(Self::plain(Cow::Borrowed(body), default_limit))
+        };
+
+        let Some(Value::String(query)) = object.remove("query") else {
+            return Err("`query` must be a string")
+        };
+
+        // out of range values are clamped rather than rejected
+        let snippet_len = match object.get("snippet_len") {
+            None => DEFAULT_SNIPPET_LEN,
+            Some(len) => len.as_f64().map_or(DEFAULT_SNIPPET_LEN, |len| {
+                len.clamp(MIN_SNIPPET_LEN as _, MAX_SNIPPET_LEN as _) as _
+            })
+        };
+
+        let snippet_mode = match object.get("snippet_mode") {
+            None => SnippetMode::default(),
+            Some(Value::String(name)) => SnippetMode::from_name(name).ok_or("`snippet_mode` must be `first`, `densest` or `merged`")?,
+            Some(_) => return Err("`snippet_mode` must be `first`, `densest` or `merged`")
+        };
+
+        let exact = match object.get("exact") {
+            None => false,
+            Some(Value::Bool(exact)) => *exact,
+            Some(_) => return Err("`exact` must be a boolean")
+        };
+
+        let operator = match object.get("operator") {
+            None => None,
+            Some(Value::String(name)) => Some(Operator::from_name(name).ok_or("`operator` must be `and` or `or`")?),
+            Some(_) => return Err("`operator` must be `and` or `or`")
+        };
+
+        let analyzer = match object.get("analyzer") {
+            None => None,
+            Some(Value::String(name)) => Some(Analyzer::from_name(name).ok_or("`analyzer` must be `english` or `unstemmed`")?),
+            Some(_) => return Err("`analyzer` must be `english` or `unstemmed`")
+        };
+
+        let explain = match object.get("explain") {
+            None => false,
+            Some(Value::Bool(explain)) => *explain,
+            Some(_) => return Err("`explain` must be a boolean")
+        };
+
+        let scope = match object.remove("scope") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(scope)) => Some(scope),
+            Some(_) => return Err("`scope` must be a string")
+        };
+
+        let extensions = match object.remove("extensions") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(extensions)) => extensions.into_iter()
+                .filter_map(|extension| match extension {
+                    Value::String(extension) => (!extension.is_empty()).then_some(Ok(extension)),
+                    _ => Some(Err("`extensions` must be an array of strings"))
+                })
+                .collect::<Result::<_, _>>()?,
+            Some(_) => return Err("`extensions` must be an array of strings")
+        };
+
+        let limit = match object.get("limit") {
+            None => default_limit,
+            Some(limit) => limit.as_f64().map_or(default_limit, |limit| {
+                limit.clamp(1.0, MAX_LIMIT as _) as _
+            })
+        };
+
+        let offset = match object.get("offset") {
+            None => 0,
+            Some(offset) => offset.as_f64().map_or(0, |offset| offset.clamp(0.0, MAX_OFFSET as _) as _)
+        };
+
+        Ok(Self {query: Cow::Owned(query), snippet_len, snippet_mode, exact, operator, analyzer, limit, offset, explain, scope, extensions})
+    }
+
+    // `GET /api/search?q=..`, with the `limit` and `offset` of the query string
+    fn from_url(url: &str, default_limit: usize) -> Result::<Self, &'static str> {
+        // an empty `q` is searched for like an empty body is, without a `q` there's no query at all
+        let query = query_param(url, "q").ok_or("`q` must be given")?;
+        let mut search = Self::plain(Cow::Owned(query), default_limit);
+        if let Some(limit) = query_param(url, "limit") {
+            search.limit = limit.parse::<usize>().map_err(|_| "`limit` must be a number")?.clamp(1, MAX_LIMIT)
+        }
+        if let Some(offset) = query_param(url, "offset") {
+            search.offset = offset.parse::<usize>().map_err(|_| "`offset` must be a number")?.min(MAX_OFFSET)
+        }
+        Ok(search)
+    }
+}
+
+// one result of a search, the same object in JSON and in NDJSON responses
+struct SearchHit<'s> {
+    // see `PathStyle`
+    path: String,
+    score: f32,
+    snippet: Cow::<'s, str>,
+    // the `[from, to]` bytes of the snippet's words that matched the query
+    highlights: Vec::<(usize, usize)>
+}
+
+impl SearchHit<'_> {
+    #[inline]
+    fn json(self) -> Value {
+        let SearchHit { path, score, snippet, highlights } = self;
+        json!({"path": path, "score": score, "snippet": snippet, "highlights": highlights})
+    }
+}
 
 pub struct Server<'a> {
-    model: Model<'a>,
-    full_root_path: &'a PathBuf
+    model: SharedModel<'a>,
+    full_root_path: &'a PathBuf,
+    // canonical indexed directories, nothing outside of them is ever served
+    roots: Vec::<PathBuf>,
+    // what the relative paths of each of the `roots` start with when there are several, see `labels`
+    labels: Vec::<String>,
+    // the same directories as the documents were indexed under, see `with_indexed_roots`
+    indexed_roots: Vec::<PathBuf>,
+    // results returned when a request doesn't ask for a number of them
+    limit: usize,
+    path_style: PathStyle,
+    // `None` lets every search finish
+    timeout: Option::<Duration>,
+    // added to every response, the files of the UI, the API and the errors alike
+    headers: Vec::<Header>,
+    // `None` when the model was done indexing before it was served
+    indexing: Option::<IndexingProgress>,
+    // where the files of the UI are read from on every request, see `with_assets`, the embedded ones when `None`
+    assets: Option::<PathBuf>,
+    while_indexing: WhileIndexing,
+    // how `/api/reindex` parses files, the same as the directories were indexed with
+    parse: ParseConfig
 }
 
 impl<'a> Server<'a> {
     #[inline]
-    pub fn new(model: Model<'a>, full_root_path: &'a PathBuf) -> Self {
-        Self {model, full_root_path}
+    pub fn new(model: impl Into::<SharedModel<'a>>, full_root_path: &'a PathBuf, roots: Vec::<PathBuf>) -> Self {
+        Self {model: model.into(), full_root_path, labels: labels(&roots), roots, indexed_roots: Vec::new(), limit: DEFAULT_LIMIT, path_style: PathStyle::default(), timeout: Some(DEFAULT_TIMEOUT), headers: Vec::new(), indexing: None, while_indexing: WhileIndexing::default(), assets: None, parse: ParseConfig::default()}
+    }
+
+    // the model being served, for whatever changes it while the server reads it
+    #[inline]
+    pub fn model(&self) -> SharedModel<'a> {
+        self.model.clone()
+    }
+
+    #[inline]
+    pub fn with_parse_config(mut self, parse: ParseConfig) -> Self {
+        self.parse = parse;
+        self
+    }
+
+    #[inline]
+    pub fn with_path_style(mut self, path_style: PathStyle) -> Self {
+        self.path_style = path_style;
+        self
+    }
+
+    // the indexed directories as they were given to the walk, one for each of the canonical `roots` and in
+    // their order, which the paths of the documents start with. without them the documents are taken to be
+    // indexed under the canonical paths
+    #[inline]
+    pub fn with_indexed_roots(mut self, indexed_roots: Vec::<PathBuf>) -> Self {
+        self.indexed_roots = indexed_roots;
+        self
+    }
+
+    // what the documents a search is scoped to have their paths start with, for `scope`, a directory in either
+    // of the styles of the paths the server reports. a relative one is looked for in the indexed directories in
+    // order and the first that has it is taken. the directory has to exist and be inside one of them
+    fn scope(&self, scope: &str) -> Result::<PathBuf, &'static str> {
+        let scope = Path::new(scope);
+        let candidates = if scope.is_absolute() {
+            vec![scope.to_owned()]
+        } else {
+            self.unlabeled(scope).map(|(root, rest)| root.join(rest)).into_iter()
+                .chain(self.roots.iter().map(|root| root.join(scope)))
+                .collect()
+        };
+
+        candidates.into_iter()
+            .filter_map(|candidate| candidate.canonicalize().ok().filter(|candidate| candidate.is_dir()))
+            .find_map(|candidate| self.indexed_path(&candidate))
+            .ok_or("`scope` must be a directory inside one of the indexed directories")
+    }
+
+    // the path that the canonical `path` inside one of the indexed directories has in the model
+    #[inline]
+    fn indexed_path(&self, path: &Path) -> Option::<PathBuf> {
+        self.roots.iter().enumerate().find_map(|(i, root)| {
+            let rest = path.strip_prefix(root).ok()?;
+            Some(self.indexed_roots.get(i).unwrap_or(root).join(rest))
+        })
+    }
+
+    // the path of an indexed file as it's reported in the results, see `PathStyle`. of the virtual path
+    // of an archive entry only the archive part is, the entry name is the same in either style
+    fn result_path(&self, path: &Path) -> String {
+        let full_path = self.full_root_path.join(path);
+        let full_path = match full_path.canonicalize() {
+            Ok(full_path) => full_path,
+            Err(..) => match archive::split_virtual_path(path) {
+                Some((archive, entry)) => return format!("{archive}{SEPARATOR}{entry}", archive = self.result_path(archive)),
+                None => full_path
+            }
+        };
+        match self.path_style {
+            PathStyle::Absolute => full_path.display().to_string(),
+            PathStyle::Relative => match self.roots.iter().enumerate().find_map(|(i, root)| Some((i, full_path.strip_prefix(root).ok()?))) {
+                Some((i, rest)) if !self.labels.is_empty() => Path::new(&self.labels[i]).join(rest).display().to_string(),
+                Some((_, rest)) => rest.display().to_string(),
+                None => path.file_name().map(Path::new).unwrap_or(path).display().to_string()
+            }
+        }
+    }
+
+    // for working on the UI, its files are read from `dir` on every request instead of the ones built in,
+    // `--assets src` serves them from where they're edited
+    #[inline]
+    pub fn with_assets(mut self, dir: PathBuf) -> Self {
+        self.assets = Some(dir);
+        self
+    }
+
+    // the model is still being indexed, as far as `progress` says, and searches are answered as `while_indexing`
+    // says until it's done
+    #[inline]
+    pub fn with_indexing(mut self, progress: IndexingProgress, while_indexing: WhileIndexing) -> Self {
+        self.indexing = Some(progress);
+        self.while_indexing = while_indexing;
+        self
+    }
+
+    // the percentage indexed so far while indexing, which is an `Err` when searches aren't answered yet
+    fn indexing(&self) -> Result::<Option::<u8>, u8> {
+        match (self.indexing.as_ref().and_then(IndexingProgress::get), self.while_indexing) {
+            (Some(progress), WhileIndexing::Unavailable(until)) if progress < until => Err(progress),
+            (progress, _) => Ok(progress)
+        }
+    }
+
+    #[inline]
+    pub fn with_timeout(mut self, timeout: Option::<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[inline]
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.clamp(1, MAX_LIMIT);
+        self
+    }
+
+    // headers to send with every response on top of its own (`Cache-Control`, `X-Content-Type-Options`,
+    // `Content-Security-Policy`), in this order and after the ones of the response. `RESERVED_HEADERS`
+    // are dropped, what a response is and how long it is stays up to the server
+    pub fn with_headers(mut self, headers: impl IntoIterator::<Item = Header>) -> Self {
+        self.headers.extend(headers.into_iter().filter(|header| {
+            let reserved = RESERVED_HEADERS.iter().any(|name| header.field.equiv(name));
+            if reserved { warn!("not adding `{header}` to the responses, the server sets it itself") }
+            !reserved
+        }));
+        self
     }
 
     pub fn serve(&mut self, addr: &str) -> IoResult::<()> {
-        let server = TinyServer::http(addr).map_err(|err| {
-            return IoError::new(IoErrorKind::AddrNotAvailable, format!("could not serve at `{addr}`: {err}"))
+        #[cfg(unix)]
+        let _socket;
+        let server = match addr.strip_prefix(UNIX_PREFIX) {
+            #[cfg(unix)]
+            Some(path) => {
+                _socket = SocketFile::claim(Path::new(path))?;
+                TinyServer::http_unix(Path::new(path))
+            },
+            #[cfg(not(unix))]
+            Some(_) => return Err(IoError::new(IoErrorKind::Unsupported, "unix sockets are only supported on unix")),
+            None => TinyServer::http(addr)
+        }.map_err(|err| {
+            IoError::new(IoErrorKind::AddrNotAvailable, format!("could not serve at `{addr}`: {err}"))
         })?;
 
-        println!("listening on <http://{addr}/>");
+        if addr.starts_with(UNIX_PREFIX) {
+            info!("listening on <{addr}>")
+        } else {
+            info!("listening on <http://{addr}/>")
+        }
 
         for rq in server.incoming_requests() {
-            match (rq.method(), rq.url()) {
-                (Method::Post, "/api/search") => self.serve_search(rq)?,
-                (Method::Get, "/styles.css") => serve_bytes(rq, include_bytes!("styles.css"), "text/css; charset=UTF-8")?,
-                (Method::Get, "/script.js") => serve_bytes(rq, include_bytes!("script.js"), "text/javascript; charset=UTF-8")?,
-                _ => serve_bytes(rq, include_bytes!("query.html"), "text/html; charset=UTF-8")?
+            match route(rq.method(), rq.url()) {
+                Route::Search => self.serve_search(rq)?,
+                Route::Terms => self.serve_terms(rq)?,
+                Route::Reindex => self.serve_reindex(rq)?,
+                Route::File => self.serve_file(rq)?,
+                Route::Styles => self.serve_asset(rq, "styles.css", include_bytes!("styles.css"), "text/css; charset=UTF-8")?,
+                Route::Script => self.serve_asset(rq, "script.js", include_bytes!("script.js"), "text/javascript; charset=UTF-8")?,
+                Route::Index => self.serve_asset(rq, "query.html", include_bytes!("query.html"), "text/html; charset=UTF-8")?
             }
         }
 
@@ -39,48 +494,946 @@ impl<'a> Server<'a> {
     }
 
     pub fn serve_search(&self, mut request: Request) -> IoResult::<()> {
-        let mut buf = Vec::with_capacity(request.body_length().unwrap());
-        if let Err(err) = request.as_reader().read_to_end(&mut buf) {
-            eprintln!("could not read the body of the request: {err}");
-            return serve_500(request)
+        let started = Instant::now();
+        let progress = match self.indexing() {
+            Ok(progress) => progress,
+            Err(progress) => return self.serve_503(request, &format!("still indexing, {progress}% so far"))
+        };
+
+        let mut buf = Vec::new();
+        let search = if matches!(request.method(), Method::Get | Method::Head) {
+            match SearchRequest::from_url(request.url(), self.limit) {
+                Ok(search) => search,
+                Err(err) => return self.serve_400(request, err)
+            }
+        } else {
+            // a chunked body doesn't say how long it is
+            buf.reserve(request.body_length().unwrap_or_default());
+            if let Err(err) = request.as_reader().read_to_end(&mut buf) {
+                error!("could not read the body of the request: {err}");
+                return self.serve_500(request, "could not read the body of the request")
+            }
+
+            let body = match str::from_utf8(&buf) {
+                Ok(body) => body,
+                Err(err) => {
+                    warn!("could not interpret body as UTF-8 string: {err}");
+                    return self.serve_400(request, "body must be a valid UTF-8 string")
+                }
+            };
+
+            match SearchRequest::parse(body, self.limit) {
+                Ok(search) => search,
+                Err(err) => return self.serve_400(request, err)
+            }
+        };
+
+        let scope = match search.scope.as_deref().map(|scope| self.scope(scope)).transpose() {
+            Ok(scope) => scope,
+            Err(err) => return self.serve_400(request, err)
+        };
+
+        // held until the response is written, so every part of it comes from the same state of the model
+        let model = self.model.read();
+        let deadline = self.timeout.map(Deadline::after);
+        let query = Query {
+            text: &search.query,
+            exact: search.exact,
+            operator: search.operator,
+            deadline: deadline.as_ref(),
+            analyzer: search.analyzer,
+            scope: scope.as_deref(),
+            extensions: &search.extensions
+        };
+        let (ranks, total, discarded) = match model.search_top_k(query, search.offset + search.limit).and_then(|ranks| {
+            Ok((ranks, model.total(query)?, model.discarded(query)?))
+        }) {
+            Ok(ok) => ok,
+            Err(err) => return self.serve_400(request, &err.to_string())
+        };
+        let found = !ranks.is_empty();
+        let ranks = page(ranks, search.offset);
+
+        let truncated = deadline.as_ref().is_some_and(Deadline::passed);
+        if truncated {
+            warn!("`{query}` ran out of time, answering with the best results found so far", query = search.query)
+        }
+        let truncated_header = or_500!(self, request, header(TRUNCATED_HEADER, if truncated { "true" } else { "false" }));
+        let indexing_header = or_500!(self, request, progress.map(|progress| header(INDEXING_HEADER, progress.to_string())).transpose());
+        let headers = iter::once(truncated_header).chain(indexing_header).collect::<Vec::<_>>();
+
+        let hit = |(path, score): (&PathBuf, f32)| {
+            let snippet = model.snippet_with(path, query, search.snippet_len, search.snippet_mode).unwrap_or_default();
+            let highlights = model.highlights(&snippet, query);
+            SearchHit { path: self.result_path(path), score, snippet, highlights }.json()
+        };
+
+        // one `SearchHit` per line, serialized while the response is being written.
+        // there's nowhere to put `total` and `discarded` in this format, so they are left out
+        if accepts(&request, NDJSON) {
+            let lines = ranks.into_iter().map(hit);
+            let content_type_header = or_500!(self, request, header("Content-Type", NDJSON));
+            let headers = iter::once(content_type_header).chain(headers).collect();
+            return self.respond(request, Response::new(StatusCode(200), headers, Streamed::new(ndjson(lines)), None, None))
+        }
+
+        // "did you mean", the query with its words that aren't in any document corrected, only looked for when
+        // nothing was found, `null` otherwise or when there's nothing to correct them to
+        let suggestion = if !found {
+            match model.suggest(query) {
+                Ok(suggestion) => suggestion,
+                Err(err) => return self.serve_400(request, &err.to_string())
+            }
+        } else {
+            None
+        };
+
+        // `took_ms` is how long finding the results took, snippets are made while the response is written
+        let mut json = json!({
+            "query": search.query,
+            "took_ms": started.elapsed().as_millis() as u64,
+            "total": total,
+            "offset": search.offset,
+            "truncated": truncated,
+            // whether the results are only of the documents indexed so far, and how many percent of them that is
+            "indexing": progress.is_some(),
+            "progress": progress.unwrap_or(100),
+            "discarded": discarded.iter().map(|(word, discard)| json!({"word": word, "reason": discard.as_str()})).collect::<Vec::<_>>(),
+            "suggestion": suggestion
+        });
+
+        // `"absent": [{"word": "running", "term": "run"}]`, the words that were searched as terms no document has
+        if search.explain && !found {
+            let absent = match model.absent(query) {
+                Ok(absent) => absent,
+                Err(err) => return self.serve_400(request, &err.to_string())
+            };
+            json["absent"] = absent.iter().map(|(word, term)| json!({"word": word, "term": term})).collect();
+        }
+
+        let streamed = ranks.len() > STREAM_RESULTS;
+        let hits = ranks.into_iter().map(hit);
+
+        let content_type_header = or_500!(self, request, header("Content-Type", "application/json"));
+        let headers = iter::once(content_type_header).chain(headers).collect::<Vec::<_>>();
+        if streamed {
+            let body = Streamed::new(or_500!(self, request, json_with_array(json, "hits", hits)));
+            return self.respond(request, Response::new(StatusCode(200), headers, body, None, None))
+        }
+
+        json["hits"] = hits.collect();
+        let json = or_500!(self, request, serde_json::to_string(&json));
+        let response = headers.into_iter().fold(Response::from_string(json), Response::with_header);
+        self.respond(request, response)
+    }
+
+    // `GET /api/terms?prefix=..&limit=..` -> `{"total": .., "terms": [{"term": .., "df": ..}]}`, see `Model::terms`
+    pub fn serve_terms(&self, request: Request) -> IoResult::<()> {
+        let url = request.url();
+        let prefix = query_param(url, "prefix").unwrap_or_default();
+        let limit = match query_param(url, "limit") {
+            None => DEFAULT_TERMS_LIMIT,
+            Some(limit) => match limit.parse::<usize>() {
+                Ok(limit) => limit.min(MAX_LIMIT),
+                Err(_) => return self.serve_400(request, "`limit` must be a number")
+            }
+        };
+
+        let terms = {
+            let model = self.model.read();
+            let (terms, total) = model.terms(&prefix, limit);
+            json!({
+                "total": total,
+                "terms": terms.iter().map(|(term, df)| json!({"term": term, "df": df})).collect::<Vec::<_>>()
+            })
+        };
+        let json = or_500!(self, request, serde_json::to_string(&terms));
+        let content_type_header = or_500!(self, request, header("Content-Type", "application/json"));
+        self.respond(request, Response::from_string(json).with_header(content_type_header))
+    }
+
+    // relative paths are tried against the indexed directory they're labeled with, then against every one of them,
+    // then against the directory the server runs in
+    fn resolve(&self, requested: &Path) -> IoResult::<PathBuf> {
+        self.unlabeled(requested)
+            .and_then(|(root, rest)| resolve_contained(root, &self.roots, rest).ok())
+            .or_else(|| self.roots.iter().find_map(|root| resolve_contained(root, &self.roots, requested).ok()))
+            .map_or_else(|| resolve_contained(self.full_root_path, &self.roots, requested), Ok)
+    }
+
+    // the indexed directory whose label a relative `path` starts with, and the rest of it
+    #[inline]
+    fn unlabeled<'p>(&self, path: &'p Path) -> Option::<(&PathBuf, &'p Path)> {
+        self.labels.iter().zip(&self.roots).find_map(|(label, root)| Some((root, path.strip_prefix(label).ok()?)))
+    }
+
+    // `POST /api/reindex` with the path of a file as the body: parse it again and index it in place of what it was.
+    // answers with the `{"indexed", "removed"}` documents, 404 outside of the indexed directories, 422 when unparsable
+    pub fn serve_reindex(&self, mut request: Request) -> IoResult::<()> {
+        let mut body = String::new();
+        if request.as_reader().read_to_string(&mut body).is_err() {
+            return self.serve_400(request, "body must be a valid UTF-8 string")
         }
 
-        let body = match str::from_utf8(&buf) {
-            Ok(body) => body,
+        let Some(file_path) = self.resolve(Path::new(body.trim())).ok().and_then(|file_path| self.indexed_path(&file_path)) else {
+            return self.serve_404(request)
+        };
+
+        let reindexed = watch::reindex(&mut self.model.write(), &file_path, &self.parse);
+        let applied = match reindexed {
+            Ok(applied) => applied,
             Err(err) => {
-                eprintln!("could not interpret body as UTF-8 string: {err}");
-                return serve_400(request, "body must be a valid UTF-8 string")
+                let response = Response::from_string(format!("422: could not parse {file_path:?}: {err}")).with_status_code(StatusCode(422));
+                return self.respond(request, response)
             }
         };
+        info!("reindexed {file_path:?}");
+
+        let json = json!({"indexed": applied.indexed, "removed": applied.removed}).to_string();
+        let content_type_header = or_500!(self, request, header("Content-Type", "application/json"));
+        self.respond(request, Response::from_string(json).with_header(content_type_header))
+    }
+
+    // `GET /file/<path>`, where <path> is a path as returned by `/api/search`, in either style. for the virtual
+    // path of an archive entry it's the archive that has to be in one of the indexed directories, the entry is
+    // read out of it and sent as if it were a file of its own
+    pub fn serve_file(&self, request: Request) -> IoResult::<()> {
+        let url = request.url();
+        let requested = percent_decode(&strip_query(url)[FILE_ROUTE.len()..]);
+        let requested = Path::new(&requested);
+        let (resolved, entry) = match self.resolve(requested) {
+            Err(err) if err.kind() == IoErrorKind::NotFound => match archive::split_virtual_path(requested) {
+                Some((archive, entry)) => (self.resolve(archive), Some((archive, entry))),
+                None => (Err(err), None)
+            },
+            resolved => (resolved, None)
+        };
+        let file_path = match resolved {
+            Ok(file_path) => file_path,
+            Err(err) if err.kind() == IoErrorKind::PermissionDenied => return self.serve_403(request),
+            Err(_) => return self.serve_404(request)
+        };
+
+        if let Some((archive, entry)) = entry {
+            // the kind is the one the archive was indexed as, by its name before any symlink was followed
+            let Some(kind) = Kind::of(archive) else { return self.serve_404(request) };
+            let bytes = match archive::read_entry(&file_path, kind, entry) {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == IoErrorKind::NotFound => return self.serve_404(request),
+                Err(err) => {
+                    error!("could not read `{entry}` in {file_path:?}: {err}");
+                    return self.serve_500(request, "could not read the entry")
+                }
+            };
+            let content_type_header = or_500!(self, request, header("Content-Type", content_type(Path::new(entry))));
+            return self.respond(request, Response::from_data(bytes).with_header(content_type_header))
+        }
+
+        let Ok(file) = File::open(&file_path) else {
+            return self.serve_404(request)
+        };
+
+        let content_type_header = or_500!(self, request, header("Content-Type", content_type(&file_path)));
+        self.respond(request, Response::from_file(file).with_header(content_type_header))
+    }
+
+    // every response goes out through here, with the headers of `with_headers` on top of its own
+    #[inline]
+    fn respond<R: Read>(&self, request: Request, mut response: Response::<R>) -> IoResult::<()> {
+        self.headers.iter().for_each(|header| response.add_header(header.clone()));
+        request.respond(response)
+    }
+
+    #[inline]
+    fn serve_400(&self, request: Request, message: &str) -> IoResult::<()> {
+        self.respond(request, Response::from_string(format!("400: {message}")).with_status_code(StatusCode(400)))
+    }
+
+    #[inline]
+    fn serve_403(&self, request: Request) -> IoResult::<()> {
+        self.respond(request, Response::from_string("403").with_status_code(StatusCode(403)))
+    }
+
+    #[inline]
+    fn serve_404(&self, request: Request) -> IoResult::<()> {
+        self.respond(request, Response::from_string("404").with_status_code(StatusCode(404)))
+    }
+
+    #[inline]
+    fn serve_503(&self, request: Request, message: &str) -> IoResult::<()> {
+        let retry_after_header = or_500!(self, request, header("Retry-After", RETRY_AFTER.to_string()));
+        let response = Response::from_string(format!("503: {message}")).with_status_code(StatusCode(503)).with_header(retry_after_header);
+        self.respond(request, response)
+    }
+
+    // `{"error": message}`, for whatever went wrong on the server's side, the request it was for is answered and
+    // the server goes on with the next one
+    #[inline]
+    fn serve_500(&self, request: Request, message: &str) -> IoResult::<()> {
+        let json = json!({"error": message}).to_string();
+        let response = Response::from_string(json).with_status_code(StatusCode(500));
+        match header("Content-Type", "application/json") {
+            Ok(content_type_header) => self.respond(request, response.with_header(content_type_header)),
+            Err(..) => self.respond(request, response)
+        }
+    }
+
+    // a file of the UI, `embedded` unless it's read from the directory of `with_assets`
+    fn serve_asset(&self, request: Request, name: &str, embedded: &[u8], content_type: &str) -> IoResult::<()> {
+        let Some(dir) = &self.assets else {
+            return self.serve_bytes(request, embedded, content_type)
+        };
 
-        let full = self.full_root_path.display();
-        let result = self.model.search(&body)
-            .into_iter()
-            .take(20)
-            .map(|(path, ..)| {
-                let full_file_path = format!("{full}{DELIM}{path}", path = path.display());
-                let relative_file_path = path;
-                (full_file_path, relative_file_path)
-            }).collect::<Vec<_>>();
+        let path = dir.join(name);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == IoErrorKind::NotFound => {
+                warn!("there is no {path:?} to serve");
+                return self.serve_404(request)
+            },
+            Err(err) => {
+                error!("could not read {path:?}: {err}");
+                return self.serve_500(request, &format!("could not read `{name}`"))
+            }
+        };
+        let content_type_header = or_500!(self, request, header("Content-Type", content_type));
+        let cache_control_header = or_500!(self, request, header("Cache-Control", "no-cache"));
+        self.respond(request, Response::from_data(bytes).with_header(content_type_header).with_header(cache_control_header))
+    }
 
-        let json = serde_json::to_string(&result).unwrap();
-        let content_type_header = Header::from_bytes("Content-Type", "application/json").unwrap();
-        request.respond(Response::from_string(json).with_header(content_type_header))
+    #[inline]
+    fn serve_bytes(&self, request: Request, bytes: &[u8], content_type: &str) -> IoResult::<()> {
+        let content_type_header = or_500!(self, request, header("Content-Type", content_type));
+        self.respond(request, Response::from_data(bytes).with_header(content_type_header))
     }
 }
 
+// the socket file of a unix socket server, removed once the server stops. a socket left behind by a server
+// that was killed is replaced, unlike a socket that something is still listening on or a file that isn't a socket
+#[cfg(unix)]
+struct SocketFile<'p>(&'p Path);
+
+#[cfg(unix)]
+impl<'p> SocketFile<'p> {
+    fn claim(path: &'p Path) -> IoResult::<Self> {
+        use std::os::unix::fs::FileTypeExt;
+        use std::os::unix::net::UnixStream;
+
+        match path.symlink_metadata() {
+            Ok(md) if !md.file_type().is_socket() => {
+                return Err(IoError::new(IoErrorKind::AlreadyExists, format!("{path:?} exists and is not a socket")))
+            },
+            Ok(..) if UnixStream::connect(path).is_ok() => {
+                return Err(IoError::new(IoErrorKind::AddrInUse, format!("something is already listening on {path:?}")))
+            },
+            Ok(..) => std::fs::remove_file(path)?,
+            Err(..) => {}
+        }
+
+        Ok(Self(path))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SocketFile<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(self.0)
+    }
+}
+
+// a body that's produced piece by piece as it's read, so only one piece is in memory at a time.
+// responses with it are sent chunked
+struct Streamed<I> {
+    pieces: I,
+    piece: Vec::<u8>,
+    at: usize
+}
+
+impl<I> Streamed<I> {
+    #[inline]
+    fn new(pieces: I) -> Self {
+        Self {pieces, piece: Vec::new(), at: 0}
+    }
+}
+
+impl<I: Iterator::<Item = Vec::<u8>>> Read for Streamed<I> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult::<usize> {
+        while self.at == self.piece.len() {
+            let Some(piece) = self.pieces.next() else {
+                return Ok(0)
+            };
+            self.piece = piece;
+            self.at = 0
+        }
+
+        let n = buf.len().min(self.piece.len() - self.at);
+        buf[..n].copy_from_slice(&self.piece[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+// a header of a response, `Err` when the value can't be one (it isn't ASCII), which is answered with a 500
 #[inline]
-fn serve_400(request: Request, message: &str) -> IoResult::<()> {
-    request.respond(Response::from_string(format!("400: {message}")).with_status_code(StatusCode(400)))
+fn header(field: &str, value: impl Into::<Vec::<u8>> + AsRef::<[u8]>) -> Result::<Header, String> {
+    Header::from_bytes(field, value).map_err(|()| format!("invalid value for the `{field}` header"))
 }
 
+// a value of a response that's already being sent, which is too late to answer with a 500 for it, so a value
+// that can't be serialized is sent as `null`
 #[inline]
-fn serve_500(request: Request) -> IoResult::<()> {
-    request.respond(Response::from_string("500").with_status_code(StatusCode(500)))
+fn streamed_value(value: &Value) -> Vec::<u8> {
+    serde_json::to_vec(value).unwrap_or_else(|err| {
+        error!("could not serialize a result: {err}");
+        b"null".to_vec()
+    })
 }
 
+// one JSON value per line
 #[inline]
-fn serve_bytes(request: Request, bytes: &[u8], content_type: &str) -> IoResult::<()> {
-    let content_type_header = Header::from_bytes("Content-Type", content_type).unwrap();
-    request.respond(Response::from_data(bytes).with_header(content_type_header))
+fn ndjson(values: impl Iterator::<Item = Value>) -> impl Iterator::<Item = Vec::<u8>> {
+    values.map(|value| {
+        let mut line = streamed_value(&value);
+        line.push(b'\n');
+        line
+    })
+}
+
+// the object `head` with the array of `values` under `key`, which must not be one of the keys of `head`.
+// `head` is serialized right away, `values` as they are sent
+fn json_with_array(head: Value, key: &str, values: impl Iterator::<Item = Value>) -> serde_json::Result::<impl Iterator::<Item = Vec::<u8>>> {
+    let mut open = serde_json::to_vec(&head)?;
+    open.pop();
+    if open.len() > 1 { open.push(b',') }
+    open.extend(serde_json::to_vec(key)?);
+    open.extend(b":[");
+
+    let values = values.enumerate().map(|(i, value)| {
+        let mut piece = if i > 0 { vec![b','] } else { Vec::new() };
+        piece.extend(streamed_value(&value));
+        piece
+    });
+
+    Ok(iter::once(open).chain(values).chain(iter::once(b"]}".to_vec())))
+}
+
+#[inline]
+fn accepts(request: &Request, content_type: &str) -> bool {
+    request.headers().iter()
+        .filter(|header| header.field.equiv("Accept"))
+        .any(|header| header.value.as_str().split(',').any(|accepted| {
+            accepted.split(';').next().unwrap_or_default().trim().eq_ignore_ascii_case(content_type)
+        }))
+}
+
+const FILE_ROUTE: &str = "/file/";
+
+// never let the browser interpret indexed files as html/js from our origin
+#[inline]
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("pdf") => "application/pdf",
+        _ => "text/plain; charset=UTF-8"
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Route {
+    Search,
+    Terms,
+    Reindex,
+    File,
+    Styles,
+    Script,
+    Index
+}
+
+#[inline]
+fn strip_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or_default()
+}
+
+// the ranks after the first `offset` of them, none when there aren't that many
+#[inline]
+fn page<T>(mut ranks: Vec::<T>, offset: usize) -> Vec::<T> {
+    ranks.drain(..offset.min(ranks.len()));
+    ranks
+}
+
+// the percent-decoded value of the first `name=value` in the query string of `url`, `+` stands for a space
+fn query_param(url: &str, name: &str) -> Option::<String> {
+    let query = url.split('#').next()?.split_once('?')?.1;
+    query.split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .find(|(key, _)| percent_decode(key) == name)
+        .map(|(_, value)| percent_decode(&value.replace('+', " ")))
+}
+
+// the query string and a trailing slash are ignored and the path matched case-insensitively.
+// `HEAD` is routed exactly like `GET`, tiny_http keeps the headers
+// (`Content-Length` included) and drops the body when responding to it
+fn route(method: &Method, url: &str) -> Route {
+    let path = strip_query(url);
+    let path = if path.len() > 1 { path.strip_suffix('/').unwrap_or(path) } else { path };
+    let path = path.to_ascii_lowercase();
+    match (method, path.as_str()) {
+        (Method::Get | Method::Head | Method::Post, "/api/search") => Route::Search,
+        (Method::Get | Method::Head, "/api/terms") => Route::Terms,
+        (Method::Post, "/api/reindex") => Route::Reindex,
+        (Method::Get | Method::Head, path) if path.starts_with(FILE_ROUTE) => Route::File,
+        (Method::Get | Method::Head, "/styles.css") => Route::Styles,
+        (Method::Get | Method::Head, "/script.js") => Route::Script,
+        _ => Route::Index
+    }
+}
+
+// with several indexed directories the relative paths of the results start with the name of the one they're in, so
+// that the same file name in two of them isn't reported alike: `docs/todo.md` and `notes/todo.md`. the ones whose
+// names are taken already are told apart by their position among them, `src`, `src~2`. none with a single one
+fn labels(roots: &[PathBuf]) -> Vec::<String> {
+    if roots.len() < 2 { return Vec::new() }
+    let mut labels = Vec::<String>::with_capacity(roots.len());
+    for root in roots {
+        let name = root.file_name().map_or_else(|| "root".to_owned(), |name| name.to_string_lossy().into_owned());
+        let label = (1..).map(|n| if n == 1 { name.clone() } else { format!("{name}~{n}") })
+            .find(|label| !labels.contains(label))
+            .unwrap_or(name);
+        labels.push(label)
+    }
+    labels
+}
+
+// resolve `requested` against `base`, following every symlink, and make sure that the result is still
+// inside of one of the canonical `roots`. Escapes are reported as `PermissionDenied`
+pub fn resolve_contained(base: &Path, roots: &[PathBuf], requested: &Path) -> IoResult::<PathBuf> {
+    let path = base.join(requested).canonicalize()?;
+    if roots.iter().any(|root| path.starts_with(root)) && path.is_file() {
+        Ok(path)
+    } else {
+        Err(IoError::new(IoErrorKind::PermissionDenied, format!("{requested:?} is outside of the indexed directories")))
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(byte) = iter.next() {
+        let decoded = match byte {
+            b'%' => {
+                let hex = iter.clone().take(2).collect::<Vec::<_>>();
+                str::from_utf8(&hex).ok()
+                    .filter(|hex| hex.len() == 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .inspect(|_| { iter.nth(1); })
+            },
+            _ => None
+        };
+        bytes.push(decoded.unwrap_or(byte))
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::core::{Model, WalkConfig, dir_get_contents};
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn resolve_contained_rejects_escapes() {
+        let dir = temp_dir("resolve_contained");
+        let root = dir.join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/inside.txt"), "inside").unwrap();
+        fs::write(dir.join("outside.txt"), "outside").unwrap();
+
+        let roots = [root.canonicalize().unwrap()];
+        let resolve = |requested: &str| resolve_contained(&root, &roots, Path::new(requested));
+
+        assert!(resolve("sub/inside.txt").is_ok());
+        assert!(resolve("sub/../sub/inside.txt").is_ok());
+        assert!(resolve(&root.join("sub/inside.txt").display().to_string()).is_ok());
+
+        let denied = |requested: &str| resolve(requested).unwrap_err().kind() == IoErrorKind::PermissionDenied;
+        assert!(denied("../outside.txt"));
+        assert!(denied("sub/../../outside.txt"));
+        assert!(denied(&dir.join("outside.txt").display().to_string()));
+        assert_eq!(resolve("missing.txt").unwrap_err().kind(), IoErrorKind::NotFound);
+
+        #[cfg(unix)] {
+            std::os::unix::fs::symlink(dir.join("outside.txt"), root.join("link.txt")).unwrap();
+            std::os::unix::fs::symlink(&dir, root.join("up")).unwrap();
+            assert!(denied("link.txt"));
+            assert!(denied("up/outside.txt"));
+        }
+    }
+
+    #[test]
+    fn virtual_paths_are_styled_by_their_archive() {
+        let dir = temp_dir("virtual_paths");
+        let root = dir.join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/docs.zip"), "").unwrap();
+        fs::write(root.join("sub/a.zip!b.txt"), "").unwrap();
+
+        let roots = vec![root.canonicalize().unwrap()];
+        let server = Server::new(Model::detached(), &dir, roots.clone());
+        assert_eq!(server.result_path(&root.join("sub/docs.zip!notes/wow!.txt")), "sub/docs.zip!notes/wow!.txt");
+        // a file of that name is the file, not an entry of `a.zip`
+        assert_eq!(server.result_path(&root.join("sub/a.zip!b.txt")), "sub/a.zip!b.txt");
+
+        let server = server.with_path_style(PathStyle::Absolute);
+        let absolute = format!("{root}/sub/docs.zip!notes/wow!.txt", root = roots[0].display());
+        assert_eq!(server.result_path(Path::new("root/sub/docs.zip!notes/wow!.txt")), absolute);
+    }
+
+    #[test]
+    fn searches_while_indexing_are_flagged_or_refused() {
+        let root = PathBuf::new();
+        assert_eq!(Server::new(Model::detached(), &root, Vec::new()).indexing(), Ok(None));
+
+        let progress = IndexingProgress::default();
+        let partial = Server::new(Model::detached(), &root, Vec::new()).with_indexing(progress.clone(), WhileIndexing::Partial);
+        let unavailable = Server::new(Model::detached(), &root, Vec::new()).with_indexing(progress.clone(), WhileIndexing::Unavailable(50));
+        assert_eq!((partial.indexing(), unavailable.indexing()), (Ok(Some(0)), Err(0)));
+        progress.set(49);
+        assert_eq!((partial.indexing(), unavailable.indexing()), (Ok(Some(49)), Err(49)));
+        progress.set(50);
+        assert_eq!((partial.indexing(), unavailable.indexing()), (Ok(Some(50)), Ok(Some(50))));
+        // every document is indexed before it's done
+        progress.set(100);
+        assert_eq!(partial.indexing(), Ok(Some(100)));
+        progress.finish();
+        assert_eq!((partial.indexing(), unavailable.indexing()), (Ok(None), Ok(None)));
+
+        assert_eq!(WhileIndexing::parse("partial"), Some(WhileIndexing::Partial));
+        assert_eq!(WhileIndexing::parse("unavailable"), Some(WhileIndexing::Unavailable(100)));
+        assert_eq!(WhileIndexing::parse("25%"), Some(WhileIndexing::Unavailable(25)));
+        assert_eq!(WhileIndexing::parse("101%"), None);
+        assert_eq!(WhileIndexing::parse("block"), None);
+    }
+
+    #[test]
+    fn scopes_are_resolved_to_the_indexed_paths() {
+        let dir = temp_dir("scopes");
+        fs::create_dir_all(dir.join("a/docs/2024")).unwrap();
+        fs::create_dir_all(dir.join("b/notes")).unwrap();
+        fs::write(dir.join("a/docs/2024/q1.txt"), "").unwrap();
+        fs::write(dir.join("outside.txt"), "").unwrap();
+
+        let roots = vec![dir.join("a").canonicalize().unwrap(), dir.join("b").canonicalize().unwrap()];
+        let server = Server::new(Model::detached(), &dir, roots.clone())
+            .with_indexed_roots(vec![PathBuf::from("./a"), PathBuf::from("b")]);
+        assert_eq!(server.scope("docs/2024/"), Ok(PathBuf::from("./a/docs/2024")));
+        assert_eq!(server.scope("notes"), Ok(PathBuf::from("b/notes")));
+        assert_eq!(server.scope(&roots[0].join("docs").display().to_string()), Ok(PathBuf::from("./a/docs")));
+        for scope in ["missing", "docs/2024/q1.txt", "..", "../b/../..", &dir.display().to_string()] {
+            assert!(server.scope(scope).is_err(), "{scope}")
+        }
+
+        let search = SearchRequest::parse(r#"{"query": "q", "scope": "docs"}"#, DEFAULT_LIMIT).unwrap();
+        assert_eq!(search.scope.as_deref(), Some("docs"));
+        assert!(SearchRequest::parse(r#"{"query": "q", "scope": 1}"#, DEFAULT_LIMIT).is_err());
+
+        let search = SearchRequest::parse(r#"{"query": "q", "scope": "docs", "extensions": ["pdf", "", ".md"]}"#, DEFAULT_LIMIT).unwrap();
+        assert_eq!((search.scope.as_deref(), search.extensions.as_slice()), (Some("docs"), ["pdf".to_owned(), ".md".to_owned()].as_slice()));
+        assert!(SearchRequest::parse(r#"{"query": "q"}"#, DEFAULT_LIMIT).unwrap().extensions.is_empty());
+        assert!(SearchRequest::parse(r#"{"query": "q", "extensions": "pdf"}"#, DEFAULT_LIMIT).is_err());
+        assert!(SearchRequest::parse(r#"{"query": "q", "extensions": [1]}"#, DEFAULT_LIMIT).is_err());
+    }
+
+    #[test]
+    fn extra_headers_leave_the_servers_own_alone() {
+        let header = parse_header("Cache-Control:  no-store").unwrap();
+        assert!(header.field.equiv("cache-control") && header.value.as_str() == "no-store");
+        assert!(parse_header("X-Content-Type-Options: nosniff").is_ok());
+        assert!(parse_header("no colon").is_err());
+        assert!(parse_header(": value").is_err());
+        assert!(parse_header("Bad Name: value").is_err());
+        assert!(parse_header("X-Injected: a\r\nSet-Cookie: b").is_err());
+        assert_eq!(parse_header("content-type: text/html").unwrap_err(), "`Content-Type` is set by the server itself");
+
+        let root = PathBuf::new();
+        let server = Server::new(Model::detached(), &root, Vec::new()).with_headers([
+            header,
+            Header::from_bytes("Content-Length", "0").unwrap(),
+            Header::from_bytes("Content-Security-Policy", "default-src 'self'").unwrap()
+        ]);
+        let names = server.headers.iter().map(|header| header.field.to_string()).collect::<Vec::<_>>();
+        assert_eq!(names, ["Cache-Control", "Content-Security-Policy"]);
+    }
+
+    // `server` listening on a free port in the background, and a way to send it a raw request
+    // and get its status and body back
+    fn spawn(mut server: Server<'static>) -> impl Fn(String) -> (String, String) {
+        use std::io::Write;
+        use std::net::{TcpListener, TcpStream};
+        use std::thread;
+
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        thread::spawn(move || server.serve(&format!("127.0.0.1:{port}")));
+        move |request: String| {
+            let mut stream = (0..100).find_map(|_| TcpStream::connect(("127.0.0.1", port)).map_err(|_| thread::sleep(Duration::from_millis(20))).ok()).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let (head, body) = response.split_once("\r\n\r\n").unwrap();
+            (head.split(' ').nth(1).unwrap().to_owned(), body.to_owned())
+        }
+    }
+
+    #[test]
+    fn searches_are_served_over_get_and_post() {
+        let mut model = Model::detached();
+        model.add_text("a.txt", "the quick fox");
+        model.add_text("b.txt", "a lazy dog");
+        let root = Box::leak(Box::new(PathBuf::new()));
+        let send = spawn(Server::new(model, root, Vec::new()));
+        let get = |url: &str| send(format!("GET {url} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"));
+        let paths = |body: &str| {
+            serde_json::from_str::<Value>(body).unwrap()["hits"].as_array().unwrap().iter().map(|hit| hit["path"].as_str().unwrap().to_owned()).collect::<Vec::<_>>()
+        };
+
+        let (status, body) = get("/api/search?q=quick%20fox");
+        assert_eq!((status.as_str(), paths(&body)), ("200", vec!["a.txt".to_owned()]));
+        let (status, body) = get("/API/Search/?limit=5&q=lazy+dog");
+        assert_eq!((status.as_str(), paths(&body)), ("200", vec!["b.txt".to_owned()]));
+
+        // a page past the end is empty, the total is still of every result
+        let (status, body) = get("/api/search?q=txt&offset=1&limit=1");
+        assert_eq!((status.as_str(), paths(&body).len()), ("200", 1));
+        let (status, body) = get("/api/search?q=txt&offset=2");
+        assert_eq!((status.as_str(), paths(&body)), ("200", Vec::<String>::new()));
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["total"], 2);
+        assert_eq!(get("/api/search?q=txt&offset=x").0, "400");
+
+        // an empty `q` is an empty query, a missing one isn't a query
+        let (status, empty) = get("/api/search?q=");
+        let posted = send("POST /api/search HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned());
+        assert_eq!((status, empty), posted);
+        assert_eq!(get("/api/search").0, "400");
+
+        let body = r#"{"query": "fox"}"#;
+        let (status, body) = send(format!("POST /api/search HTTP/1.1\r\nHost: localhost\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n{body}", len = body.len()));
+        assert_eq!((status.as_str(), paths(&body)), ("200", vec!["a.txt".to_owned()]));
+    }
+
+    #[test]
+    fn files_are_reindexed_when_asked_to() {
+        let dir = temp_dir("reindex");
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.txt"), "the quick fox").unwrap();
+        fs::write(root.join("b.txt"), "a lazy dog").unwrap();
+        fs::write(dir.join("outside.txt"), "a red herring").unwrap();
+
+        let (contents, _) = dir_get_contents(&[&root], &WalkConfig::default(), &ParseConfig::default()).unwrap();
+        let mut model = Model::detached();
+        model.add_contents(Box::leak(Box::new(contents)));
+        let full_root_path = Box::leak(Box::new(dir.clone()));
+        let server = Server::new(model, full_root_path, vec![root.canonicalize().unwrap()]).with_indexed_roots(vec![root.clone()]);
+        let send = spawn(server);
+        let reindex = |path: &str| send(format!("POST /api/reindex HTTP/1.1\r\nHost: localhost\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n{path}", len = path.len()));
+        let search = |q: &str| {
+            let (_, body) = send(format!("GET /api/search?q={q} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"));
+            serde_json::from_str::<Value>(&body).unwrap()["hits"].as_array().unwrap().iter().map(|hit| hit["path"].as_str().unwrap().to_owned()).collect::<Vec::<_>>()
+        };
+
+        assert_eq!(search("fox"), ["a.txt"]);
+        fs::write(root.join("a.txt"), "the slow tortoise").unwrap();
+        assert_eq!(search("tortoise"), Vec::<String>::new());
+        let (status, body) = reindex("a.txt");
+        assert_eq!((status.as_str(), serde_json::from_str::<Value>(&body).unwrap()), ("200", json!({"indexed": 1, "removed": 0})));
+        assert_eq!(search("tortoise"), ["a.txt"]);
+        assert_eq!(search("fox"), Vec::<String>::new());
+
+        assert_eq!(reindex("missing.txt").0, "404");
+        assert_eq!(reindex("../outside.txt").0, "404");
+        fs::write(root.join("b.unknown"), "a lazy dog").unwrap();
+        assert_eq!(reindex("b.unknown").0, "422");
+        assert_eq!(search("lazy"), ["b.txt"]);
+    }
+
+    #[test]
+    fn several_directories_are_searched_together() {
+        let dir = temp_dir("several_roots");
+        let (docs, notes) = (dir.join("docs"), dir.join("notes"));
+        fs::create_dir_all(&docs).unwrap();
+        fs::create_dir_all(&notes).unwrap();
+        fs::write(docs.join("todo.txt"), "kiwi in the docs").unwrap();
+        fs::write(notes.join("todo.txt"), "kiwi in the notes").unwrap();
+
+        let (contents, _) = dir_get_contents(&[&docs, &notes], &WalkConfig::default(), &ParseConfig::default()).unwrap();
+        let mut model = Model::detached();
+        model.add_contents(Box::leak(Box::new(contents)));
+        let full_root_path = Box::leak(Box::new(dir.clone()));
+        let send = spawn(Server::new(model, full_root_path, vec![docs.canonicalize().unwrap(), notes.canonicalize().unwrap()]));
+        let get = |url: &str| send(format!("GET {url} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"));
+
+        let (_, body) = get("/api/search?q=kiwi");
+        let json = serde_json::from_str::<Value>(&body).unwrap();
+        let mut paths = json["hits"].as_array().unwrap().iter().map(|hit| hit["path"].as_str().unwrap().to_owned()).collect::<Vec::<_>>();
+        paths.sort_unstable();
+        assert_eq!(paths, ["docs/todo.txt", "notes/todo.txt"]);
+        assert_eq!(get("/file/notes/todo.txt"), ("200".to_owned(), "kiwi in the notes".to_owned()));
+
+        assert_eq!(labels(&[PathBuf::from("/a/src"), PathBuf::from("/b/src"), PathBuf::from("/c/docs")]), ["src", "src~2", "docs"]);
+        assert!(labels(&[PathBuf::from("/a/src")]).is_empty());
+    }
+
+    #[test]
+    fn searches_are_answered_while_indexing() {
+        let model = SharedModel::new(Model::detached());
+        model.write().add_text("a.txt", "kiwi salad");
+        let progress = IndexingProgress::default();
+        progress.set(50);
+        let root = Box::leak(Box::new(PathBuf::new()));
+        let send = spawn(Server::new(model.clone(), root, Vec::new()).with_indexing(progress.clone(), WhileIndexing::Partial));
+        let search = || {
+            let (status, body) = send("GET /api/search?q=kiwi HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_owned());
+            assert_eq!(status, "200");
+            let json = serde_json::from_str::<Value>(&body).unwrap();
+            (json["hits"].as_array().unwrap().len(), json["indexing"].clone())
+        };
+
+        // right after the start, with some of the documents indexed
+        assert_eq!(search(), (1, json!(true)));
+
+        let mut indexed = model.write();
+        indexed.add_text("b.txt", "kiwi tart");
+        indexed.add_text("c.txt", "plum jam");
+        indexed.finish();
+        drop(indexed);
+        progress.finish();
+        assert_eq!(search(), (2, json!(false)));
+    }
+
+    #[test]
+    fn hits_are_relative_to_the_indexed_directory() {
+        let dir = temp_dir("hits");
+        let root = dir.join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/fox.txt"), "").unwrap();
+
+        let mut model = Model::detached();
+        model.add_text(root.join("sub/fox.txt"), "the quick fox");
+        let full_root_path = Box::leak(Box::new(dir.clone()));
+        let send = spawn(Server::new(model, full_root_path, vec![root.canonicalize().unwrap()]));
+        let (status, body) = send("GET /api/search?q=quick HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_owned());
+        assert_eq!(status, "200");
+
+        let mut json = serde_json::from_str::<Value>(&body).unwrap();
+        assert!(json["took_ms"].is_u64());
+        json["took_ms"] = json!(0);
+        let score = json["hits"][0]["score"].as_f64().unwrap();
+        assert!(score > 0.0);
+        assert_eq!(json, json!({
+            "query": "quick",
+            "took_ms": 0,
+            "total": 1,
+            "offset": 0,
+            "truncated": false,
+            "indexing": false,
+            "progress": 100,
+            "discarded": [],
+            "suggestion": null,
+            "hits": [{"path": "sub/fox.txt", "score": score, "snippet": "the quick fox", "highlights": [[4, 9]]}]
+        }));
+    }
+
+    #[test]
+    fn routing_normalizes_urls() {
+        assert_eq!(route(&Method::Post, "/api/search"), Route::Search);
+        assert_eq!(route(&Method::Post, "/api/search/"), Route::Search);
+        assert_eq!(route(&Method::Post, "/API/Search"), Route::Search);
+        assert_eq!(route(&Method::Post, "/api/search?v=2"), Route::Search);
+        assert_eq!(route(&Method::Get, "/script.js?v=2"), Route::Script);
+        assert_eq!(route(&Method::Get, "/Styles.CSS#top"), Route::Styles);
+        assert_eq!(route(&Method::Head, "/script.js/"), Route::Script);
+        assert_eq!(route(&Method::Get, "/FILE/Some/Path.txt"), Route::File);
+        assert_eq!(route(&Method::Get, "/api/search?q=rust+cargo"), Route::Search);
+        assert_eq!(route(&Method::Put, "/api/search"), Route::Index);
+        assert_eq!(route(&Method::Get, "/api/terms?prefix=ap&limit=5"), Route::Terms);
+        assert_eq!(route(&Method::Post, "/API/reindex/"), Route::Reindex);
+        assert_eq!(route(&Method::Get, "/api/reindex"), Route::Index);
+        assert_eq!(route(&Method::Get, "/"), Route::Index);
+        assert_eq!(route(&Method::Get, "/script.jsx"), Route::Index);
+    }
+
+    #[test]
+    fn limit_precedence() {
+        assert_eq!(SearchRequest::parse("plain query", 30).unwrap().limit, 30);
+        assert_eq!(SearchRequest::parse(r#"{"query": "q"}"#, 30).unwrap().limit, 30);
+        assert_eq!(SearchRequest::parse(r#"{"query": "q", "limit": 5}"#, 30).unwrap().limit, 5);
+        assert_eq!(SearchRequest::parse(r#"{"query": "q", "limit": 0}"#, 30).unwrap().limit, 1);
+        assert_eq!(SearchRequest::parse(r#"{"query": "q", "limit": 1e9}"#, 30).unwrap().limit, MAX_LIMIT);
+    }
+
+    #[test]
+    fn pages_start_at_the_offset() {
+        let search = SearchRequest::parse(r#"{"query": "q"}"#, DEFAULT_LIMIT).unwrap();
+        assert_eq!((search.offset, search.limit), (0, DEFAULT_LIMIT));
+        let search = SearchRequest::parse(r#"{"query": "q", "offset": 40, "limit": 20}"#, DEFAULT_LIMIT).unwrap();
+        assert_eq!((search.offset, search.limit), (40, 20));
+        let search = SearchRequest::parse(r#"{"query": "q", "offset": -1, "limit": 1e9}"#, DEFAULT_LIMIT).unwrap();
+        assert_eq!((search.offset, search.limit), (0, MAX_LIMIT));
+        assert_eq!(SearchRequest::parse(r#"{"query": "q", "offset": 1e9}"#, DEFAULT_LIMIT).unwrap().offset, MAX_OFFSET);
+
+        let search = SearchRequest::from_url("/api/search?q=fox&offset=20&limit=5000", DEFAULT_LIMIT).unwrap();
+        assert_eq!((search.offset, search.limit), (20, MAX_LIMIT));
+        assert!(SearchRequest::from_url("/api/search?q=fox&offset=next", DEFAULT_LIMIT).is_err());
+        assert!(SearchRequest::from_url("/api/search?q=fox&limit=-1", DEFAULT_LIMIT).is_err());
+
+        assert_eq!(page(vec![1, 2, 3], 0), [1, 2, 3]);
+        assert_eq!(page(vec![1, 2, 3], 2), [3]);
+        assert!(page(vec![1, 2, 3], 3).is_empty());
+        assert!(page(vec![1, 2, 3], 10).is_empty());
+    }
+
+    #[test]
+    fn headers_that_cant_be_made_are_errors() {
+        assert!(header("Content-Type", "application/json").is_ok());
+        assert!(header("Content-Type", content_type(Path::new("notes.txt"))).is_ok());
+        assert_eq!(header("X-Search-Indexing", "50 %").unwrap().value.as_str(), "50 %");
+        assert_eq!(header("X-Path", "résumé.pdf").unwrap_err(), "invalid value for the `X-Path` header");
+        assert!(header("Bad\u{e9}", "value").is_err());
+    }
+
+    #[test]
+    fn streamed_bodies() {
+        let read = |pieces: Vec::<Vec::<u8>>| {
+            let mut body = String::new();
+            Streamed::new(pieces.into_iter()).read_to_string(&mut body).unwrap();
+            body
+        };
+
+        let values = || (0..3).map(|i| json!({"i": i}));
+        assert_eq!(read(ndjson(values()).collect()), "{\"i\":0}\n{\"i\":1}\n{\"i\":2}\n");
+
+        let body = read(json_with_array(json!({"total": 3}), "results", values()).unwrap().collect());
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({"total": 3, "results": [{"i": 0}, {"i": 1}, {"i": 2}]}));
+        assert_eq!(read(json_with_array(json!({}), "results", iter::empty()).unwrap().collect()), "{\"results\":[]}");
+
+        let mut small = [0; 3];
+        let mut reader = Streamed::new(ndjson([json!("long string")].into_iter()));
+        assert_eq!(reader.read(&mut small).unwrap(), 3);
+        assert_eq!(&small, b"\"lo");
+    }
+
+    #[test]
+    fn percent_decode_works() {
+        assert_eq!(percent_decode("a%20b+c"), "a b+c");
+        assert_eq!(percent_decode("%2e%2E/x"), "../x");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+
+        assert_eq!(query_param("/api/terms?prefix=a%20b&limit=5", "prefix").as_deref(), Some("a b"));
+        assert_eq!(query_param("/api/terms?prefix=a+b", "prefix").as_deref(), Some("a b"));
+        assert_eq!(query_param("/api/terms?limit=5#prefix=x", "limit").as_deref(), Some("5"));
+        assert_eq!(query_param("/api/terms?prefix", "prefix").as_deref(), Some(""));
+        assert_eq!(query_param("/api/terms?limit=5", "prefix"), None);
+        assert_eq!(query_param("/api/terms", "prefix"), None);
+    }
 }
@@ -1,4 +1,6 @@
 // TODO: add Snowball license in here
+#![allow(clippy::all)]
+
 pub mod algorithms;
 mod among;
 mod snowball_env;
@@ -1,27 +1,250 @@
-use std::thread::sleep;
-use std::time::Duration;
-use std::sync::mpsc::Receiver;
+use std::env;
+use std::thread;
+use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::sync::mpsc::{Sender, Receiver};
+use std::io::{self, Write, IsTerminal, Result as IoResult};
 
-pub type Signal = u8;
+use log::{error, info, warn};
+use serde_json::json;
 
-pub const SIGNAL_STOP: u8 = 0;
+use crate::core::{Model, Ranks, Explained};
+use crate::query::Query;
+use crate::watch::{Indexing, apply, debounce};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Signal {
+    // percentage of the documents indexed so far
+    Progress(u8),
+    Done
+}
+
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[H";
 
 pub fn draw_percentage(rx: Receiver::<Signal>, msgs: String) {
+    println!("{msgs}");
+
+    let mut stdout = io::stdout();
+    let tty = stdout.is_terminal();
+
+    // block until the next milestone and only redraw the percentage when it has changed,
+    // in place when we're in a terminal and line by line when we're piped somewhere
     let mut percentage = None;
+    while let Ok(msg) = rx.recv() {
+        match msg {
+            Signal::Done => break,
+            Signal::Progress(perc) if percentage != Some(perc) => {
+                percentage = Some(perc);
+                if tty {
+                    print!("\r{perc}%..");
+                    _ = stdout.flush()
+                } else {
+                    println!("{perc}%..")
+                }
+            },
+            Signal::Progress(..) => {}
+        }
+    }
+
+    if tty && percentage.is_some() { println!() }
+}
+
+const RESET: &str = "\x1B[0m";
+const DIM: &str = "\x1B[2m";
+const SCORE: &str = "\x1B[32m";
+const HIGHLIGHT: &str = "\x1B[1;33m";
+
+pub struct Printer {
+    pub limit: usize,
+    pub json: bool,
+    // one JSON object per result and line instead of a single array, takes precedence over `json`
+    pub ndjson: bool,
+    pub color: bool
+}
+
+impl Printer {
+    // colors are only used when stdout is a terminal and neither `--no-color` nor `NO_COLOR` say otherwise
+    #[inline]
+    pub fn use_color(no_color: bool) -> bool {
+        !no_color &&
+        env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) &&
+        io::stdout().is_terminal()
+    }
+
+    // the results on stdout, the query words that were dropped are logged so they don't end up in piped JSON
+    pub fn print_explained(&self, explained: &Explained, query: &str) {
+        explained.discarded.iter().for_each(|(word, discard)| {
+            warn!("ignoring `{word}` in the query: {discard}", discard = discard.as_str())
+        });
+        explained.absent.iter().for_each(|(word, term)| {
+            warn!("`{word}` was searched as `{term}`, which isn't in any document")
+        });
+        if let Some(suggestion) = &explained.suggestion {
+            info!("did you mean `{suggestion}`?")
+        }
+        self.print(&explained.ranks, query)
+    }
+
+    pub fn print(&self, ranks: &Ranks, query: &str) {
+        let ranks = ranks.iter().take(self.limit);
+        if self.ndjson {
+            ranks.for_each(|(path, score)| {
+                println!("{json}", json = json!({"path": path.display().to_string(), "score": score}))
+            });
+            return
+        }
+
+        if self.json {
+            let json = ranks.map(|(path, score)| {
+                json!({"path": path.display().to_string(), "score": score})
+            }).collect::<Vec::<_>>();
+            println!("{json}", json = json!(json));
+            return
+        }
+
+        if !self.color {
+            ranks.enumerate().for_each(|(i, (path, score))| {
+                println!("{n:>3}. {score:.6}  {path}", n = i + 1, path = path.display())
+            });
+            return
+        }
+
+        let words = query.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect::<Vec::<_>>();
+
+        ranks.enumerate().for_each(|(i, (path, score))| {
+            let path = highlight(&path.display().to_string(), &words);
+            println!("{DIM}{n:>3}.{RESET} {SCORE}{score:.6}{RESET}  {path}", n = i + 1)
+        })
+    }
+}
+
+// wrap every case-insensitive occurrence of any of `words` in `HIGHLIGHT`, `words` must be lowercase
+fn highlight(text: &str, words: &[String]) -> String {
+    let lower = text.to_ascii_lowercase();
+    let mut marks = vec![false; text.len()];
+    words.iter().for_each(|word| {
+        lower.match_indices(word.as_str()).for_each(|(i, m)| marks[i..i + m.len()].fill(true))
+    });
+
+    let mut on = false;
+    let mut string = String::with_capacity(text.len());
+    text.char_indices().for_each(|(i, c)| {
+        if marks[i] != on {
+            on = marks[i];
+            string.push_str(if on { HIGHLIGHT } else { RESET });
+        }
+        string.push(c)
+    });
+    if on { string.push_str(RESET) }
+
+    string
+}
+
+// `options` is what every query is searched with, and its text is replaced by the one of `query`
+#[inline]
+fn run_query(model: &Model, printer: &Printer, query: &str, options: Query) {
+    match model.search_explained(Query { text: query, ..options }) {
+        Ok(explained) => printer.print_explained(&explained, query),
+        Err(err) => error!("{err}")
+    }
+}
+
+pub fn repl(model: &Model, printer: &Printer, options: Query) -> IoResult::<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut line = String::new();
     loop {
-        let Ok(msg) = rx.try_recv() else  {
-            print!("\x1B[2J\x1B[H");
-            println!("{msgs}");
-            if let Some(perc) = percentage {
-                println!("{perc}%..")
-            }
-            sleep(Duration::from_secs_f32(0.5));
+        print!("> ");
+        stdout.flush()?;
+
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(())
+        }
+
+        let query = line.trim();
+        if query.is_empty() {
+            print!("{CLEAR_SCREEN}");
             continue
+        }
+
+        run_query(model, printer, query, options)
+    }
+}
+
+// what the live search reacts to, changes come from `watch::Watch` and queries from `read_queries`
+pub enum Input {
+    Changed(PathBuf),
+    Query(String),
+    Eof
+}
+
+// read stdin on its own thread, so that the live search can wait on queries and changes at the same time
+pub fn read_queries(tx: Sender::<Input>) {
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let input = match stdin.read_line(&mut line) {
+                Ok(0) | Err(..) => Input::Eof,
+                Ok(..) => Input::Query(line.trim().to_owned())
+            };
+            let eof = matches!(input, Input::Eof);
+            if tx.send(input).is_err() || eof { return }
+        }
+    });
+}
+
+// keep `model` up to date with the changes coming from `rx` and re-run the last query after every batch of them.
+// with `prompt` it also takes queries like `repl` does, until EOF, otherwise it runs until the watcher goes away.
+// the progress of the initial indexing has been drawn by now, changes are logged as one line per batch instead
+pub fn live(model: &mut Model, printer: &Printer, indexing: Indexing, options: Query, rx: Receiver::<Input>, mut last: Option::<String>, prompt: bool) -> IoResult::<()> {
+    let mut stdout = io::stdout();
+    let mut pending = VecDeque::new();
+
+    if let Some(query) = &last {
+        run_query(model, printer, query, options)
+    }
+
+    loop {
+        if prompt {
+            print!("> ");
+            stdout.flush()?;
+        }
+
+        let Some(input) = pending.pop_front().or_else(|| rx.recv().ok()) else {
+            return Ok(())
         };
 
-        match msg {
-            SIGNAL_STOP => return,
-            perc @ _ => percentage = Some(perc)
+        match input {
+            Input::Changed(path) => {
+                let (paths, rest) = debounce(path, &rx, |input| match input {
+                    Input::Changed(path) => Ok(path),
+                    input => Err(input)
+                });
+                pending.extend(rest);
+
+                let applied = apply(model, paths, indexing);
+                if prompt { println!() }
+                info!("reindexed {indexed} files, removed {removed}", indexed = applied.indexed, removed = applied.removed);
+                if let Some(query) = &last {
+                    run_query(model, printer, query, options)
+                }
+            },
+            Input::Query(query) if query.is_empty() => print!("{CLEAR_SCREEN}"),
+            Input::Query(query) => {
+                run_query(model, printer, &query, options);
+                last = Some(query)
+            },
+            Input::Eof => {
+                println!();
+                return Ok(())
+            }
         }
     }
 }
@@ -0,0 +1,12 @@
+use std::fs;
+use std::env;
+use std::process;
+use std::path::PathBuf;
+
+// fresh, empty directory unique to this test run
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("search-rs-{name}-{pid}", pid = process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::thread::Scope;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+
+use log::{info, warn};
+use hashbrown::HashSet;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Result as NotifyResult, Watcher};
+use notify::event::ModifyKind;
+
+use crate::core::{Model, ParseConfig, ParseError, SharedModel, WalkConfig, parse, parse_archive};
+use crate::formats::archive::{self, Kind};
+
+// how long the file system has to be quiet before a batch of changes is applied, editors tend to
+// write, rename and touch a file several times for a single save
+pub const DEBOUNCE: Duration = Duration::from_millis(250);
+
+pub struct Watch {
+    // events stop coming as soon as this is dropped
+    _watcher: RecommendedWatcher
+}
+
+impl Watch {
+    // `on_change` gets every path that changed under one of `roots`, named as walking `roots` would name it. what
+    // `walk` prunes or ignores isn't passed on, nor are directories that didn't appear, which `apply` would walk
+    pub fn new<P, F>(roots: &[P], walk: &WalkConfig, on_change: F) -> NotifyResult::<Self>
+    where
+        P: AsRef::<Path>,
+        F: Fn(PathBuf) + Send + 'static
+    {
+        let roots = roots.iter().map(|root| {
+            let root = root.as_ref();
+            (root.canonicalize().unwrap_or_else(|_| root.to_owned()), root.to_owned(), walk.ignore(root))
+        }).collect::<Vec::<_>>();
+
+        let watched = roots.iter().map(|(canonical, ..)| canonical.clone()).collect::<Vec::<_>>();
+        let walk = walk.clone();
+        let mut watcher = notify::recommended_watcher(move |event: NotifyResult::<Event>| match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(..)) => {
+                let appeared = matches!(event.kind, EventKind::Create(..) | EventKind::Modify(ModifyKind::Name(..)));
+                event.paths.into_iter().filter(|path| appeared || !path.is_dir()).filter_map(|path| {
+                    roots.iter().find_map(|(canonical, given, ignore)| {
+                        path.strip_prefix(canonical).ok().filter(|rest| !walk.is_excluded(rest) && !ignore.is_pruned(rest)).map(|rest| given.join(rest))
+                    })
+                }).for_each(&on_change)
+            },
+            Ok(..) => {},
+            Err(err) => warn!("could not watch for changes: {err}")
+        })?;
+
+        for root in watched.iter() {
+            watcher.watch(root, RecursiveMode::Recursive)?
+        }
+
+        Ok(Self {_watcher: watcher})
+    }
+
+    // keep `model` up to date with the changes under `roots` from a thread of `scope`, for the server: every
+    // debounced batch is `apply`ed with the model to itself, searches wait for it to be done. the thread is done
+    // once the returned `Watch` is dropped
+    pub fn applying<'scope, 'a: 'scope, P>(scope: &'scope Scope<'scope, '_>, model: SharedModel<'a>, roots: &[P], indexing: Indexing<'scope>) -> NotifyResult::<Self>
+    where
+        P: AsRef::<Path>
+    {
+        let (tx, rx) = channel();
+        let watch = Self::new(roots, indexing.walk, move |path| _ = tx.send(path))?;
+        scope.spawn(move || {
+            while let Ok(path) = rx.recv() {
+                let (paths, _) = debounce(path, &rx, Ok);
+                let applied = apply(&mut model.write(), paths, indexing);
+                info!("reindexed {indexed} files, removed {removed}", indexed = applied.indexed, removed = applied.removed)
+            }
+        });
+        Ok(watch)
+    }
+}
+
+// the changed paths of `first` and of everything that comes after it until the channel is quiet for `DEBOUNCE`.
+// whatever isn't a change is handed back to be dealt with after the batch is applied
+pub fn debounce<T>(first: PathBuf, rx: &Receiver::<T>, changed: impl Fn(T) -> Result::<PathBuf, T>) -> (HashSet::<PathBuf>, Vec::<T>) {
+    let mut paths = HashSet::from_iter([first]);
+    let mut rest = Vec::new();
+    loop {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(msg) => match changed(msg) {
+                Ok(path) => _ = paths.insert(path),
+                Err(msg) => rest.push(msg)
+            },
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return (paths, rest)
+        }
+    }
+}
+
+// how `apply` finds and parses files, the same as the directories were indexed with
+#[derive(Clone, Copy)]
+pub struct Indexing<'c> {
+    pub walk: &'c WalkConfig,
+    pub parse: &'c ParseConfig
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Applied {
+    pub indexed: usize,
+    pub removed: usize
+}
+
+// bring `model` up to date with `paths`: files are (re)indexed, directories that appeared are walked, and what's gone
+// or can't be parsed anymore is removed, with every document under it
+pub fn apply<'a>(model: &mut Model<'a>, paths: HashSet::<PathBuf>, indexing: Indexing) -> Applied {
+    let mut applied = Applied::default();
+    for path in paths {
+        if path.is_dir() {
+            indexing.walk.walk(&path).filter_map(Result::ok).for_each(|file_path| index(model, file_path, indexing.parse, &mut applied));
+        } else if path.is_file() {
+            index(model, path, indexing.parse, &mut applied)
+        } else {
+            let gone = model.docs.keys().filter(|doc_path| {
+                doc_path.starts_with(&path) || archive::split_virtual_path(doc_path).is_some_and(|(archive, _)| archive.starts_with(&path))
+            }).map(|doc_path| doc_path.to_path_buf()).collect::<Vec::<_>>();
+            applied.removed += gone.len();
+            gone.iter().for_each(|doc_path| model.rm_document(doc_path));
+        }
+    }
+    applied
+}
+
+#[inline]
+fn index(model: &mut Model, file_path: PathBuf, config: &ParseConfig, applied: &mut Applied) {
+    match reindex(model, &file_path, config) {
+        Ok(reindexed) => {
+            applied.indexed += reindexed.indexed;
+            applied.removed += reindexed.removed
+        },
+        // whatever it was indexed as, the file itself or the entries of an archive
+        Err(..) => {
+            let gone = model.docs.keys().filter(|doc_path| {
+                ***doc_path == file_path || archive::split_virtual_path(doc_path).is_some_and(|(archive, _)| archive == file_path)
+            }).map(|doc_path| doc_path.to_path_buf()).collect::<Vec::<_>>();
+            applied.removed += gone.len();
+            gone.iter().for_each(|doc_path| model.rm_document(doc_path))
+        }
+    }
+}
+
+// parse `file_path`, an archive entry by entry, and index it in place of what it was indexed as before, the
+// entries that aren't in the archive anymore are removed. nothing is changed when it can't be parsed
+pub fn reindex(model: &mut Model, file_path: &Path, config: &ParseConfig) -> Result::<Applied, ParseError> {
+    let mut applied = Applied::default();
+    if let Some(kind) = Kind::of(file_path) {
+        let (texts, _) = parse_archive(file_path, kind, config)?;
+        // entries that aren't in the archive anymore
+        let gone = model.docs.keys().filter(|doc_path| {
+            archive::split_virtual_path(doc_path).is_some_and(|(archive, _)| archive == file_path) &&
+                !texts.iter().any(|(entry_path, _)| *entry_path == ***doc_path)
+        }).map(|doc_path| doc_path.to_path_buf()).collect::<Vec::<_>>();
+        applied.removed += gone.len();
+        gone.iter().for_each(|doc_path| model.rm_document(doc_path));
+
+        applied.indexed += texts.len();
+        texts.into_iter().for_each(|(entry_path, text)| model.add_text(entry_path, text));
+        return Ok(applied)
+    }
+
+    let (text, _) = parse(file_path, config)?;
+    model.add_text(file_path, text);
+    applied.indexed += 1;
+    Ok(applied)
+}
+
+#[cfg(all(test, feature = "watch_tests"))]
+mod tests {
+    use super::*;
+    use std::{fs, thread};
+    use std::time::Instant;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn changes_are_applied_to_a_shared_model() {
+        let root = temp_dir("watch").join("docs");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("a.txt"), "apples").unwrap();
+        let model = SharedModel::new(Model::detached());
+        model.write().add_text(root.join("a.txt"), "apples");
+
+        let (walk, parse) = (WalkConfig::default(), ParseConfig::default());
+        let found = |query: &str| model.read().search(query).unwrap().len();
+        thread::scope(|scope| {
+            let watch = Watch::applying(scope, model.clone(), &[&root], Indexing { walk: &walk, parse: &parse }).unwrap();
+            fs::write(root.join("b.txt"), "bananas").unwrap();
+            fs::write(root.join("c.unknown"), "bananas").unwrap();
+            fs::remove_file(root.join("a.txt")).unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while (found("banana") == 0 || found("apple") > 0) && Instant::now() < deadline {
+                thread::sleep(DEBOUNCE)
+            }
+            assert_eq!((found("banana"), found("apple")), (1, 0));
+            drop(watch)
+        });
+    }
+}